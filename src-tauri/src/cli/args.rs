@@ -0,0 +1,451 @@
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use std::path::PathBuf;
+
+use lib::{
+    domain::{
+        self, AudioCodec, ConflictPolicy, Container, HdrMode, MetadataMode, Preset,
+        StreamSelection, StreamSelector, SubtitleBurnIn, TrimRange, VideoCodec,
+    },
+    naming::{NameTemplate, DEFAULT_NAME_TEMPLATE},
+};
+
+use crate::OutputFormat;
+
+/// Compress videos with the bundled ffmpeg.
+///
+/// Run without arguments to start the interactive wizard.
+#[derive(Parser, Debug)]
+#[command(
+    name = "compresso-cli",
+    version,
+    about,
+    args_conflicts_with_subcommands = true,
+    subcommand_negates_reqs = true
+)]
+pub struct Cli {
+    #[command(subcommand)]
+    pub command: Option<Commands>,
+
+    /// Input video files, folders or glob patterns (e.g. "clips/*.mov")
+    #[arg(required = true, value_name = "INPUT")]
+    pub inputs: Vec<String>,
+
+    /// Folder where compressed videos are written
+    #[arg(short, long, value_name = "DIR", required = true)]
+    pub output_dir: Option<PathBuf>,
+
+    #[command(flatten)]
+    pub encode: EncodeArgs,
+
+    /// Output file name, using {stem} {ext} {date} {width} {height} {quality} {preset} {codec} {index} {parent}
+    #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_NAME_TEMPLATE, value_parser = NameTemplate::parse)]
+    pub name_template: NameTemplate,
+
+    /// What to do when the output file already exists
+    #[arg(long, value_enum, default_value = "auto-rename")]
+    pub on_conflict: ConflictArg,
+
+    /// Scan input folders recursively
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Recreate the subfolders of input folders under the output folder
+    #[arg(long)]
+    pub mirror_tree: bool,
+
+    /// Number of files converted at the same time
+    #[arg(short, long, value_name = "N", default_value = "1", value_parser = clap::value_parser!(u16).range(1..))]
+    pub jobs: u16,
+
+    /// Re-encode inputs even when the output folder's manifest shows them already converted
+    #[arg(long)]
+    pub force: bool,
+
+    /// Write a JSON report of every input (status, sizes, timings, ffmpeg arguments) to this file
+    #[arg(long, value_name = "FILE")]
+    pub report: Option<PathBuf>,
+
+    /// Print the JSON report to stdout; progress and messages go to stderr
+    #[arg(long)]
+    pub json: bool,
+
+    /// Answer yes to every confirmation (create output folder, start conversion)
+    #[arg(short, long)]
+    pub yes: bool,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Commands {
+    /// Show container, stream and chapter details of a video
+    Probe {
+        /// Video file to inspect
+        file: PathBuf,
+
+        /// Print the full probe result as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// List the codecs, containers and filters the ffmpeg binary supports
+    Capabilities {
+        /// Print every encoder, muxer and filter as JSON
+        #[arg(long)]
+        json: bool,
+    },
+    /// Compress videos as they appear in a folder, until interrupted
+    Watch(Box<WatchArgs>),
+}
+
+/// Encoding settings shared by batch conversion and `watch`.
+#[derive(Args, Debug)]
+pub struct EncodeArgs {
+    /// Saved profile to start from; other encoding flags override its values
+    #[arg(long, value_name = "NAME")]
+    pub profile: Option<String>,
+
+    /// Profiles file to read --profile from instead of the app's
+    #[arg(long, value_name = "FILE", requires = "profile")]
+    pub profiles_file: Option<PathBuf>,
+
+    /// Output container, or "same" to keep the source extension [default: same]
+    #[arg(short, long, value_parser = parse_output_format)]
+    pub format: Option<OutputFormat>,
+
+    /// Video codec: h264, h265, av1 or vp9. Defaults to vp9 for webm, h264 otherwise
+    #[arg(short, long, value_parser = parse_video_codec)]
+    pub codec: Option<VideoCodec>,
+
+    /// Encoding preset [default: ironclad]
+    #[arg(short, long, value_enum)]
+    pub preset: Option<PresetArg>,
+
+    /// Quality between 0 and 100, higher is better [default: 70]
+    #[arg(short, long, value_parser = clap::value_parser!(u16).range(0..=100))]
+    pub quality: Option<u16>,
+
+    /// Encode in two passes to fit this size in MB, overrides --quality
+    #[arg(short, long, value_name = "MB", value_parser = parse_target_size)]
+    pub target_size: Option<f64>,
+
+    /// Output frame rate, keeps the original when omitted
+    #[arg(long, value_parser = clap::value_parser!(u32).range(1..))]
+    pub fps: Option<u32>,
+
+    /// Output dimensions as WIDTHxHEIGHT, e.g. 1280x720
+    #[arg(long, value_name = "WIDTHxHEIGHT", value_parser = parse_size)]
+    pub size: Option<(u32, u32)>,
+
+    /// Remove the audio track
+    #[arg(long)]
+    pub mute: bool,
+
+    /// Audio codec: copy, aac or opus. Defaults to ffmpeg's choice for the format
+    #[arg(long, value_parser = parse_audio_codec)]
+    pub audio_codec: Option<AudioCodec>,
+
+    /// Audio bitrate in kbps, e.g. 96
+    #[arg(long, value_name = "KBPS", value_parser = clap::value_parser!(u32).range(6..=512))]
+    pub audio_bitrate: Option<u32>,
+
+    /// Number of audio channels, 1 downmixes to mono
+    #[arg(long, value_name = "N", value_parser = clap::value_parser!(u8).range(1..=8))]
+    pub audio_channels: Option<u8>,
+
+    /// Audio sample rate in Hz, e.g. 48000
+    #[arg(long, value_name = "HZ", value_parser = clap::value_parser!(u32).range(8000..=192000))]
+    pub sample_rate: Option<u32>,
+
+    /// Normalize loudness to EBU R128 (-16 LUFS) after measuring it in a first pass
+    #[arg(long)]
+    pub normalize_audio: bool,
+
+    /// Audio streams to keep: "all", or stream numbers (from 0) and languages, e.g. 0,2 or
+    /// eng,jpn. Defaults to ffmpeg's pick of one stream
+    #[arg(long, value_name = "STREAMS", value_parser = parse_stream_selection)]
+    pub audio_streams: Option<StreamSelection>,
+
+    /// Mix the kept audio streams into one
+    #[arg(long)]
+    pub mix_audio: bool,
+
+    /// Subtitle streams to keep, in the form of --audio-streams
+    #[arg(long, value_name = "STREAMS", value_parser = parse_stream_selection)]
+    pub subtitle_streams: Option<StreamSelection>,
+
+    /// Add a .srt, .vtt, .ass or .ssa file as a subtitle stream of every output; repeat to add
+    /// several
+    #[arg(long, value_name = "FILE", value_parser = parse_subtitle_file)]
+    pub subtitle_file: Vec<String>,
+
+    /// Burn a subtitle into the picture: a subtitle stream number (from 0) or a subtitle file
+    #[arg(long, value_name = "STREAM|FILE", value_parser = parse_burn_in)]
+    pub burn_subtitles: Option<SubtitleBurnIn>,
+
+    /// Keep only these parts of each video, joined in order, e.g. 0:10-0:45,1:20-2:00.
+    /// A range without an end (1:20-) keeps the rest of the video
+    #[arg(long, value_name = "START-END", value_delimiter = ',', value_parser = parse_trim_range)]
+    pub trim: Vec<TrimRange>,
+
+    /// Keep all source metadata and chapters, or strip it, including GPS and device tags.
+    /// Defaults to ffmpeg's copy of the global tags
+    #[arg(long, value_enum)]
+    pub metadata: Option<MetadataArg>,
+
+    /// Give each output the modification and access times of its source
+    #[arg(long)]
+    pub keep_file_times: bool,
+
+    /// Encoding of HDR videos: tone map to SDR, or keep 10-bit HDR with h265, av1 or vp9
+    /// [default: tonemap]
+    #[arg(long, value_enum)]
+    pub hdr: Option<HdrArg>,
+}
+
+#[derive(Args, Debug)]
+pub struct WatchArgs {
+    /// Folder to watch for new videos
+    #[arg(value_name = "DIR")]
+    pub dir: PathBuf,
+
+    /// Folder where compressed videos are written
+    #[arg(short, long, value_name = "DIR")]
+    pub output_dir: PathBuf,
+
+    #[command(flatten)]
+    pub encode: EncodeArgs,
+
+    /// Output file name, using {stem} {ext} {date} {width} {height} {quality} {preset} {codec} {index} {parent}
+    #[arg(long, value_name = "TEMPLATE", default_value = DEFAULT_NAME_TEMPLATE, value_parser = NameTemplate::parse)]
+    pub name_template: NameTemplate,
+
+    /// What to do when the output file already exists
+    #[arg(long, value_enum, default_value = "auto-rename")]
+    pub on_conflict: ConflictArg,
+
+    /// Also watch subfolders
+    #[arg(short, long)]
+    pub recursive: bool,
+
+    /// Recreate the subfolders of the watched folder under the output folder
+    #[arg(long)]
+    pub mirror_tree: bool,
+
+    /// Seconds a new file must stop growing before it is compressed
+    #[arg(long, value_name = "SECONDS", default_value = "5", value_parser = clap::value_parser!(u64).range(1..))]
+    pub settle: u64,
+
+    /// Move each original into this folder once it is compressed
+    #[arg(long, value_name = "DIR", conflicts_with = "delete_originals")]
+    pub move_originals: Option<PathBuf>,
+
+    /// Delete each original once it is compressed
+    #[arg(long)]
+    pub delete_originals: bool,
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum PresetArg {
+    /// Slow, compression-focused encode
+    #[value(alias = "default")]
+    Ironclad,
+    /// Fast encode with fewer tuning flags
+    Thunderbolt,
+}
+
+impl From<PresetArg> for Preset {
+    fn from(value: PresetArg) -> Self {
+        match value {
+            PresetArg::Ironclad => Preset::Ironclad,
+            PresetArg::Thunderbolt => Preset::Thunderbolt,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum MetadataArg {
+    /// Copy all metadata, stream tags and chapters of the source
+    Preserve,
+    /// Remove all metadata, stream tags and chapters
+    Strip,
+}
+
+impl From<MetadataArg> for MetadataMode {
+    fn from(value: MetadataArg) -> Self {
+        match value {
+            MetadataArg::Preserve => MetadataMode::Preserve,
+            MetadataArg::Strip => MetadataMode::Strip,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum HdrArg {
+    /// Convert to SDR BT.709, which every player shows correctly
+    Tonemap,
+    /// Keep 10-bit HDR and its color tags
+    Preserve,
+}
+
+impl From<HdrArg> for HdrMode {
+    fn from(value: HdrArg) -> Self {
+        match value {
+            HdrArg::Tonemap => HdrMode::Tonemap,
+            HdrArg::Preserve => HdrMode::Preserve,
+        }
+    }
+}
+
+#[derive(ValueEnum, Clone, Copy, Debug)]
+pub enum ConflictArg {
+    Overwrite,
+    Skip,
+    AutoRename,
+}
+
+impl From<ConflictArg> for ConflictPolicy {
+    fn from(value: ConflictArg) -> Self {
+        match value {
+            ConflictArg::Overwrite => ConflictPolicy::Overwrite,
+            ConflictArg::Skip => ConflictPolicy::Skip,
+            ConflictArg::AutoRename => ConflictPolicy::AutoRename,
+        }
+    }
+}
+
+fn parse_output_format(value: &str) -> Result<OutputFormat, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("same") {
+        return Ok(OutputFormat::SameAsSource);
+    }
+    Container::from_extension(value)
+        .map(OutputFormat::Fixed)
+        .ok_or_else(|| {
+            let extensions: Vec<&str> = Container::ALL.iter().map(|c| c.extension()).collect();
+            format!("expected one of: same, {}", extensions.join(", "))
+        })
+}
+
+fn parse_video_codec(value: &str) -> Result<VideoCodec, String> {
+    VideoCodec::from_name(value.trim()).ok_or_else(|| {
+        let codecs: Vec<&str> = VideoCodec::ALL.iter().map(|c| c.as_ref()).collect();
+        format!("expected one of: {}", codecs.join(", "))
+    })
+}
+
+fn parse_audio_codec(value: &str) -> Result<AudioCodec, String> {
+    AudioCodec::from_name(value.trim()).ok_or_else(|| {
+        let codecs: Vec<&str> = AudioCodec::ALL.iter().map(|c| c.as_ref()).collect();
+        format!("expected one of: {}", codecs.join(", "))
+    })
+}
+
+/// Parses `all`, or a comma separated list of stream numbers and languages.
+pub fn parse_stream_selection(value: &str) -> Result<StreamSelection, String> {
+    let value = value.trim();
+    if value.eq_ignore_ascii_case("all") {
+        return Ok(StreamSelection::All);
+    }
+    let selectors = value
+        .split(',')
+        .map(|part| {
+            let part = part.trim();
+            if let Ok(index) = part.parse::<u32>() {
+                Ok(StreamSelector::Index(index))
+            } else if !part.is_empty() && part.chars().all(|c| c.is_ascii_alphabetic()) {
+                Ok(StreamSelector::Language(part.to_string()))
+            } else {
+                Err(format!(
+                    "expected all, stream numbers or languages, e.g. 0,2 or eng: {part}"
+                ))
+            }
+        })
+        .collect::<Result<Vec<StreamSelector>, String>>()?;
+    Ok(StreamSelection::Pick(selectors))
+}
+
+fn parse_subtitle_file(value: &str) -> Result<String, String> {
+    if !domain::is_subtitle_file(value) {
+        return Err(String::from("expected a .srt, .vtt, .ass or .ssa file"));
+    }
+    Ok(value.to_string())
+}
+
+/// Parses a subtitle stream number or a subtitle file.
+pub fn parse_burn_in(value: &str) -> Result<SubtitleBurnIn, String> {
+    match value.trim().parse::<u32>() {
+        Ok(position) => Ok(SubtitleBurnIn::Stream(position)),
+        Err(_) => parse_subtitle_file(value)
+            .map(SubtitleBurnIn::File)
+            .map_err(|_| String::from("expected a subtitle stream number or a subtitle file")),
+    }
+}
+
+fn parse_target_size(value: &str) -> Result<f64, String> {
+    let size = value
+        .trim()
+        .parse::<f64>()
+        .map_err(|_| format!("invalid size: {value}"))?;
+    if !size.is_finite() || size <= 0.0 {
+        return Err(String::from("target size must be greater than 0"));
+    }
+    Ok(size)
+}
+
+fn parse_size(value: &str) -> Result<(u32, u32), String> {
+    let (width, height) = value
+        .trim()
+        .split_once(['x', 'X'])
+        .ok_or_else(|| String::from("expected WIDTHxHEIGHT, e.g. 1280x720"))?;
+    let width = width
+        .parse::<u32>()
+        .map_err(|_| format!("invalid width: {width}"))?;
+    let height = height
+        .parse::<u32>()
+        .map_err(|_| format!("invalid height: {height}"))?;
+    if width == 0 || height == 0 {
+        return Err(String::from("width and height must be greater than 0"));
+    }
+    Ok((width, height))
+}
+
+/// Parses `START-END` where both are `[[HH:]MM:]SS[.fff]`. An empty start is
+/// the beginning of the video, an empty end the end of it.
+pub fn parse_trim_range(value: &str) -> Result<TrimRange, String> {
+    let (start, end) = value
+        .trim()
+        .split_once('-')
+        .ok_or_else(|| String::from("expected START-END, e.g. 0:10-0:45"))?;
+    let start_seconds = if start.trim().is_empty() {
+        0.0
+    } else {
+        parse_timestamp(start)?
+    };
+    let end_seconds = if end.trim().is_empty() {
+        None
+    } else {
+        Some(parse_timestamp(end)?)
+    };
+    if let Some(end_seconds) = end_seconds {
+        if end_seconds <= start_seconds {
+            return Err(format!("range {value} must end after it starts"));
+        }
+    }
+    Ok(TrimRange {
+        start_seconds,
+        end_seconds,
+    })
+}
+
+fn parse_timestamp(value: &str) -> Result<f64, String> {
+    let parts: Vec<&str> = value.trim().split(':').collect();
+    if parts.len() > 3 {
+        return Err(format!("invalid time: {value}"));
+    }
+    parts.iter().try_fold(0.0, |seconds, part| {
+        let part = part
+            .parse::<f64>()
+            .ok()
+            .filter(|part| part.is_finite() && *part >= 0.0)
+            .ok_or_else(|| format!("invalid time: {value}"))?;
+        Ok(seconds * 60.0 + part)
+    })
+}
@@ -0,0 +1,56 @@
+use lib::domain::{FfmpegCapabilities, StreamKind, VideoCodec};
+use std::path::Path;
+
+pub fn run_capabilities(ffmpeg_path: &Path, json: bool) -> Result<(), String> {
+    let capabilities = lib::capabilities::detect(ffmpeg_path)?;
+
+    if json {
+        let output =
+            serde_json::to_string_pretty(capabilities.as_ref()).map_err(|e| e.to_string())?;
+        println!("{output}");
+    } else {
+        print_capabilities(ffmpeg_path, &capabilities);
+    }
+
+    Ok(())
+}
+
+fn print_capabilities(ffmpeg_path: &Path, capabilities: &FfmpegCapabilities) {
+    println!("{}", ffmpeg_path.display());
+
+    println!("\nVideo codecs");
+    for codec in VideoCodec::ALL {
+        match capabilities.video_encoder_for(codec) {
+            Some(encoder) => println!("  {:<5} yes ({encoder})", codec.as_ref()),
+            None => println!(
+                "  {:<5} no (needs {})",
+                codec.as_ref(),
+                codec.encoders().join(" or ")
+            ),
+        }
+    }
+
+    let containers: Vec<&str> = capabilities
+        .containers
+        .iter()
+        .map(|container| container.extension())
+        .collect();
+    println!("\nContainers: {}", containers.join(", "));
+
+    let count = |kind: StreamKind| {
+        capabilities
+            .encoders
+            .iter()
+            .filter(|encoder| encoder.kind == kind)
+            .count()
+    };
+    println!(
+        "Encoders: {} video, {} audio, {} subtitle",
+        count(StreamKind::Video),
+        count(StreamKind::Audio),
+        count(StreamKind::Subtitle)
+    );
+    println!("Muxers: {}", capabilities.muxers.len());
+    println!("Filters: {}", capabilities.filters.len());
+    println!("\nUse --json for the full lists.");
+}
@@ -1,49 +1,122 @@
+/// `println!` that moves to stderr while stdout carries the JSON report.
+macro_rules! status {
+    ($($arg:tt)*) => {
+        if $crate::STDOUT_IS_REPORT.load(std::sync::atomic::Ordering::Relaxed) {
+            eprintln!($($arg)*)
+        } else {
+            println!($($arg)*)
+        }
+    };
+}
+
+mod args;
+mod capabilities;
+mod manifest;
+mod probe;
+mod report;
+mod watch;
+
+use args::{Cli, Commands, EncodeArgs};
+use clap::Parser;
 use dialoguer::{theme::ColorfulTheme, Confirm, Input, MultiSelect, Select};
-use indicatif::{ProgressBar, ProgressStyle};
-use regex::Regex;
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use lib::{
+    diagnostics::{self, StderrTail},
+    domain::{
+        self, AudioCodec, AudioOptions, ConflictPolicy, Container, EncodeOptions, EncodeProfile,
+        EncodeProgress, FfmpegCapabilities, FfmpegError, HdrFormat, HdrMode, MetadataMode,
+        MetadataOptions, Preset, StreamKind, StreamOptions, StreamSelection, StreamSelector,
+        SubtitleBurnIn, SubtitleOptions, TrimRange, VideoCodec, VideoTransform,
+    },
+    encoder::{self, EncodeRequest, EXTENSIONS},
+    loudness::{self, LoudnessMeasurement},
+    naming::{self, NameContext, NameTemplate},
+    profiles,
+    progress::{ProgressParser, ProgressTracker},
+};
+use manifest::{Manifest, ManifestCheck};
+use nanoid::nanoid;
+use report::{BatchReport, FileReport};
 use std::{
+    collections::HashSet,
     env,
     ffi::OsStr,
     fs,
     io::{BufRead, BufReader},
     path::{Path, PathBuf},
     process::{Command, Stdio},
+    sync::{
+        atomic::{AtomicBool, AtomicUsize, Ordering},
+        Arc, Mutex,
+    },
     thread,
-    time::Duration,
+    time::{Duration, Instant},
 };
 use walkdir::WalkDir;
 
-const EXTENSIONS: [&str; 5] = ["mp4", "mov", "webm", "avi", "mkv"];
+/// Bar length for encodes with a known duration, 100% in hundredths.
+const PROGRESS_BAR_LENGTH: u64 = 10_000;
 
-#[derive(Clone, Copy, Debug)]
-enum ConflictPolicy {
-    Overwrite,
-    Skip,
-    AutoRename,
-}
+/// Used when neither `--quality` nor the profile sets one.
+const DEFAULT_QUALITY: u16 = 70;
+
+/// Set by `--json`, which keeps stdout for the report.
+static STDOUT_IS_REPORT: AtomicBool = AtomicBool::new(false);
 
 #[derive(Clone, Debug)]
 struct Settings {
     output_dir: PathBuf,
     output_format: OutputFormat,
+    /// `None` uses each output container's default codec.
+    video_codec: Option<VideoCodec>,
     preset: Preset,
     quality: u16,
-    fps: Option<u32>,
+    target_size_mb: Option<f64>,
+    fps: Option<f32>,
     dimensions: Option<(u32, u32)>,
     mute_audio: bool,
+    audio: AudioOptions,
+    streams: StreamOptions,
+    /// Subtitle files added to and burned into every output.
+    subtitles: SubtitleOptions,
+    metadata: MetadataOptions,
+    hdr: HdrMode,
+    /// Parts of each video to keep, empty keeps everything.
+    trim: Vec<TrimRange>,
     conflict_policy: ConflictPolicy,
+    name_template: NameTemplate,
+    /// Recreate the input subfolders under `output_dir`.
+    mirror_tree: bool,
+    /// Number of files converted at the same time.
+    jobs: usize,
+    /// Re-encode inputs the output folder's manifest lists as done.
+    force: bool,
+    /// Saved profile the settings started from.
+    profile_name: Option<String>,
+    /// `None` when the ffmpeg build could not be inspected.
+    capabilities: Option<Arc<FfmpegCapabilities>>,
 }
 
-#[derive(Clone, Copy, Debug)]
-enum OutputFormat {
-    SameAsSource,
-    Fixed(&'static str),
+/// A video to convert. `relative_dir` is the folder it was found in,
+/// relative to the scanned input folder; empty for files given directly.
+#[derive(Clone, Debug)]
+struct InputFile {
+    path: PathBuf,
+    relative_dir: PathBuf,
+}
+
+/// Binaries conversions run: ffmpeg encodes, ffprobe reads the inputs and
+/// checks the outputs.
+#[derive(Clone, Debug)]
+struct Tools {
+    ffmpeg: PathBuf,
+    ffprobe: PathBuf,
 }
 
 #[derive(Clone, Copy, Debug)]
-enum Preset {
-    Default,
-    Thunderbolt,
+enum OutputFormat {
+    SameAsSource,
+    Fixed(Container),
 }
 
 fn main() {
@@ -54,10 +127,267 @@ fn main() {
 }
 
 fn run() -> Result<(), String> {
-    let theme = ColorfulTheme::default();
+    // Parse before locating ffmpeg so `--help` and argument errors work without it.
+    let cli = if env::args_os().len() > 1 {
+        Some(Cli::parse())
+    } else {
+        None
+    };
+
+    if let Some(Some(Commands::Probe { file, json })) = cli.as_ref().map(|cli| &cli.command) {
+        let ffprobe_path = resolve_ffprobe_path()?;
+        return probe::run_probe(&ffprobe_path, file, *json);
+    }
 
     let ffmpeg_path = resolve_ffmpeg_path()?;
-    println!("Using ffmpeg: {}", ffmpeg_path.display());
+
+    if let Some(Some(Commands::Capabilities { json })) = cli.as_ref().map(|cli| &cli.command) {
+        return capabilities::run_capabilities(&ffmpeg_path, *json);
+    }
+
+    let tools = Tools {
+        ffmpeg: ffmpeg_path,
+        ffprobe: resolve_ffprobe_path()?,
+    };
+
+    if cli.as_ref().is_some_and(|cli| cli.json) {
+        STDOUT_IS_REPORT.store(true, Ordering::Relaxed);
+    }
+
+    status!("Using ffmpeg: {}", tools.ffmpeg.display());
+
+    let capabilities = match lib::capabilities::detect(&tools.ffmpeg) {
+        Ok(capabilities) => Some(capabilities),
+        Err(err) => {
+            eprintln!("Warning: could not read ffmpeg capabilities: {err}");
+            None
+        }
+    };
+
+    match cli {
+        Some(Cli {
+            command: Some(Commands::Watch(args)),
+            ..
+        }) => watch::run_watch(&tools, *args, capabilities),
+        Some(cli) => run_with_args(&tools, cli, capabilities),
+        None => run_interactive(&tools, capabilities),
+    }
+}
+
+fn run_with_args(
+    tools: &Tools,
+    cli: Cli,
+    capabilities: Option<Arc<FfmpegCapabilities>>,
+) -> Result<(), String> {
+    let theme = ColorfulTheme::default();
+
+    let output_dir = cli
+        .output_dir
+        .ok_or("An output folder is required (--output-dir).")?;
+
+    let files = resolve_inputs(&cli.inputs, cli.recursive)?;
+    if files.is_empty() {
+        return Err("No supported video files found.".to_string());
+    }
+
+    let mut settings = settings_from_args(
+        output_dir,
+        &cli.encode,
+        cli.on_conflict.into(),
+        capabilities,
+    )?;
+    settings.name_template = cli.name_template;
+    settings.mirror_tree = cli.mirror_tree;
+    settings.jobs = usize::from(cli.jobs);
+    settings.force = cli.force;
+
+    ensure_output_dir(&theme, &settings.output_dir, cli.yes)?;
+
+    print_summary(&settings, files.len());
+
+    if !cli.yes {
+        let proceed = Confirm::with_theme(&theme)
+            .with_prompt("Start batch conversion?")
+            .default(true)
+            .interact()
+            .map_err(|e| e.to_string())?;
+        if !proceed {
+            return Ok(());
+        }
+    }
+
+    let report = convert_all(tools, &files, &settings);
+    if let Some(report_path) = &cli.report {
+        report.write(report_path)?;
+    }
+    if cli.json {
+        println!("{}", report.to_json()?);
+    }
+    if report.failed > 0 {
+        return Err(format!("{} file(s) failed to convert.", report.failed));
+    }
+
+    Ok(())
+}
+
+/// Settings for one file at a time from the encoding flags on top of the
+/// `--profile`, checking the codec against the container and the ffmpeg build.
+fn settings_from_args(
+    output_dir: PathBuf,
+    encode: &EncodeArgs,
+    conflict_policy: ConflictPolicy,
+    capabilities: Option<Arc<FfmpegCapabilities>>,
+) -> Result<Settings, String> {
+    let profile = match &encode.profile {
+        Some(name) => {
+            let profiles_path = match &encode.profiles_file {
+                Some(path) => path.clone(),
+                None => profiles::default_profiles_path()
+                    .ok_or("Could not find the app config folder, use --profiles-file.")?,
+            };
+            profiles::load_profile(&profiles_path, name)?
+        }
+        None => EncodeProfile {
+            name: String::new(),
+            container: None,
+            video_codec: None,
+            preset: Preset::Ironclad,
+            quality: None,
+            target_size_mb: None,
+            fps: None,
+            dimensions: None,
+            mute_audio: false,
+            audio: AudioOptions::default(),
+            streams: StreamOptions::default(),
+            metadata: MetadataOptions::default(),
+            hdr: HdrMode::default(),
+        },
+    };
+
+    let output_format = encode.format.unwrap_or(match profile.container {
+        Some(container) => OutputFormat::Fixed(container),
+        None => OutputFormat::SameAsSource,
+    });
+    let video_codec = encode.codec.or(profile.video_codec);
+    // An explicit --quality wins over the profile's target size.
+    let target_size_mb = match (encode.target_size, encode.quality) {
+        (Some(target_size_mb), _) => Some(target_size_mb),
+        (None, Some(_)) => None,
+        (None, None) => profile.target_size_mb,
+    };
+
+    if let (Some(codec), OutputFormat::Fixed(container)) = (video_codec, output_format) {
+        if !container.supports_video_codec(codec) {
+            return Err(format!(
+                "{} cannot be stored in {}.",
+                codec.as_ref(),
+                container.extension()
+            ));
+        }
+    }
+    let audio = AudioOptions {
+        codec: encode.audio_codec.or(profile.audio.codec),
+        bitrate_kbps: encode.audio_bitrate.or(profile.audio.bitrate_kbps),
+        channels: encode.audio_channels.or(profile.audio.channels),
+        sample_rate: encode.sample_rate.or(profile.audio.sample_rate),
+        normalize: encode.normalize_audio || profile.audio.normalize,
+    };
+    if let (Some(encoder), Some(capabilities)) = (
+        audio.codec.and_then(|codec| codec.encoder()),
+        capabilities.as_deref(),
+    ) {
+        if !capabilities.has_encoder(encoder) {
+            return Err(format!(
+                "{encoder} audio encoding is not available in this ffmpeg build."
+            ));
+        }
+    }
+    let streams = StreamOptions {
+        audio: encode
+            .audio_streams
+            .clone()
+            .unwrap_or_else(|| profile.streams.audio.clone()),
+        subtitles: encode
+            .subtitle_streams
+            .clone()
+            .unwrap_or_else(|| profile.streams.subtitles.clone()),
+        mix_audio: encode.mix_audio || profile.streams.mix_audio,
+    };
+    let subtitles = SubtitleOptions {
+        files: encode.subtitle_file.clone(),
+        burn_in: encode.burn_subtitles.clone(),
+    };
+    let burn_in_file = match &subtitles.burn_in {
+        Some(SubtitleBurnIn::File(file)) => Some(file),
+        _ => None,
+    };
+    if let Some(missing) = subtitles
+        .files
+        .iter()
+        .chain(burn_in_file)
+        .find(|file| !Path::new(file).is_file())
+    {
+        return Err(format!("Subtitle file {missing} does not exist."));
+    }
+    if let (Some(_), Some(capabilities)) = (&subtitles.burn_in, capabilities.as_deref()) {
+        if !capabilities.has_filter("subtitles") {
+            return Err(String::from(
+                "Burning in subtitles is not available in this ffmpeg build.",
+            ));
+        }
+    }
+    let metadata = MetadataOptions {
+        mode: encode
+            .metadata
+            .map(MetadataMode::from)
+            .unwrap_or(profile.metadata.mode),
+        copy_file_times: encode.keep_file_times || profile.metadata.copy_file_times,
+    };
+    let hdr = encode.hdr.map(HdrMode::from).unwrap_or(profile.hdr);
+    if !encode.trim.is_empty() {
+        domain::validate_trim_ranges(&encode.trim)
+            .map_err(|err| format!("Invalid --trim: {err}."))?;
+    }
+    if let (Some(codec), Some(capabilities)) = (video_codec, capabilities.as_deref()) {
+        if capabilities.video_encoder_for(codec).is_none() {
+            return Err(format!(
+                "{} encoding is not available in this ffmpeg build.",
+                codec.as_ref()
+            ));
+        }
+    }
+
+    Ok(Settings {
+        output_dir,
+        output_format,
+        video_codec,
+        preset: encode.preset.map(Preset::from).unwrap_or(profile.preset),
+        quality: encode.quality.or(profile.quality).unwrap_or(DEFAULT_QUALITY),
+        target_size_mb,
+        fps: encode.fps.map(|fps| fps as f32).or(profile.fps),
+        dimensions: encode.size.or(profile.dimensions),
+        mute_audio: encode.mute || profile.mute_audio,
+        audio,
+        streams,
+        subtitles,
+        metadata,
+        hdr,
+        trim: encode.trim.clone(),
+        conflict_policy,
+        name_template: NameTemplate::default(),
+        mirror_tree: false,
+        jobs: 1,
+        force: false,
+        profile_name: encode.profile.as_ref().map(|_| profile.name),
+        capabilities,
+    })
+}
+
+fn run_interactive(
+    tools: &Tools,
+    capabilities: Option<Arc<FfmpegCapabilities>>,
+) -> Result<(), String> {
+    let theme = ColorfulTheme::default();
 
     let input_mode = Select::with_theme(&theme)
         .with_prompt("Choose input mode")
@@ -92,11 +422,29 @@ fn run() -> Result<(), String> {
 
     let output_dir = prompt_output_dir(&theme, &input_dir)?;
 
+    let mirror_tree = if recursive {
+        Confirm::with_theme(&theme)
+            .with_prompt("Recreate subfolders in the output folder?")
+            .default(false)
+            .interact()
+            .map_err(|e| e.to_string())?
+    } else {
+        false
+    };
+
     let output_format = prompt_output_format(&theme)?;
 
+    let video_codec = prompt_video_codec(&theme, output_format, capabilities.as_deref())?;
+
     let preset = prompt_preset(&theme)?;
 
-    let quality = prompt_quality(&theme)?;
+    let target_size_mb = prompt_target_size(&theme)?;
+
+    // Quality is ignored when encoding to a target size.
+    let quality = match target_size_mb {
+        Some(_) => DEFAULT_QUALITY,
+        None => prompt_quality(&theme)?,
+    };
 
     let fps = prompt_fps(&theme)?;
 
@@ -108,17 +456,51 @@ fn run() -> Result<(), String> {
         .interact()
         .map_err(|e| e.to_string())?;
 
+    let audio = if mute_audio {
+        AudioOptions::default()
+    } else {
+        prompt_audio(&theme, capabilities.as_deref())?
+    };
+
+    let streams = prompt_streams(&theme, mute_audio)?;
+
+    let subtitles = prompt_subtitles(&theme)?;
+
+    let metadata = prompt_metadata(&theme)?;
+
+    let hdr = prompt_hdr(&theme, video_codec)?;
+
+    let trim = prompt_trim(&theme)?;
+
     let conflict_policy = prompt_conflict_policy(&theme)?;
 
+    let name_template = prompt_name_template(&theme)?;
+
+    let jobs = prompt_jobs(&theme)?;
+
     let settings = Settings {
         output_dir,
         output_format,
+        video_codec,
         preset,
         quality,
-        fps,
+        target_size_mb,
+        fps: fps.map(|fps| fps as f32),
         dimensions,
         mute_audio,
+        audio,
+        streams,
+        subtitles,
+        metadata,
+        hdr,
+        trim,
         conflict_policy,
+        name_template,
+        mirror_tree,
+        jobs,
+        force: false,
+        profile_name: None,
+        capabilities,
     };
 
     print_summary(&settings, files.len());
@@ -133,49 +515,195 @@ fn run() -> Result<(), String> {
         return Ok(());
     }
 
-    let mut succeeded = 0u32;
-    let mut failed = 0u32;
-    let mut skipped = 0u32;
+    convert_all(tools, &files, &settings);
 
-    for (index, input_path) in files.iter().enumerate() {
-        println!(
-            "\nProcessing {}/{}: {}",
-            index + 1,
-            files.len(),
-            input_path.display()
-        );
+    Ok(())
+}
 
-        let output_path = match build_output_path(input_path, &settings) {
-            Ok(path) => path,
-            Err(ConflictPolicy::Skip) => {
-                println!("Skipped (output exists).");
-                skipped += 1;
+/// An input waiting for a worker, with its reserved output path.
+struct ConversionTask {
+    index: usize,
+    input_path: PathBuf,
+    output_path: PathBuf,
+    /// `None` when the settings are invalid for this input; the conversion
+    /// then fails and nothing is recorded.
+    settings_hash: Option<String>,
+}
+
+fn convert_all(tools: &Tools, files: &[InputFile], settings: &Settings) -> BatchReport {
+    let started = Instant::now();
+
+    let manifest = Manifest::load(&settings.output_dir);
+
+    // Output paths are picked up front so parallel workers never share one.
+    let mut reserved: HashSet<PathBuf> = HashSet::new();
+    let mut file_reports: Vec<FileReport> = Vec::with_capacity(files.len());
+    let mut tasks: Vec<ConversionTask> = Vec::new();
+    for (index, input) in files.iter().enumerate() {
+        let input_path = &input.path;
+        let settings_hash = encode_settings(input_path, settings)
+            .ok()
+            .map(|(options, video_encoder)| manifest::settings_hash(&options, video_encoder));
+        let previous_output = match settings_hash
+            .as_deref()
+            .map(|hash| manifest.check(input_path, hash))
+        {
+            Some(ManifestCheck::Done(output_path)) if !settings.force => {
+                status!("Skipped (already converted): {}", input_path.display());
+                file_reports.push(FileReport::new(input_path, Some(&output_path)));
                 continue;
             }
-            Err(_) => {
-                failed += 1;
-                println!("Failed to resolve output path.");
-                continue;
+            Some(ManifestCheck::Done(output_path) | ManifestCheck::Stale(output_path)) => {
+                Some(output_path)
             }
+            _ => None,
         };
 
-        match convert_file(&ffmpeg_path, input_path, &output_path, &settings) {
-            Ok(()) => {
-                succeeded += 1;
-                println!("Saved to {}", output_path.display());
+        let output_path = build_output_path(
+            tools,
+            input_path,
+            &input.relative_dir,
+            index + 1,
+            settings,
+            previous_output.as_deref(),
+            &reserved,
+        );
+
+        match output_path {
+            Ok(Some(path)) => {
+                reserved.insert(path.clone());
+                file_reports.push(FileReport::new(input_path, Some(&path)));
+                tasks.push(ConversionTask {
+                    index,
+                    input_path: input_path.clone(),
+                    output_path: path,
+                    settings_hash,
+                });
+            }
+            Ok(None) => {
+                status!("Skipped (output exists): {}", input_path.display());
+                file_reports.push(FileReport::new(input_path, None));
             }
             Err(err) => {
-                failed += 1;
-                println!("Failed: {err}");
+                status!(
+                    "Failed to resolve output path: {}: {err}",
+                    input_path.display()
+                );
+                let mut file_report = FileReport::new(input_path, None);
+                file_report.finish(Err(FfmpegError::from(err)), Duration::ZERO);
+                file_reports.push(file_report);
             }
         }
     }
 
-    println!(
-        "\nDone. Succeeded: {succeeded}, Failed: {failed}, Skipped: {skipped}",
+    let bars = MultiProgress::new();
+    let overall = bars.add(ProgressBar::new(tasks.len() as u64));
+    if let Ok(style) =
+        ProgressStyle::with_template("[{elapsed_precise}] {bar:40.green/white} {pos}/{len} files")
+    {
+        overall.set_style(style);
+    }
+
+    let tasks = Arc::new(tasks);
+    let manifest = Arc::new(Mutex::new(manifest));
+    let file_reports = Arc::new(Mutex::new(file_reports));
+    let settings = Arc::new(settings.clone());
+    let next_task = Arc::new(AtomicUsize::new(0));
+    let file_count = files.len();
+    let workers = settings.jobs.clamp(1, tasks.len().max(1));
+
+    let handles: Vec<thread::JoinHandle<()>> = (0..workers)
+        .map(|_| {
+            let tools = tools.clone();
+            let tasks = tasks.clone();
+            let file_reports = file_reports.clone();
+            let manifest = manifest.clone();
+            let settings = settings.clone();
+            let next_task = next_task.clone();
+            let bars = bars.clone();
+            let overall = overall.clone();
+            thread::spawn(move || {
+                while let Some(task) = tasks.get(next_task.fetch_add(1, Ordering::SeqCst)) {
+                    let ConversionTask {
+                        index,
+                        input_path,
+                        output_path,
+                        settings_hash,
+                    } = task;
+                    bars.suspend(|| {
+                        status!(
+                            "Processing {}/{}: {}",
+                            index + 1,
+                            file_count,
+                            input_path.display()
+                        )
+                    });
+
+                    let file_started = Instant::now();
+                    let mut file_report = FileReport::new(input_path, Some(output_path));
+                    let result = convert_file(
+                        &tools,
+                        input_path,
+                        output_path,
+                        &settings,
+                        &bars,
+                        &mut file_report,
+                    );
+                    match &result {
+                        Ok(()) => {
+                            bars.suspend(|| status!("Saved to {}", output_path.display()));
+                            let recorded = match (settings_hash, manifest.lock()) {
+                                (Some(hash), Ok(mut manifest)) => {
+                                    manifest.record(input_path, hash, output_path)
+                                }
+                                _ => Ok(()),
+                            };
+                            if let Err(err) = recorded {
+                                bars.suspend(|| status!("Warning: {err}"));
+                            }
+                        }
+                        Err(err) => {
+                            bars.suspend(|| {
+                                status!("Failed: {}: {err}", input_path.display());
+                                if let Some(stderr) = &err.stderr {
+                                    for line in stderr.lines() {
+                                        status!("  {line}");
+                                    }
+                                }
+                            });
+                        }
+                    }
+                    file_report.finish(result, file_started.elapsed());
+                    if let Ok(mut file_reports) = file_reports.lock() {
+                        file_reports[*index] = file_report;
+                    }
+                    overall.inc(1);
+                }
+            })
+        })
+        .collect();
+
+    for handle in handles {
+        if handle.join().is_err() {
+            bars.suspend(|| status!("A conversion worker stopped unexpectedly."));
+        }
+    }
+    overall.finish_and_clear();
+
+    let file_reports = file_reports
+        .lock()
+        .map(|file_reports| file_reports.clone())
+        .unwrap_or_default();
+    let report = BatchReport::new(&tools.ffmpeg, file_reports, started.elapsed());
+
+    status!(
+        "\nDone. Succeeded: {}, Failed: {}, Skipped: {}",
+        report.succeeded,
+        report.failed,
+        report.skipped
     );
 
-    Ok(())
+    report
 }
 
 fn prompt_dir(theme: &ColorfulTheme, label: &str) -> Result<PathBuf, String> {
@@ -201,19 +729,29 @@ fn prompt_output_dir(theme: &ColorfulTheme, default_dir: &Path) -> Result<PathBu
         .interact_text()
         .map_err(|e| e.to_string())?;
     let path = PathBuf::from(input.trim());
-    if !path.exists() {
-        let create = Confirm::with_theme(theme)
+    ensure_output_dir(theme, &path, false)?;
+    Ok(path)
+}
+
+fn ensure_output_dir(theme: &ColorfulTheme, path: &Path, assume_yes: bool) -> Result<(), String> {
+    if path.exists() {
+        if !path.is_dir() {
+            return Err(format!("Output path is not a directory: {}", path.display()));
+        }
+        return Ok(());
+    }
+    let create = assume_yes
+        || Confirm::with_theme(theme)
             .with_prompt("Output folder does not exist. Create it?")
             .default(true)
             .interact()
             .map_err(|e| e.to_string())?;
-        if create {
-            fs::create_dir_all(&path).map_err(|e| e.to_string())?;
-        } else {
-            return Err("Output folder is required.".to_string());
-        }
+    if create {
+        fs::create_dir_all(path).map_err(|e| e.to_string())?;
+    } else {
+        return Err("Output folder is required.".to_string());
     }
-    Ok(path)
+    Ok(())
 }
 
 fn prompt_output_format(theme: &ColorfulTheme) -> Result<OutputFormat, String> {
@@ -233,15 +771,45 @@ fn prompt_output_format(theme: &ColorfulTheme) -> Result<OutputFormat, String> {
         .map_err(|e| e.to_string())?;
     let format = match selection {
         0 => OutputFormat::SameAsSource,
-        1 => OutputFormat::Fixed("mp4"),
-        2 => OutputFormat::Fixed("mov"),
-        3 => OutputFormat::Fixed("webm"),
-        4 => OutputFormat::Fixed("avi"),
-        _ => OutputFormat::Fixed("mkv"),
+        1 => OutputFormat::Fixed(Container::Mp4),
+        2 => OutputFormat::Fixed(Container::Mov),
+        3 => OutputFormat::Fixed(Container::Webm),
+        4 => OutputFormat::Fixed(Container::Avi),
+        _ => OutputFormat::Fixed(Container::Mkv),
     };
     Ok(format)
 }
 
+fn prompt_video_codec(
+    theme: &ColorfulTheme,
+    output_format: OutputFormat,
+    capabilities: Option<&FfmpegCapabilities>,
+) -> Result<Option<VideoCodec>, String> {
+    // Only offer codecs the container can hold and this ffmpeg can encode.
+    let codecs: Vec<VideoCodec> = VideoCodec::ALL
+        .into_iter()
+        .filter(|codec| match output_format {
+            OutputFormat::SameAsSource => true,
+            OutputFormat::Fixed(container) => container.supports_video_codec(*codec),
+        })
+        .filter(|codec| {
+            capabilities.is_none_or(|capabilities| {
+                capabilities.video_encoder_for(*codec).is_some()
+            })
+        })
+        .collect();
+    let mut options = vec![String::from("Default for format")];
+    options.extend(codecs.iter().map(|codec| codec.as_ref().to_string()));
+
+    let selection = Select::with_theme(theme)
+        .with_prompt("Video codec")
+        .items(&options)
+        .default(0)
+        .interact()
+        .map_err(|e| e.to_string())?;
+    Ok(selection.checked_sub(1).map(|index| codecs[index]))
+}
+
 fn prompt_preset(theme: &ColorfulTheme) -> Result<Preset, String> {
     let options = ["Default (slow)", "Thunderbolt (fast)"];
     let selection = Select::with_theme(theme)
@@ -251,7 +819,7 @@ fn prompt_preset(theme: &ColorfulTheme) -> Result<Preset, String> {
         .interact()
         .map_err(|e| e.to_string())?;
     Ok(if selection == 0 {
-        Preset::Default
+        Preset::Ironclad
     } else {
         Preset::Thunderbolt
     })
@@ -269,6 +837,25 @@ fn prompt_quality(theme: &ColorfulTheme) -> Result<u16, String> {
     Ok(quality)
 }
 
+fn prompt_target_size(theme: &ColorfulTheme) -> Result<Option<f64>, String> {
+    let input: String = Input::with_theme(theme)
+        .with_prompt("Target file size in MB (blank to use quality instead)")
+        .allow_empty(true)
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+    let trimmed = input.trim();
+    if trimmed.is_empty() {
+        return Ok(None);
+    }
+    let size = trimmed
+        .parse::<f64>()
+        .map_err(|_| "Target size must be a number.".to_string())?;
+    if !size.is_finite() || size <= 0.0 {
+        return Err("Target size must be greater than 0.".to_string());
+    }
+    Ok(Some(size))
+}
+
 fn prompt_fps(theme: &ColorfulTheme) -> Result<Option<u32>, String> {
     let input: String = Input::with_theme(theme)
         .with_prompt("FPS (blank to keep original)")
@@ -285,6 +872,18 @@ fn prompt_fps(theme: &ColorfulTheme) -> Result<Option<u32>, String> {
     Ok(Some(fps))
 }
 
+fn prompt_jobs(theme: &ColorfulTheme) -> Result<usize, String> {
+    let jobs: u16 = Input::with_theme(theme)
+        .with_prompt("Files to convert at the same time")
+        .default(1)
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+    if jobs == 0 {
+        return Err("At least one file must be converted at a time.".to_string());
+    }
+    Ok(usize::from(jobs))
+}
+
 fn prompt_dimensions(theme: &ColorfulTheme) -> Result<Option<(u32, u32)>, String> {
     let resize = Confirm::with_theme(theme)
         .with_prompt("Resize video?")
@@ -321,16 +920,245 @@ fn prompt_conflict_policy(theme: &ColorfulTheme) -> Result<ConflictPolicy, Strin
     Ok(policy)
 }
 
+fn prompt_audio(
+    theme: &ColorfulTheme,
+    capabilities: Option<&FfmpegCapabilities>,
+) -> Result<AudioOptions, String> {
+    // Without capabilities every codec is offered and ffmpeg reports what is missing.
+    let codecs: Vec<AudioCodec> = AudioCodec::ALL
+        .into_iter()
+        .filter(|codec| match (codec.encoder(), capabilities) {
+            (Some(encoder), Some(capabilities)) => capabilities.has_encoder(encoder),
+            _ => true,
+        })
+        .collect();
+    let mut options = vec!["ffmpeg default".to_string()];
+    options.extend(codecs.iter().map(|codec| match codec {
+        AudioCodec::Copy => "Copy source audio".to_string(),
+        other => other.as_ref().to_uppercase(),
+    }));
+    let selection = Select::with_theme(theme)
+        .with_prompt("Audio codec")
+        .items(&options)
+        .default(0)
+        .interact()
+        .map_err(|e| e.to_string())?;
+    let codec = selection.checked_sub(1).map(|index| codecs[index]);
+    if codec == Some(AudioCodec::Copy) {
+        return Ok(AudioOptions {
+            codec,
+            ..AudioOptions::default()
+        });
+    }
+
+    let bitrate: String = Input::with_theme(theme)
+        .with_prompt("Audio bitrate in kbps (leave empty for default)")
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), String> {
+            if input.trim().is_empty() || input.trim().parse::<u32>().is_ok() {
+                Ok(())
+            } else {
+                Err("Enter a whole number".to_string())
+            }
+        })
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+    let mono = Confirm::with_theme(theme)
+        .with_prompt("Downmix to mono?")
+        .default(false)
+        .interact()
+        .map_err(|e| e.to_string())?;
+    let normalize = Confirm::with_theme(theme)
+        .with_prompt("Normalize loudness (EBU R128)?")
+        .default(false)
+        .interact()
+        .map_err(|e| e.to_string())?;
+
+    Ok(AudioOptions {
+        codec,
+        bitrate_kbps: bitrate.trim().parse::<u32>().ok(),
+        channels: if mono { Some(1) } else { None },
+        sample_rate: None,
+        normalize,
+    })
+}
+
+fn prompt_streams(theme: &ColorfulTheme, mute_audio: bool) -> Result<StreamOptions, String> {
+    let prompt_selection = |prompt: &str| -> Result<StreamSelection, String> {
+        let input: String = Input::with_theme(theme)
+            .with_prompt(prompt)
+            .allow_empty(true)
+            .validate_with(|input: &String| -> Result<(), String> {
+                if input.trim().is_empty() {
+                    return Ok(());
+                }
+                args::parse_stream_selection(input).map(|_| ())
+            })
+            .interact_text()
+            .map_err(|e| e.to_string())?;
+        if input.trim().is_empty() {
+            Ok(StreamSelection::Default)
+        } else {
+            args::parse_stream_selection(&input)
+        }
+    };
+
+    let mut streams = StreamOptions::default();
+    if !mute_audio {
+        streams.audio = prompt_selection(
+            "Audio streams to keep (all, numbers or languages such as 0,2 or eng, empty keeps one)",
+        )?;
+        if streams.audio != StreamSelection::Default {
+            streams.mix_audio = Confirm::with_theme(theme)
+                .with_prompt("Mix the kept audio streams into one?")
+                .default(false)
+                .interact()
+                .map_err(|e| e.to_string())?;
+        }
+    }
+    streams.subtitles = prompt_selection(
+        "Subtitle streams to keep (all, numbers or languages, empty keeps ffmpeg's default)",
+    )?;
+    Ok(streams)
+}
+
+fn prompt_subtitles(theme: &ColorfulTheme) -> Result<SubtitleOptions, String> {
+    let file: String = Input::with_theme(theme)
+        .with_prompt("Subtitle file to add to every video (empty adds none)")
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), String> {
+            let input = input.trim();
+            if input.is_empty() || Path::new(input).is_file() && domain::is_subtitle_file(input) {
+                Ok(())
+            } else {
+                Err(String::from(
+                    "Expected an existing .srt, .vtt, .ass or .ssa file.",
+                ))
+            }
+        })
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+    let burn_in: String = Input::with_theme(theme)
+        .with_prompt("Burn in subtitles (subtitle stream number or file, empty burns none)")
+        .allow_empty(true)
+        .validate_with(|input: &String| -> Result<(), String> {
+            let input = input.trim();
+            if input.is_empty() {
+                return Ok(());
+            }
+            match args::parse_burn_in(input)? {
+                SubtitleBurnIn::File(file) if !Path::new(&file).is_file() => {
+                    Err(format!("Subtitle file {file} does not exist."))
+                }
+                _ => Ok(()),
+            }
+        })
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+
+    Ok(SubtitleOptions {
+        files: Some(file.trim().to_string())
+            .filter(|file| !file.is_empty())
+            .into_iter()
+            .collect(),
+        burn_in: match burn_in.trim() {
+            "" => None,
+            burn_in => Some(args::parse_burn_in(burn_in)?),
+        },
+    })
+}
+
+fn prompt_metadata(theme: &ColorfulTheme) -> Result<MetadataOptions, String> {
+    let modes = [
+        MetadataMode::Default,
+        MetadataMode::Preserve,
+        MetadataMode::Strip,
+    ];
+    let mode = Select::with_theme(theme)
+        .with_prompt("Metadata")
+        .items(&[
+            "Default (keep the main tags)",
+            "Preserve all metadata and chapters",
+            "Strip all metadata, including GPS and device tags",
+        ])
+        .default(0)
+        .interact()
+        .map_err(|e| e.to_string())?;
+    let copy_file_times = Confirm::with_theme(theme)
+        .with_prompt("Keep the file dates of the originals?")
+        .default(false)
+        .interact()
+        .map_err(|e| e.to_string())?;
+
+    Ok(MetadataOptions {
+        mode: modes[mode],
+        copy_file_times,
+    })
+}
+
+/// Only asked when the codec can keep HDR, others always tone map.
+fn prompt_hdr(theme: &ColorfulTheme, video_codec: Option<VideoCodec>) -> Result<HdrMode, String> {
+    if !video_codec.is_some_and(|codec| codec.supports_hdr()) {
+        return Ok(HdrMode::Tonemap);
+    }
+    let keep = Select::with_theme(theme)
+        .with_prompt("HDR videos")
+        .items(&["Tone map to SDR (BT.709)", "Keep 10-bit HDR"])
+        .default(0)
+        .interact()
+        .map_err(|e| e.to_string())?;
+
+    Ok(if keep == 1 {
+        HdrMode::Preserve
+    } else {
+        HdrMode::Tonemap
+    })
+}
+
+fn prompt_trim(theme: &ColorfulTheme) -> Result<Vec<TrimRange>, String> {
+    let parse = |input: &str| -> Result<Vec<TrimRange>, String> {
+        if input.trim().is_empty() {
+            return Ok(Vec::new());
+        }
+        let ranges = input
+            .split(',')
+            .map(args::parse_trim_range)
+            .collect::<Result<Vec<TrimRange>, String>>()?;
+        domain::validate_trim_ranges(&ranges)?;
+        Ok(ranges)
+    };
+    let input: String = Input::with_theme(theme)
+        .with_prompt("Keep only parts of each video (e.g. 0:10-0:45,1:20-2:00, empty keeps all)")
+        .allow_empty(true)
+        .validate_with(|input: &String| parse(input).map(|_| ()))
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+    parse(&input)
+}
+
+fn prompt_name_template(theme: &ColorfulTheme) -> Result<NameTemplate, String> {
+    let input: String = Input::with_theme(theme)
+        .with_prompt(
+            "Output file name ({stem} {ext} {date} {width} {height} {quality} {preset} {codec} {index} {parent})",
+        )
+        .default(naming::DEFAULT_NAME_TEMPLATE.to_string())
+        .validate_with(|input: &String| NameTemplate::parse(input).map(|_| ()))
+        .interact_text()
+        .map_err(|e| e.to_string())?;
+    NameTemplate::parse(&input)
+}
+
 fn prompt_file_selection(
     theme: &ColorfulTheme,
     base_dir: &Path,
-    files: &[PathBuf],
-) -> Result<Vec<PathBuf>, String> {
+    files: &[InputFile],
+) -> Result<Vec<InputFile>, String> {
     let items: Vec<String> = files
         .iter()
-        .map(|path| {
-            path.strip_prefix(base_dir)
-                .unwrap_or(path)
+        .map(|file| {
+            file.path
+                .strip_prefix(base_dir)
+                .unwrap_or(&file.path)
                 .display()
                 .to_string()
         })
@@ -344,15 +1172,23 @@ fn prompt_file_selection(
 
     let mut selected = Vec::new();
     for index in selections {
-        if let Some(path) = files.get(index) {
-            selected.push(path.clone());
+        if let Some(file) = files.get(index) {
+            selected.push(file.clone());
         }
     }
     Ok(selected)
 }
 
-fn collect_video_files(base_dir: &Path, recursive: bool) -> Result<Vec<PathBuf>, String> {
+fn collect_video_files(base_dir: &Path, recursive: bool) -> Result<Vec<InputFile>, String> {
     let mut files = Vec::new();
+    let input_file = |path: PathBuf| InputFile {
+        relative_dir: path
+            .parent()
+            .and_then(|dir| dir.strip_prefix(base_dir).ok())
+            .map(Path::to_path_buf)
+            .unwrap_or_default(),
+        path,
+    };
 
     if recursive {
         for entry in WalkDir::new(base_dir)
@@ -363,7 +1199,7 @@ fn collect_video_files(base_dir: &Path, recursive: bool) -> Result<Vec<PathBuf>,
             if entry.file_type().is_file() {
                 let path = entry.path();
                 if is_video_file(path) {
-                    files.push(path.to_path_buf());
+                    files.push(input_file(path.to_path_buf()));
                 }
             }
         }
@@ -372,12 +1208,55 @@ fn collect_video_files(base_dir: &Path, recursive: bool) -> Result<Vec<PathBuf>,
             let entry = entry.map_err(|e| e.to_string())?;
             let path = entry.path();
             if path.is_file() && is_video_file(&path) {
-                files.push(path);
+                files.push(input_file(path));
             }
         }
     }
 
-    files.sort();
+    files.sort_by(|a, b| a.path.cmp(&b.path));
+    Ok(files)
+}
+
+/// Expands CLI inputs (files, folders and glob patterns) into a sorted list of video files.
+fn resolve_inputs(inputs: &[String], recursive: bool) -> Result<Vec<InputFile>, String> {
+    let mut files = Vec::new();
+
+    for input in inputs {
+        let path = PathBuf::from(input);
+        let matches = if path.exists() {
+            vec![path]
+        } else {
+            let paths: Vec<PathBuf> = glob::glob(input)
+                .map_err(|e| format!("Invalid input pattern {input}: {e}"))?
+                .filter_map(Result::ok)
+                .collect();
+            if paths.is_empty() {
+                return Err(format!("Path does not exist: {input}"));
+            }
+            paths
+        };
+
+        for path in matches {
+            if path.is_dir() {
+                files.extend(collect_video_files(&path, recursive)?);
+            } else if is_video_file(&path) {
+                files.push(InputFile {
+                    path,
+                    relative_dir: PathBuf::new(),
+                });
+            } else {
+                status!("Skipping unsupported file: {}", path.display());
+            }
+        }
+    }
+
+    // A file both named and found in a named folder keeps its subfolder.
+    files.sort_by(|a, b| {
+        a.path
+            .cmp(&b.path)
+            .then_with(|| b.relative_dir.cmp(&a.relative_dir))
+    });
+    files.dedup_by(|a, b| a.path == b.path);
     Ok(files)
 }
 
@@ -388,256 +1267,413 @@ fn is_video_file(path: &Path) -> bool {
         .unwrap_or(false)
 }
 
-fn build_output_path(input_path: &Path, settings: &Settings) -> Result<PathBuf, ConflictPolicy> {
-    let output_ext = match settings.output_format {
+fn output_container(input_path: &Path, settings: &Settings) -> Container {
+    match settings.output_format {
         OutputFormat::SameAsSource => input_path
             .extension()
             .and_then(OsStr::to_str)
-            .unwrap_or("mp4"),
-        OutputFormat::Fixed(ext) => ext,
-    };
-
-    let stem = input_path
-        .file_stem()
-        .and_then(OsStr::to_str)
-        .unwrap_or("output");
-
-    let base_name = format!("{stem}_compressed.{output_ext}");
-    let mut candidate = settings.output_dir.join(base_name);
-
-    if !candidate.exists() {
-        return Ok(candidate);
+            .and_then(Container::from_extension)
+            .unwrap_or(Container::Mp4),
+        OutputFormat::Fixed(container) => container,
     }
+}
 
-    match settings.conflict_policy {
-        ConflictPolicy::Overwrite => Ok(candidate),
-        ConflictPolicy::Skip => Err(ConflictPolicy::Skip),
-        ConflictPolicy::AutoRename => {
-            let mut index = 1u32;
-            loop {
-                let name = format!("{stem}_compressed_{index}.{output_ext}");
-                candidate = settings.output_dir.join(name);
-                if !candidate.exists() {
-                    return Ok(candidate);
-                }
-                index += 1;
-            }
-        }
+/// Output path of the `index`-th (1-based) input from the name template,
+/// under `relative_dir` of the output folder with `--mirror-tree`.
+/// `previous_output` is an earlier output of the same input, replaced when the
+/// template names it again. `reserved` holds paths already given to other
+/// files of this batch; they are never overwritten, even with
+/// `ConflictPolicy::Overwrite`. `Ok(None)` means the file is skipped.
+fn build_output_path(
+    tools: &Tools,
+    input_path: &Path,
+    relative_dir: &Path,
+    index: usize,
+    settings: &Settings,
+    previous_output: Option<&Path>,
+    reserved: &HashSet<PathBuf>,
+) -> Result<Option<PathBuf>, String> {
+    let (options, _) = encode_settings(input_path, settings)?;
+    // Only probe when the template asks for dimensions the settings don't set.
+    let source_dimensions =
+        if settings.name_template.needs_dimensions() && options.dimensions.is_none() {
+            probe_video_dimensions(&tools.ffprobe, input_path)?
+        } else {
+            None
+        };
+    let context = NameContext::new(input_path, &options, source_dimensions, index);
+    let output_dir = if settings.mirror_tree {
+        settings.output_dir.join(relative_dir)
+    } else {
+        settings.output_dir.clone()
+    };
+    let candidate = output_dir.join(settings.name_template.render(&context)?);
+
+    // A changed source or new settings replace the earlier output.
+    if previous_output == Some(candidate.as_path()) && !reserved.contains(&candidate) {
+        return Ok(Some(candidate));
     }
+    Ok(naming::resolve_conflict(
+        candidate,
+        settings.conflict_policy,
+        reserved,
+    ))
 }
 
 fn convert_file(
-    ffmpeg_path: &Path,
+    tools: &Tools,
     input_path: &Path,
     output_path: &Path,
     settings: &Settings,
-) -> Result<(), String> {
-    let total_us = probe_duration_us(ffmpeg_path, input_path).ok().flatten();
-    let progress = match total_us {
-        Some(total) => {
-            let pb = ProgressBar::new(total);
+    bars: &MultiProgress,
+    file_report: &mut FileReport,
+) -> Result<(), FfmpegError> {
+    let details = probe_input_details(&tools.ffprobe, input_path).ok();
+    let source_duration = details
+        .as_ref()
+        .and_then(|details| details.duration_seconds);
+    file_report.duration_seconds = source_duration;
+    let mut request = build_encode_request(input_path, output_path, settings, details.as_ref())?;
+    if details.as_ref().and_then(|details| details.hdr).is_some() && request.hdr.is_none() {
+        status!(
+            "Warning: this ffmpeg build cannot tone map {} (needs zscale), encoding its HDR as is.",
+            input_path.display()
+        );
+    }
+    // Progress and target size bitrates follow the trimmed duration.
+    let duration_seconds = encoder::output_duration(&request.options.transforms, source_duration)?;
+    // Subfolders of `--mirror-tree` are created when their first file is converted.
+    if let Some(dir) = output_path.parent() {
+        fs::create_dir_all(dir)
+            .map_err(|e| format!("Could not create folder {}: {e}", dir.display()))?;
+    }
+
+    // Unique per conversion, parallel jobs may write outputs with the same name.
+    let passlog_prefix =
+        env::temp_dir().join(format!("compresso-{}-{}", std::process::id(), nanoid!()));
+    let mut measure_args: Option<Vec<String>> = None;
+    if encoder::needs_loudness_measurement(&request) {
+        let args = encoder::build_loudness_args(&request);
+        request.loudness = measure_loudness(&tools.ffmpeg, &args)?;
+        if request.loudness.is_none() {
+            status!(
+                "Warning: could not measure the loudness of {}, keeping it as is.",
+                input_path.display()
+            );
+        }
+        measure_args = Some(args);
+    }
+
+    let passes = match settings.target_size_mb {
+        Some(target_size_mb) => {
+            let duration_seconds = duration_seconds.ok_or_else(|| {
+                String::from("Could not read video duration to compress to a target size.")
+            })?;
+            let plan = encoder::plan_target_size(
+                target_size_mb,
+                duration_seconds,
+                encoder::target_size_audio_kbps(&request.options),
+                encoder::output_audio_streams(&request),
+            )?;
+            encoder::build_target_size_args(&request, plan, &passlog_prefix.display().to_string())
+        }
+        None => vec![encoder::build_ffmpeg_args(&request)],
+    };
+    file_report.ffmpeg_args = measure_args.into_iter().chain(passes.clone()).collect();
+
+    // One bar spans all passes, positioned in hundredths of a percent.
+    let pass_count = passes.len() as u8;
+    let progress = match duration_seconds {
+        Some(_) => {
+            // Above the overall bar, which is always last.
+            let pb = bars.insert_from_back(1, ProgressBar::new(PROGRESS_BAR_LENGTH));
             pb.set_style(
                 ProgressStyle::with_template(
-                    "{spinner} [{elapsed_precise}] {bar:40.cyan/blue} {percent}% {msg}",
+                    "{spinner} [{elapsed_precise}] {bar:40.cyan/blue} {percent}% {prefix} {msg}",
                 )
                 .map_err(|e| e.to_string())?,
             );
             pb
         }
         None => {
-            let pb = ProgressBar::new_spinner();
+            let pb = bars.insert_from_back(1, ProgressBar::new_spinner());
             pb.enable_steady_tick(Duration::from_millis(120));
             pb
         }
     };
 
-    let message = input_path
+    let file_name = input_path
         .file_name()
         .map(|name| name.to_string_lossy().to_string())
         .unwrap_or_default();
-    progress.set_message(message);
+    progress.set_prefix(file_name);
+
+    let mut result = Ok(());
+    for (index, args) in passes.iter().enumerate() {
+        let tracker = ProgressTracker::pass(duration_seconds, index as u8 + 1, pass_count);
+        result = run_ffmpeg(&tools.ffmpeg, args, &progress, tracker);
+        if result.is_err() {
+            break;
+        }
+    }
+
+    progress.finish_and_clear();
+    bars.remove(&progress);
+
+    if pass_count > 1 {
+        delete_passlog_files(&passlog_prefix);
+    }
 
-    let mut args = build_ffmpeg_args(input_path, output_path, settings);
+    let result = result
+        .and_then(|_| diagnostics::verify_output(Command::new(&tools.ffprobe), output_path))
+        .and_then(|_| {
+            if !settings.metadata.copy_file_times {
+                return Ok(());
+            }
+            lib::fs::copy_file_times(input_path, output_path).map_err(|e| {
+                FfmpegError::from(format!("Could not copy the file times of the video: {e}"))
+            })
+        });
+    // A broken output would pass for a converted file on the next run.
+    if result.is_err() {
+        let _ = fs::remove_file(output_path);
+    }
+    result
+}
 
+fn run_ffmpeg(
+    ffmpeg_path: &Path,
+    args: &[String],
+    progress: &ProgressBar,
+    tracker: ProgressTracker,
+) -> Result<(), FfmpegError> {
     let mut child = Command::new(ffmpeg_path)
-        .args(&mut args)
+        .args(args)
         .stdout(Stdio::piped())
         .stderr(Stdio::piped())
         .spawn()
-        .map_err(|e| e.to_string())?;
+        .map_err(|e| diagnostics::spawn_error("ffmpeg", &e))?;
 
-    let stdout = child.stdout.take().ok_or("Failed to read ffmpeg output")?;
-    let stderr = child.stderr.take().ok_or("Failed to read ffmpeg error")?;
+    let stdout = child
+        .stdout
+        .take()
+        .ok_or_else(|| String::from("Failed to read ffmpeg output"))?;
+    let stderr = child
+        .stderr
+        .take()
+        .ok_or_else(|| String::from("Failed to read ffmpeg error"))?;
 
     let stderr_handle = thread::spawn(move || {
         let reader = BufReader::new(stderr);
-        let mut buffer = String::new();
+        let mut tail = StderrTail::default();
         for line in reader.lines().map_while(Result::ok) {
-            buffer.push_str(&line);
-            buffer.push('\n');
+            tail.push(&line);
         }
-        buffer
+        tail
     });
 
     let stdout_reader = BufReader::new(stdout);
+    let mut parser = ProgressParser::default();
     for line in stdout_reader.lines().map_while(Result::ok) {
-        if let Some(out_time) = parse_out_time_us(&line) {
-            if let Some(total) = total_us {
-                progress.set_position(out_time.min(total));
-            }
-        }
-        if line.starts_with("progress=end") {
-            if let Some(total) = total_us {
-                progress.set_position(total);
+        if let Some(sample) = parser.push_line(&line) {
+            let report = tracker.report(&sample);
+            if let Some(percentage) = report.percentage {
+                progress.set_position((percentage * 100.0) as u64);
             }
+            progress.set_message(describe_progress(&report));
         }
     }
 
     let status = child.wait().map_err(|e| e.to_string())?;
-    let stderr_output = stderr_handle.join().unwrap_or_default();
-
-    progress.finish_and_clear();
+    let stderr_tail = stderr_handle.join().unwrap_or_default();
 
     if status.success() {
         Ok(())
     } else {
-        Err(stderr_output.trim().to_string())
+        Err(diagnostics::classify(status.code(), &stderr_tail.text()))
     }
 }
 
-fn build_ffmpeg_args(input_path: &Path, output_path: &Path, settings: &Settings) -> Vec<String> {
-    let mut args = vec![
-        "-i".to_string(),
-        input_path.display().to_string(),
-        "-hide_banner".to_string(),
-        "-progress".to_string(),
-        "-".to_string(),
-        "-nostats".to_string(),
-        "-loglevel".to_string(),
-        "error".to_string(),
-    ];
-
-    let mut codec = "libx264".to_string();
-    let output_ext = match settings.output_format {
-        OutputFormat::SameAsSource => output_path
-            .extension()
-            .and_then(OsStr::to_str)
-            .unwrap_or("mp4"),
-        OutputFormat::Fixed(ext) => ext,
-    };
-
-    if output_ext.eq_ignore_ascii_case("webm") {
-        codec = "libvpx-vp9".to_string();
-    }
-
-    let crf = quality_to_crf(settings.quality);
-
-    match settings.preset {
-        Preset::Thunderbolt => {
-            args.push("-c:v".to_string());
-            args.push(codec.clone());
-            args.push("-crf".to_string());
-            args.push(crf.to_string());
-        }
-        Preset::Default => {
-            args.extend([
-                "-pix_fmt".to_string(),
-                "yuv420p".to_string(),
-                "-c:v".to_string(),
-                codec.clone(),
-                "-b:v".to_string(),
-                "0".to_string(),
-                "-movflags".to_string(),
-                "+faststart".to_string(),
-                "-preset".to_string(),
-                "slow".to_string(),
-                "-qp".to_string(),
-                "0".to_string(),
-                "-crf".to_string(),
-                crf.to_string(),
-            ]);
-        }
-    }
-
-    let padding = "pad=ceil(iw/2)*2:ceil(ih/2)*2".to_string();
-    let vf_filter = if let Some((width, height)) = settings.dimensions {
-        format!("scale={width}:{height},{padding}")
-    } else {
-        padding
-    };
-
-    args.push("-vf".to_string());
-    args.push(vf_filter);
+/// Runs a `loudnorm` measurement pass, `None` when it measured nothing usable.
+fn measure_loudness(
+    ffmpeg_path: &Path,
+    args: &[String],
+) -> Result<Option<LoudnessMeasurement>, FfmpegError> {
+    let mut command = Command::new(ffmpeg_path);
+    command.args(args);
+    let output = diagnostics::run_to_completion(command, "ffmpeg")?;
+    Ok(loudness::parse_measurement(&String::from_utf8_lossy(
+        &output.stderr,
+    )))
+}
 
-    if let Some(fps) = settings.fps {
-        args.push("-r".to_string());
-        args.push(fps.to_string());
+/// Removes the stats files a two-pass encode wrote at `prefix`: `-0.log` and
+/// its `.mbtree`, or x265's `-x265.log` and its `.cutree`, with the `.temp`
+/// files left by an interrupted pass.
+fn delete_passlog_files(prefix: &Path) {
+    let prefix = prefix.display().to_string();
+    for stats_file in ["-0.log", "-0.log.mbtree", "-x265.log", "-x265.log.cutree"] {
+        for suffix in ["", ".temp"] {
+            let _ = fs::remove_file(format!("{prefix}{stats_file}{suffix}"));
+        }
     }
+}
 
-    if settings.mute_audio {
-        args.push("-an".to_string());
-    }
+/// Options and encoder used for `input_path`, validated.
+fn encode_settings(
+    input_path: &Path,
+    settings: &Settings,
+) -> Result<(EncodeOptions, Option<&'static str>), String> {
+    let options = EncodeOptions {
+        container: output_container(input_path, settings),
+        video_codec: settings.video_codec,
+        preset: settings.preset,
+        quality: Some(settings.quality),
+        target_size_mb: settings.target_size_mb,
+        dimensions: settings.dimensions,
+        fps: settings.fps,
+        mute_audio: settings.mute_audio,
+        audio: settings.audio,
+        streams: settings.streams.clone(),
+        subtitles: settings.subtitles.clone(),
+        metadata: settings.metadata,
+        hdr: settings.hdr,
+        transforms: if settings.trim.is_empty() {
+            Vec::new()
+        } else {
+            vec![VideoTransform::Trim(settings.trim.clone())]
+        },
+    };
+    options.validate().map_err(|errors| errors.to_string())?;
+    let video_encoder = settings
+        .capabilities
+        .as_deref()
+        .map(|capabilities| encoder::select_video_encoder(&options, capabilities))
+        .transpose()?;
+
+    Ok((options, video_encoder))
+}
 
-    args.push(output_path.display().to_string());
-    args.push("-y".to_string());
+fn build_encode_request(
+    input_path: &Path,
+    output_path: &Path,
+    settings: &Settings,
+    details: Option<&InputDetails>,
+) -> Result<EncodeRequest, String> {
+    let (options, video_encoder) = encode_settings(input_path, settings)?;
+    let streams = match details {
+        Some(details) => {
+            let streams = encoder::select_streams(
+                &options,
+                &details.audio_languages,
+                &details.subtitle_languages,
+            )?;
+            encoder::check_subtitle_codecs(&options, streams.as_ref(), &details.subtitle_codecs)?;
+            streams
+        }
+        None if !encoder::picks_streams(&options) => None,
+        None => {
+            return Err(format!(
+                "Could not read the streams of {} to select them.",
+                input_path.display()
+            ))
+        }
+    };
 
-    args
+    let hdr = encoder::hdr_conversion(
+        &options,
+        details.and_then(|details| details.hdr),
+        settings.capabilities.as_deref(),
+    )?;
+
+    Ok(EncodeRequest {
+        input_path: input_path.display().to_string(),
+        output_path: output_path.display().to_string(),
+        options,
+        video_encoder,
+        report_progress: true,
+        clip: None,
+        has_audio: details.is_none_or(|details| !details.audio_languages.is_empty()),
+        loudness: None,
+        streams,
+        hdr,
+    })
 }
 
-fn quality_to_crf(quality: u16) -> u16 {
-    let max_crf: u16 = 36;
-    let min_crf: u16 = 24;
-    let diff = (max_crf - min_crf) - ((max_crf - min_crf) * quality) / 100;
-    min_crf + diff
+/// What ffprobe reports about an input.
+struct InputDetails {
+    duration_seconds: Option<f64>,
+    /// Language of each audio stream, in order.
+    audio_languages: Vec<Option<String>>,
+    /// Language of each subtitle stream, in order.
+    subtitle_languages: Vec<Option<String>>,
+    /// Codec of each subtitle stream, in order.
+    subtitle_codecs: Vec<Option<String>>,
+    /// HDR transfer of the first video stream.
+    hdr: Option<HdrFormat>,
 }
 
-fn probe_duration_us(ffmpeg_path: &Path, input_path: &Path) -> Result<Option<u64>, String> {
-    let input_arg = input_path.display().to_string();
-    let output = Command::new(ffmpeg_path)
-        .args(["-i", &input_arg])
-        .stderr(Stdio::piped())
-        .stdout(Stdio::null())
-        .output()
-        .map_err(|e| e.to_string())?;
-
-    let stderr = String::from_utf8_lossy(&output.stderr);
-    let re = Regex::new(r"Duration: (?P<duration>\d{2}:\d{2}:\d{2}\.\d+)")
-        .map_err(|e| e.to_string())?;
-    for line in stderr.lines() {
-        if let Some(caps) = re.captures(line) {
-            if let Some(duration) = caps.name("duration") {
-                return Ok(parse_time_to_us(duration.as_str()));
-            }
-        }
-    }
+fn probe_input_details(ffprobe_path: &Path, input_path: &Path) -> Result<InputDetails, String> {
+    let media_info = probe::probe_media(ffprobe_path, input_path)?;
+    Ok(InputDetails {
+        duration_seconds: media_info.duration_seconds(),
+        audio_languages: media_info.stream_languages(StreamKind::Audio),
+        subtitle_languages: media_info.stream_languages(StreamKind::Subtitle),
+        subtitle_codecs: media_info
+            .streams_of(StreamKind::Subtitle)
+            .map(|stream| stream.codec_name.clone())
+            .collect(),
+        hdr: media_info
+            .primary_video_stream()
+            .and_then(|stream| stream.hdr_format()),
+    })
+}
 
-    Ok(None)
+/// Displayed size of the first video stream, with its rotation applied.
+fn probe_video_dimensions(
+    ffprobe_path: &Path,
+    input_path: &Path,
+) -> Result<Option<(u32, u32)>, String> {
+    let media_info = probe::probe_media(ffprobe_path, input_path)?;
+    Ok(media_info
+        .primary_video_stream()
+        .and_then(|stream| stream.display_dimensions()))
 }
 
-fn parse_out_time_us(line: &str) -> Option<u64> {
-    if let Some(value) = line.strip_prefix("out_time_ms=") {
-        return value.trim().parse::<u64>().ok();
+/// Speed, ETA and size details shown after the bar.
+fn describe_progress(report: &EncodeProgress) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    if let Some(speed) = report.speed {
+        parts.push(format!("{speed:.1}x"));
     }
-    if let Some(value) = line.strip_prefix("out_time=") {
-        return parse_time_to_us(value.trim());
+    if let Some(eta) = report.eta_seconds {
+        let eta = eta.round() as u64;
+        parts.push(format!("ETA {:02}:{:02}:{:02}", eta / 3600, eta / 60 % 60, eta % 60));
     }
-    None
-}
-
-fn parse_time_to_us(value: &str) -> Option<u64> {
-    let parts: Vec<&str> = value.split(':').collect();
-    if parts.len() != 3 {
-        return None;
+    match (report.current_size, report.projected_size) {
+        (Some(current), Some(projected)) => parts.push(format!(
+            "{:.1} MB of ~{:.1} MB",
+            current as f64 / 1_000_000.0,
+            projected as f64 / 1_000_000.0
+        )),
+        (Some(current), None) => parts.push(format!("{:.1} MB", current as f64 / 1_000_000.0)),
+        _ => {}
     }
-    let hours = parts[0].parse::<u64>().ok()?;
-    let minutes = parts[1].parse::<u64>().ok()?;
-    let seconds = parts[2].parse::<f64>().ok()?;
-    let total_seconds = (hours * 3600) as f64 + (minutes * 60) as f64 + seconds;
-    Some((total_seconds * 1_000_000.0) as u64)
+    parts.join(", ")
 }
 
 fn resolve_ffmpeg_path() -> Result<PathBuf, String> {
-    if let Ok(path) = env::var("COMPRESSO_FFMPEG_PATH") {
+    resolve_tool_path("ffmpeg", "COMPRESSO_FFMPEG_PATH")
+}
+
+fn resolve_ffprobe_path() -> Result<PathBuf, String> {
+    resolve_tool_path("ffprobe", "COMPRESSO_FFPROBE_PATH")
+}
+
+/// Looks up the `compresso_<tool>` sidecar next to the CLI or in the repo,
+/// falling back to `<tool>` on PATH.
+fn resolve_tool_path(tool: &str, env_var: &str) -> Result<PathBuf, String> {
+    if let Ok(path) = env::var(env_var) {
         let path = PathBuf::from(path);
         if path.exists() {
             return Ok(path);
@@ -645,15 +1681,16 @@ fn resolve_ffmpeg_path() -> Result<PathBuf, String> {
     }
 
     let exe_suffix = if cfg!(windows) { ".exe" } else { "" };
+    let sidecar = format!("compresso_{tool}");
 
     if let Ok(exe_path) = env::current_exe() {
         if let Some(exe_dir) = exe_path.parent() {
-            let direct = exe_dir.join(format!("compresso_ffmpeg{exe_suffix}"));
+            let direct = exe_dir.join(format!("{sidecar}{exe_suffix}"));
             if direct.exists() {
                 return Ok(direct);
             }
 
-            let bin_sidecar = exe_dir.join("bin").join(format!("compresso_ffmpeg{exe_suffix}"));
+            let bin_sidecar = exe_dir.join("bin").join(format!("{sidecar}{exe_suffix}"));
             if bin_sidecar.exists() {
                 return Ok(bin_sidecar);
             }
@@ -661,7 +1698,7 @@ fn resolve_ffmpeg_path() -> Result<PathBuf, String> {
     }
 
     if let Ok(cwd) = env::current_dir() {
-        let target = format!("compresso_ffmpeg-{}{}", target_triple(), exe_suffix);
+        let target = format!("{}-{}{}", sidecar, target_triple(), exe_suffix);
         let repo_sidecar = cwd.join("src-tauri").join("bin").join(&target);
         if repo_sidecar.exists() {
             return Ok(repo_sidecar);
@@ -673,11 +1710,13 @@ fn resolve_ffmpeg_path() -> Result<PathBuf, String> {
         }
     }
 
-    if let Some(path) = find_in_path("ffmpeg") {
+    if let Some(path) = find_in_path(tool) {
         return Ok(path);
     }
 
-    Err("Could not locate ffmpeg binary. Set COMPRESSO_FFMPEG_PATH or place compresso_ffmpeg alongside the CLI.".to_string())
+    Err(format!(
+        "Could not locate {tool} binary. Set {env_var} or place {sidecar} alongside the CLI."
+    ))
 }
 
 fn target_triple() -> String {
@@ -712,41 +1751,161 @@ fn find_in_path(command: &str) -> Option<PathBuf> {
     None
 }
 
+/// e.g. `aac, 96 kbps, mono, normalized`
+fn describe_audio(audio: &AudioOptions) -> String {
+    let mut parts: Vec<String> = Vec::new();
+    parts.push(
+        audio
+            .codec
+            .map(|codec| codec.as_ref().to_string())
+            .unwrap_or_else(|| "default codec".to_string()),
+    );
+    if let Some(kbps) = audio.bitrate_kbps {
+        parts.push(format!("{kbps} kbps"));
+    }
+    match audio.channels {
+        Some(1) => parts.push("mono".to_string()),
+        Some(channels) => parts.push(format!("{channels} channels")),
+        None => {}
+    }
+    if let Some(sample_rate) = audio.sample_rate {
+        parts.push(format!("{sample_rate} Hz"));
+    }
+    if audio.normalize {
+        parts.push("normalized".to_string());
+    }
+    parts.join(", ")
+}
+
+/// `all`, or the picked stream numbers and languages.
+fn describe_stream_selection(selection: &StreamSelection) -> String {
+    match selection {
+        StreamSelection::Default => "default".to_string(),
+        StreamSelection::All => "all".to_string(),
+        StreamSelection::Pick(selectors) => selectors
+            .iter()
+            .map(|selector| match selector {
+                StreamSelector::Index(index) => index.to_string(),
+                StreamSelector::Language(language) => language.clone(),
+            })
+            .collect::<Vec<String>>()
+            .join(", "),
+    }
+}
+
+/// `0:10-0:45`, or `1:20-end` for a range that runs to the end.
+fn describe_trim_range(range: &TrimRange) -> String {
+    let format_time = |seconds: f64| {
+        let millis = (seconds * 1000.0).round() as u64;
+        let whole = millis / 1000;
+        let time = if whole >= 3600 {
+            let (hours, minutes) = (whole / 3600, whole % 3600 / 60);
+            format!("{hours}:{minutes:02}:{:02}", whole % 60)
+        } else {
+            format!("{}:{:02}", whole / 60, whole % 60)
+        };
+        match millis % 1000 {
+            0 => time,
+            fraction => format!("{time}.{fraction:03}"),
+        }
+    };
+    let end = range
+        .end_seconds
+        .map(format_time)
+        .unwrap_or_else(|| String::from("end"));
+    format!("{}-{end}", format_time(range.start_seconds))
+}
+
 fn print_summary(settings: &Settings, total_files: usize) {
-    println!("\nBatch summary");
-    println!("Files: {total_files}");
-    println!("Output folder: {}", settings.output_dir.display());
-    println!(
+    status!("\nBatch summary");
+    status!("Files: {total_files}");
+    if let Some(profile_name) = &settings.profile_name {
+        status!("Profile: {profile_name}");
+    }
+    status!("Output folder: {}", settings.output_dir.display());
+    status!(
         "Format: {}",
         match settings.output_format {
             OutputFormat::SameAsSource => "same as source",
-            OutputFormat::Fixed(ext) => ext,
+            OutputFormat::Fixed(container) => container.extension(),
         }
     );
-    println!(
-        "Preset: {}",
-        match settings.preset {
-            Preset::Default => "default",
-            Preset::Thunderbolt => "thunderbolt",
-        }
+    status!(
+        "Codec: {}",
+        settings
+            .video_codec
+            .as_ref()
+            .map(|codec| codec.as_ref())
+            .unwrap_or("default for format")
     );
-    println!("Quality: {}", settings.quality);
-    println!(
+    status!("Preset: {}", settings.preset.as_ref());
+    match settings.target_size_mb {
+        Some(size) => status!("Target size: {size} MB (two-pass)"),
+        None => status!("Quality: {}", settings.quality),
+    }
+    status!(
         "FPS: {}",
         settings
             .fps
             .map(|fps| fps.to_string())
             .unwrap_or_else(|| "original".to_string())
     );
-    println!(
+    status!(
         "Resize: {}",
         settings
             .dimensions
             .map(|(w, h)| format!("{w}x{h}"))
             .unwrap_or_else(|| "no".to_string())
     );
-    println!("Mute audio: {}", if settings.mute_audio { "yes" } else { "no" });
-    println!(
+    status!("Mute audio: {}", if settings.mute_audio { "yes" } else { "no" });
+    if !settings.mute_audio && settings.audio != AudioOptions::default() {
+        status!("Audio: {}", describe_audio(&settings.audio));
+    }
+    if !settings.mute_audio && settings.streams.audio != StreamSelection::Default {
+        let mut audio_streams = describe_stream_selection(&settings.streams.audio);
+        if settings.streams.mix_audio {
+            audio_streams.push_str(", mixed");
+        }
+        status!("Audio streams: {audio_streams}");
+    }
+    if settings.streams.subtitles != StreamSelection::Default {
+        status!(
+            "Subtitle streams: {}",
+            describe_stream_selection(&settings.streams.subtitles)
+        );
+    }
+    if !settings.subtitles.files.is_empty() {
+        status!("Subtitle files: {}", settings.subtitles.files.join(", "));
+    }
+    match &settings.subtitles.burn_in {
+        Some(SubtitleBurnIn::Stream(position)) => {
+            status!("Burn in subtitles: stream {position}")
+        }
+        Some(SubtitleBurnIn::File(file)) => status!("Burn in subtitles: {file}"),
+        None => {}
+    }
+    if settings.metadata.mode != MetadataMode::Default {
+        status!("Metadata: {}", settings.metadata.mode.as_ref());
+    }
+    if settings.metadata.copy_file_times {
+        status!("Keep file times: yes");
+    }
+    if settings.hdr != HdrMode::default() {
+        status!("HDR: {}", settings.hdr.as_ref());
+    }
+    if !settings.trim.is_empty() {
+        let ranges: Vec<String> = settings.trim.iter().map(describe_trim_range).collect();
+        status!("Keep: {}", ranges.join(", "));
+    }
+    status!("Parallel jobs: {}", settings.jobs);
+    if settings.force {
+        status!("Re-encode converted files: yes");
+    }
+    status!("File names: {}", settings.name_template.as_str());
+    if settings.mirror_tree {
+        status!("Recreate subfolders: yes");
+    }
+    status!(
         "On conflict: {}",
         match settings.conflict_policy {
             ConflictPolicy::Overwrite => "overwrite",
@@ -0,0 +1,270 @@
+use lib::domain::EncodeOptions;
+use serde::{Deserialize, Serialize};
+use std::{
+    collections::HashMap,
+    fs,
+    path::{Path, PathBuf},
+    time::UNIX_EPOCH,
+};
+
+/// Kept in the output folder, records which inputs were converted with which
+/// settings so a re-run can skip them.
+pub const MANIFEST_FILE_NAME: &str = ".compresso-manifest.json";
+
+/// Size and modification time of an input when it was converted.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SourceStamp {
+    pub size: u64,
+    /// Nanoseconds since the Unix epoch, `None` when the platform has no mtime.
+    pub modified_ns: Option<u128>,
+}
+
+impl SourceStamp {
+    pub fn read(path: &Path) -> Option<Self> {
+        let metadata = fs::metadata(path).ok()?;
+        let modified_ns = metadata
+            .modified()
+            .ok()
+            .and_then(|modified| modified.duration_since(UNIX_EPOCH).ok())
+            .map(|since_epoch| since_epoch.as_nanos());
+        Some(Self {
+            size: metadata.len(),
+            modified_ns,
+        })
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ManifestEntry {
+    pub source: SourceStamp,
+    pub settings_hash: String,
+    pub output_path: PathBuf,
+    pub output_size: u64,
+}
+
+/// What a re-run should do with one input.
+pub enum ManifestCheck {
+    /// Converted before with the same source and settings, output still intact.
+    Done(PathBuf),
+    /// Converted before, but the source, settings or output changed. Carries
+    /// the previous output path so it can be replaced.
+    Stale(PathBuf),
+    New,
+}
+
+#[derive(Serialize, Deserialize, Default, Debug)]
+pub struct Manifest {
+    /// Keyed by the canonical input path.
+    entries: HashMap<PathBuf, ManifestEntry>,
+    #[serde(skip)]
+    path: PathBuf,
+}
+
+impl Manifest {
+    /// Loads the manifest of `output_dir`. A missing or unreadable file starts empty.
+    pub fn load(output_dir: &Path) -> Self {
+        let path = output_dir.join(MANIFEST_FILE_NAME);
+        let mut manifest = fs::read_to_string(&path)
+            .ok()
+            .and_then(|json| serde_json::from_str::<Manifest>(&json).ok())
+            .unwrap_or_default();
+        manifest.path = path;
+        manifest
+    }
+
+    pub fn check(&self, input_path: &Path, settings_hash: &str) -> ManifestCheck {
+        let Some(entry) = self.entries.get(&manifest_key(input_path)) else {
+            return ManifestCheck::New;
+        };
+        let output_intact = fs::metadata(&entry.output_path)
+            .map(|metadata| metadata.len() == entry.output_size)
+            .unwrap_or(false);
+        if output_intact
+            && entry.settings_hash == settings_hash
+            && SourceStamp::read(input_path) == Some(entry.source)
+        {
+            ManifestCheck::Done(entry.output_path.clone())
+        } else {
+            ManifestCheck::Stale(entry.output_path.clone())
+        }
+    }
+
+    /// Records a finished conversion and saves the manifest right away, so an
+    /// interrupted batch keeps the files that were already done.
+    pub fn record(
+        &mut self,
+        input_path: &Path,
+        settings_hash: &str,
+        output_path: &Path,
+    ) -> Result<(), String> {
+        let source = SourceStamp::read(input_path)
+            .ok_or_else(|| format!("Could not read {}", input_path.display()))?;
+        let output_size = fs::metadata(output_path).map_err(|e| e.to_string())?.len();
+        self.entries.insert(
+            manifest_key(input_path),
+            ManifestEntry {
+                source,
+                settings_hash: settings_hash.to_string(),
+                output_path: output_path.to_path_buf(),
+                output_size,
+            },
+        );
+        self.save()
+    }
+
+    fn save(&self) -> Result<(), String> {
+        let json = serde_json::to_vec_pretty(self).map_err(|e| e.to_string())?;
+        // Written next to the real file first so a crash never leaves half a manifest.
+        let temp_path = self.path.with_extension("json.tmp");
+        fs::write(&temp_path, json)
+            .and_then(|_| fs::rename(&temp_path, &self.path))
+            .map_err(|e| format!("Could not save {}: {e}", self.path.display()))
+    }
+}
+
+fn manifest_key(input_path: &Path) -> PathBuf {
+    fs::canonicalize(input_path).unwrap_or_else(|_| input_path.to_path_buf())
+}
+
+/// Stable hash of the options and encoder that shape an output.
+pub fn settings_hash(options: &EncodeOptions, video_encoder: Option<&str>) -> String {
+    let options = serde_json::to_string(options).unwrap_or_default();
+    let encoder = video_encoder.unwrap_or_default();
+    format!("{:016x}", fnv1a(format!("{options}|{encoder}").as_bytes()))
+}
+
+/// 64-bit FNV-1a, unlike `DefaultHasher` stable across Rust releases.
+fn fnv1a(bytes: &[u8]) -> u64 {
+    bytes.iter().fold(0xcbf2_9ce4_8422_2325, |hash, byte| {
+        (hash ^ u64::from(*byte)).wrapping_mul(0x0100_0000_01b3)
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nanoid::nanoid;
+    use serde_json::json;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("compresso-manifest-{}", nanoid!()));
+            fs::create_dir_all(&dir).unwrap();
+            Self(dir)
+        }
+
+        fn file(&self, name: &str, contents: &str) -> PathBuf {
+            let path = self.0.join(name);
+            fs::write(&path, contents).unwrap();
+            path
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn options(quality: u16) -> EncodeOptions {
+        serde_json::from_value(json!({ "container": "mp4", "quality": quality })).unwrap()
+    }
+
+    #[test]
+    fn fnv1a_matches_reference_values() {
+        assert_eq!(fnv1a(b""), 0xcbf2_9ce4_8422_2325);
+        assert_eq!(fnv1a(b"a"), 0xaf63_dc4c_8601_ec8c);
+        assert_eq!(fnv1a(b"foobar"), 0x8594_4171_f739_67e8);
+    }
+
+    #[test]
+    fn settings_hash_depends_on_options_and_encoder() {
+        let hash = settings_hash(&options(60), Some("libx264"));
+
+        assert_eq!(hash.len(), 16);
+        assert_eq!(hash, settings_hash(&options(60), Some("libx264")));
+        assert_ne!(hash, settings_hash(&options(70), Some("libx264")));
+        assert_ne!(hash, settings_hash(&options(60), Some("libx265")));
+        assert_ne!(hash, settings_hash(&options(60), None));
+    }
+
+    #[test]
+    fn recorded_conversions_survive_a_reload() {
+        let dir = TempDir::new();
+        let input = dir.file("clip.mov", "source");
+        let output = dir.file("clip_compressed.mp4", "output");
+
+        let mut manifest = Manifest::load(&dir.0);
+        assert!(matches!(manifest.check(&input, "hash"), ManifestCheck::New));
+        manifest.record(&input, "hash", &output).unwrap();
+
+        assert!(dir.0.join(MANIFEST_FILE_NAME).exists());
+        assert!(!dir.0.join(".compresso-manifest.json.tmp").exists());
+        let reloaded = Manifest::load(&dir.0);
+        assert!(matches!(
+            reloaded.check(&input, "hash"),
+            ManifestCheck::Done(path) if path == output
+        ));
+    }
+
+    #[test]
+    fn changed_settings_source_or_output_are_stale() {
+        let dir = TempDir::new();
+        let input = dir.file("clip.mov", "source");
+        let output = dir.file("clip_compressed.mp4", "output");
+        let mut manifest = Manifest::load(&dir.0);
+        manifest.record(&input, "hash", &output).unwrap();
+
+        assert!(matches!(
+            manifest.check(&input, "other"),
+            ManifestCheck::Stale(path) if path == output
+        ));
+
+        fs::write(&output, "truncated").unwrap();
+        assert!(matches!(
+            manifest.check(&input, "hash"),
+            ManifestCheck::Stale(_)
+        ));
+
+        manifest.record(&input, "hash", &output).unwrap();
+        fs::write(&input, "new source").unwrap();
+        assert!(matches!(
+            manifest.check(&input, "hash"),
+            ManifestCheck::Stale(_)
+        ));
+
+        manifest.record(&input, "hash", &output).unwrap();
+        fs::remove_file(&output).unwrap();
+        assert!(matches!(
+            manifest.check(&input, "hash"),
+            ManifestCheck::Stale(_)
+        ));
+    }
+
+    #[test]
+    fn unreadable_manifest_starts_empty() {
+        let dir = TempDir::new();
+        let input = dir.file("clip.mov", "source");
+        dir.file(MANIFEST_FILE_NAME, "{ not json");
+
+        let manifest = Manifest::load(&dir.0);
+        assert!(manifest.entries.is_empty());
+        assert!(matches!(manifest.check(&input, "hash"), ManifestCheck::New));
+    }
+
+    #[test]
+    fn recording_a_missing_output_fails() {
+        let dir = TempDir::new();
+        let input = dir.file("clip.mov", "source");
+        let mut manifest = Manifest::load(&dir.0);
+
+        assert!(manifest
+            .record(&input, "hash", &dir.0.join("missing.mp4"))
+            .is_err());
+        assert!(manifest.entries.is_empty());
+    }
+}
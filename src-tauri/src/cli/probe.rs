@@ -0,0 +1,169 @@
+use lib::{
+    domain::{MediaInfo, StreamInfo, StreamKind},
+    probe,
+};
+use std::{path::Path, process::Command};
+
+pub fn run_probe(ffprobe_path: &Path, file: &Path, json: bool) -> Result<(), String> {
+    if !file.exists() {
+        return Err(format!("File does not exist: {}", file.display()));
+    }
+
+    let media_info = probe_media(ffprobe_path, file)?;
+
+    if json {
+        let output = serde_json::to_string_pretty(&media_info).map_err(|e| e.to_string())?;
+        println!("{output}");
+    } else {
+        print_media_info(file, &media_info);
+    }
+
+    Ok(())
+}
+
+/// Runs ffprobe on `file` and parses its JSON output.
+pub fn probe_media(ffprobe_path: &Path, file: &Path) -> Result<MediaInfo, String> {
+    let output = Command::new(ffprobe_path)
+        .args(probe::ffprobe_args(&file.display().to_string()))
+        .output()
+        .map_err(|e| e.to_string())?;
+
+    if !output.status.success() {
+        let stderr = String::from_utf8_lossy(&output.stderr);
+        return Err(format!("ffprobe failed: {}", stderr.trim()));
+    }
+
+    probe::parse_ffprobe_json(&String::from_utf8_lossy(&output.stdout))
+}
+
+fn print_media_info(file: &Path, info: &MediaInfo) {
+    println!("{}", file.display());
+
+    let format = &info.format;
+    println!(
+        "Container: {}",
+        format
+            .format_long_name
+            .as_deref()
+            .or(format.format_name.as_deref())
+            .unwrap_or("unknown")
+    );
+    if let Some(duration) = info.duration_seconds() {
+        println!("Duration: {}", format_duration(duration));
+    }
+    if let Some(size) = format.size {
+        println!("Size: {:.2} MB", size as f64 / 1_000_000.0);
+    }
+    if let Some(bit_rate) = format.bit_rate {
+        println!("Bitrate: {} kb/s", bit_rate / 1000);
+    }
+    for (key, value) in &format.tags {
+        println!("  {key}: {value}");
+    }
+
+    println!("\nStreams");
+    for stream in &info.streams {
+        println!("  {}", describe_stream(stream));
+    }
+
+    if !info.chapters.is_empty() {
+        println!("\nChapters");
+        for chapter in &info.chapters {
+            println!(
+                "  #{} {} - {}{}",
+                chapter.id,
+                format_duration(chapter.start_seconds),
+                format_duration(chapter.end_seconds),
+                chapter
+                    .title
+                    .as_deref()
+                    .map(|title| format!(" {title}"))
+                    .unwrap_or_default()
+            );
+        }
+    }
+}
+
+fn describe_stream(stream: &StreamInfo) -> String {
+    let mut parts: Vec<String> = Vec::new();
+
+    if let Some(codec) = &stream.codec_name {
+        match &stream.profile {
+            Some(profile) => parts.push(format!("{codec} ({profile})")),
+            None => parts.push(codec.clone()),
+        }
+    }
+
+    match stream.kind {
+        StreamKind::Video => {
+            if let (Some(width), Some(height)) = (stream.width, stream.height) {
+                parts.push(format!("{width}x{height}"));
+            }
+            if let Some(frame_rate) = stream.frame_rate {
+                parts.push(format!("{frame_rate:.2} fps"));
+            }
+            if let Some(pix_fmt) = &stream.pix_fmt {
+                match stream.bit_depth {
+                    Some(depth) => parts.push(format!("{pix_fmt} {depth}-bit")),
+                    None => parts.push(pix_fmt.clone()),
+                }
+            }
+            if let Some(transfer) = &stream.color_transfer {
+                parts.push(format!(
+                    "{}/{}/{}",
+                    stream.color_primaries.as_deref().unwrap_or("unknown"),
+                    transfer,
+                    stream.color_space.as_deref().unwrap_or("unknown")
+                ));
+            }
+            if let Some(hdr) = stream.hdr_format() {
+                parts.push(format!("HDR {}", hdr.as_ref().to_uppercase()));
+            }
+            if let Some(rotation) = stream.rotation.filter(|rotation| *rotation != 0) {
+                parts.push(format!("rotated {rotation}°"));
+            }
+            if stream.is_attached_picture {
+                parts.push(String::from("cover art"));
+            }
+        }
+        StreamKind::Audio => {
+            if let Some(sample_rate) = stream.sample_rate {
+                parts.push(format!("{sample_rate} Hz"));
+            }
+            if let Some(layout) = &stream.channel_layout {
+                parts.push(layout.clone());
+            } else if let Some(channels) = stream.channels {
+                parts.push(format!("{channels} channels"));
+            }
+        }
+        _ => {}
+    }
+
+    if let Some(bit_rate) = stream.bit_rate {
+        parts.push(format!("{} kb/s", bit_rate / 1000));
+    }
+    if let Some(language) = &stream.language {
+        parts.push(format!("[{language}]"));
+    }
+    if let Some(title) = &stream.title {
+        parts.push(format!("\"{title}\""));
+    }
+    if stream.is_default {
+        parts.push(String::from("default"));
+    }
+
+    format!(
+        "#{} {}: {}",
+        stream.index,
+        stream.kind.as_ref(),
+        parts.join(", ")
+    )
+}
+
+fn format_duration(seconds: f64) -> String {
+    let total = seconds.max(0.0);
+    let hours = (total / 3600.0).floor();
+    let minutes = ((total - hours * 3600.0) / 60.0).floor();
+    let rest = total - hours * 3600.0 - minutes * 60.0;
+    format!("{:02}:{:02}:{:05.2}", hours as u64, minutes as u64, rest)
+}
@@ -0,0 +1,109 @@
+use lib::domain::FfmpegError;
+use serde::Serialize;
+use std::{fs, path::Path, time::Duration};
+
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum FileStatus {
+    Succeeded,
+    Failed,
+    Skipped,
+}
+
+/// Outcome of one input file, written by `--report` and `--json`.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FileReport {
+    pub input_path: String,
+    pub output_path: Option<String>,
+    pub status: FileStatus,
+    pub error: Option<FfmpegError>,
+    pub input_size: Option<u64>,
+    pub output_size: Option<u64>,
+    /// Input size divided by output size, e.g. 4.0 for a file four times smaller.
+    pub compression_ratio: Option<f64>,
+    /// Duration of the source video.
+    pub duration_seconds: Option<f64>,
+    /// Wall-clock time spent converting this file.
+    pub elapsed_seconds: f64,
+    /// Arguments passed to ffmpeg, one entry per pass.
+    pub ffmpeg_args: Vec<Vec<String>>,
+}
+
+impl FileReport {
+    pub fn new(input_path: &Path, output_path: Option<&Path>) -> Self {
+        Self {
+            input_path: input_path.display().to_string(),
+            output_path: output_path.map(|path| path.display().to_string()),
+            status: FileStatus::Skipped,
+            error: None,
+            input_size: fs::metadata(input_path).ok().map(|metadata| metadata.len()),
+            output_size: None,
+            compression_ratio: None,
+            duration_seconds: None,
+            elapsed_seconds: 0.0,
+            ffmpeg_args: Vec::new(),
+        }
+    }
+
+    pub fn finish(&mut self, result: Result<(), FfmpegError>, elapsed: Duration) {
+        self.elapsed_seconds = elapsed.as_secs_f64();
+        match result {
+            Ok(()) => {
+                self.status = FileStatus::Succeeded;
+                self.output_size = self
+                    .output_path
+                    .as_ref()
+                    .and_then(|path| fs::metadata(path).ok())
+                    .map(|metadata| metadata.len());
+                self.compression_ratio = match (self.input_size, self.output_size) {
+                    (Some(input), Some(output)) if output > 0 => Some(input as f64 / output as f64),
+                    _ => None,
+                };
+            }
+            Err(err) => {
+                self.status = FileStatus::Failed;
+                self.error = Some(err);
+            }
+        }
+    }
+}
+
+/// Results of a whole batch, in input order.
+#[derive(Serialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct BatchReport {
+    pub ffmpeg_path: String,
+    pub succeeded: u32,
+    pub failed: u32,
+    pub skipped: u32,
+    pub elapsed_seconds: f64,
+    pub files: Vec<FileReport>,
+}
+
+impl BatchReport {
+    pub fn new(ffmpeg_path: &Path, files: Vec<FileReport>, elapsed: Duration) -> Self {
+        let count =
+            |status: FileStatus| files.iter().filter(|file| file.status == status).count() as u32;
+        let succeeded = count(FileStatus::Succeeded);
+        let failed = count(FileStatus::Failed);
+        let skipped = count(FileStatus::Skipped);
+        Self {
+            ffmpeg_path: ffmpeg_path.display().to_string(),
+            succeeded,
+            failed,
+            skipped,
+            elapsed_seconds: elapsed.as_secs_f64(),
+            files,
+        }
+    }
+
+    pub fn to_json(&self) -> Result<String, String> {
+        serde_json::to_string_pretty(self).map_err(|e| e.to_string())
+    }
+
+    pub fn write(&self, path: &Path) -> Result<(), String> {
+        fs::write(path, self.to_json()?)
+            .map_err(|e| format!("Could not write report {}: {e}", path.display()))
+    }
+}
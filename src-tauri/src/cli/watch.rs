@@ -0,0 +1,294 @@
+use dialoguer::theme::ColorfulTheme;
+use indicatif::MultiProgress;
+use lib::domain::FfmpegCapabilities;
+use notify::{EventKind, RecursiveMode, Watcher};
+use std::{
+    collections::{HashMap, HashSet},
+    fs,
+    path::{Path, PathBuf},
+    sync::{mpsc, Arc},
+    time::{Duration, Instant},
+};
+
+use crate::{
+    args::WatchArgs,
+    build_output_path, convert_file, encode_settings, ensure_output_dir, is_video_file,
+    manifest::{self, Manifest, ManifestCheck},
+    report::FileReport,
+    settings_from_args, Settings, Tools,
+};
+
+/// How often pending files are checked for growth when no events arrive.
+const POLL_INTERVAL: Duration = Duration::from_secs(1);
+
+/// What happens to an original once it was compressed.
+enum AfterCompress {
+    Keep,
+    MoveTo(PathBuf),
+    Delete,
+}
+
+/// A new file that may still be written to.
+struct PendingFile {
+    size: Option<u64>,
+    unchanged_since: Instant,
+}
+
+/// Compresses every video that appears in `args.dir`, one at a time, until
+/// the process is interrupted.
+pub fn run_watch(
+    tools: &Tools,
+    args: WatchArgs,
+    capabilities: Option<Arc<FfmpegCapabilities>>,
+) -> Result<(), String> {
+    let theme = ColorfulTheme::default();
+
+    if !args.dir.is_dir() {
+        return Err(format!("Not a directory: {}", args.dir.display()));
+    }
+    let watch_dir = fs::canonicalize(&args.dir).map_err(|e| e.to_string())?;
+
+    ensure_output_dir(&theme, &args.output_dir, true)?;
+    let output_dir = fs::canonicalize(&args.output_dir).map_err(|e| e.to_string())?;
+    let mut settings = settings_from_args(
+        output_dir.clone(),
+        &args.encode,
+        args.on_conflict.into(),
+        capabilities,
+    )?;
+    settings.name_template = args.name_template.clone();
+    settings.mirror_tree = args.mirror_tree;
+
+    let after = match (&args.move_originals, args.delete_originals) {
+        (Some(dir), _) => {
+            ensure_output_dir(&theme, dir, true)?;
+            AfterCompress::MoveTo(fs::canonicalize(dir).map_err(|e| e.to_string())?)
+        }
+        (None, true) => AfterCompress::Delete,
+        (None, false) => AfterCompress::Keep,
+    };
+
+    // Outputs and moved originals may live inside the watched folder.
+    let mut ignored_dirs = vec![output_dir];
+    if let AfterCompress::MoveTo(dir) = &after {
+        ignored_dirs.push(dir.clone());
+    }
+    // Everything in them is ignored, which would be every new file.
+    if let Some(dir) = ignored_dirs.iter().find(|dir| watch_dir.starts_with(dir)) {
+        return Err(format!(
+            "{} is or contains the watched folder, pick a folder inside or outside of the watched one.",
+            dir.display()
+        ));
+    }
+
+    let (sender, receiver) = mpsc::channel();
+    let mut watcher = notify::recommended_watcher(sender).map_err(|e| e.to_string())?;
+    let mode = if args.recursive {
+        RecursiveMode::Recursive
+    } else {
+        RecursiveMode::NonRecursive
+    };
+    watcher
+        .watch(&watch_dir, mode)
+        .map_err(|e| format!("Could not watch {}: {e}", watch_dir.display()))?;
+
+    let mut manifest = Manifest::load(&settings.output_dir);
+    let settle = Duration::from_secs(args.settle);
+    let mut pending: HashMap<PathBuf, PendingFile> = HashMap::new();
+    // Counts the files picked up since the watch started, for `{index}`.
+    let mut picked_up = 0usize;
+
+    status!(
+        "Watching {} for new videos, press Ctrl+C to stop.",
+        watch_dir.display()
+    );
+
+    loop {
+        match receiver.recv_timeout(POLL_INTERVAL) {
+            Ok(Ok(event)) => {
+                if matches!(event.kind, EventKind::Create(_) | EventKind::Modify(_)) {
+                    for path in event.paths {
+                        let is_ignored = ignored_dirs.iter().any(|dir| path.starts_with(dir));
+                        if !is_ignored && is_video_file(&path) {
+                            pending.entry(path).or_insert(PendingFile {
+                                size: None,
+                                unchanged_since: Instant::now(),
+                            });
+                        }
+                    }
+                }
+            }
+            Ok(Err(err)) => status!("Warning: watch error: {err}"),
+            Err(mpsc::RecvTimeoutError::Timeout) => {}
+            Err(mpsc::RecvTimeoutError::Disconnected) => {
+                return Err("The folder watcher stopped.".to_string());
+            }
+        }
+
+        let mut ready: Vec<PathBuf> = Vec::new();
+        pending.retain(|path, file| {
+            let Some(size) = fs::metadata(path)
+                .ok()
+                .filter(|m| m.is_file())
+                .map(|m| m.len())
+            else {
+                // Removed or renamed before it settled.
+                return false;
+            };
+            if file.size != Some(size) {
+                file.size = Some(size);
+                file.unchanged_since = Instant::now();
+            } else if size > 0 && file.unchanged_since.elapsed() >= settle {
+                ready.push(path.clone());
+                return false;
+            }
+            true
+        });
+
+        ready.sort();
+        for input_path in ready {
+            picked_up += 1;
+            let relative_dir = input_path
+                .parent()
+                .and_then(|dir| dir.strip_prefix(&watch_dir).ok())
+                .map(Path::to_path_buf)
+                .unwrap_or_default();
+            compress_new_file(
+                tools,
+                &input_path,
+                &relative_dir,
+                picked_up,
+                &settings,
+                &mut manifest,
+                &after,
+            );
+        }
+    }
+}
+
+fn compress_new_file(
+    tools: &Tools,
+    input_path: &Path,
+    relative_dir: &Path,
+    index: usize,
+    settings: &Settings,
+    manifest: &mut Manifest,
+    after: &AfterCompress,
+) {
+    let settings_hash = encode_settings(input_path, settings)
+        .ok()
+        .map(|(options, video_encoder)| manifest::settings_hash(&options, video_encoder));
+    if let Some(ManifestCheck::Done(_)) = settings_hash
+        .as_deref()
+        .map(|hash| manifest.check(input_path, hash))
+    {
+        status!("Skipped (already converted): {}", input_path.display());
+        return;
+    }
+
+    let output_path = match build_output_path(
+        tools,
+        input_path,
+        relative_dir,
+        index,
+        settings,
+        None,
+        &HashSet::new(),
+    ) {
+        Ok(Some(path)) => path,
+        Ok(None) => {
+            status!("Skipped (output exists): {}", input_path.display());
+            return;
+        }
+        Err(err) => {
+            status!(
+                "Failed to resolve output path: {}: {err}",
+                input_path.display()
+            );
+            return;
+        }
+    };
+
+    status!("Compressing {}", input_path.display());
+    let started = Instant::now();
+    let mut file_report = FileReport::new(input_path, Some(&output_path));
+    let result = convert_file(
+        tools,
+        input_path,
+        &output_path,
+        settings,
+        &MultiProgress::new(),
+        &mut file_report,
+    );
+    file_report.finish(result.clone(), started.elapsed());
+
+    if let Err(err) = result {
+        status!("Failed: {}: {err}", input_path.display());
+        if let Some(stderr) = &err.stderr {
+            for line in stderr.lines() {
+                status!("  {line}");
+            }
+        }
+        return;
+    }
+
+    status!(
+        "Saved to {} ({} -> {}, {:.0}s)",
+        output_path.display(),
+        format_size(file_report.input_size),
+        format_size(file_report.output_size),
+        file_report.elapsed_seconds
+    );
+
+    let handled = match after {
+        AfterCompress::Keep => match settings_hash {
+            Some(hash) => manifest.record(input_path, &hash, &output_path),
+            None => Ok(()),
+        },
+        AfterCompress::MoveTo(dir) => move_original(input_path, dir).map(|moved_to| {
+            status!("Moved original to {}", moved_to.display());
+        }),
+        AfterCompress::Delete => fs::remove_file(input_path)
+            .map(|_| status!("Deleted original {}", input_path.display()))
+            .map_err(|e| format!("Could not delete {}: {e}", input_path.display())),
+    };
+    if let Err(err) = handled {
+        status!("Warning: {err}");
+    }
+}
+
+/// Moves `input_path` into `dir`, numbering the name when it is taken.
+fn move_original(input_path: &Path, dir: &Path) -> Result<PathBuf, String> {
+    let file_name = input_path
+        .file_name()
+        .ok_or_else(|| format!("Invalid file name: {}", input_path.display()))?;
+    let mut target = dir.join(file_name);
+    let stem = input_path
+        .file_stem()
+        .map(|stem| stem.to_string_lossy().to_string())
+        .unwrap_or_default();
+    let extension = input_path
+        .extension()
+        .map(|ext| format!(".{}", ext.to_string_lossy()))
+        .unwrap_or_default();
+    let mut index = 1u32;
+    while target.exists() {
+        target = dir.join(format!("{stem}_{index}{extension}"));
+        index += 1;
+    }
+
+    // `rename` fails across drives, fall back to copying.
+    if fs::rename(input_path, &target).is_err() {
+        fs::copy(input_path, &target)
+            .and_then(|_| fs::remove_file(input_path))
+            .map_err(|e| format!("Could not move {}: {e}", input_path.display()))?;
+    }
+    Ok(target)
+}
+
+fn format_size(size: Option<u64>) -> String {
+    match size {
+        Some(size) => format!("{:.1} MB", size as f64 / 1_000_000.0),
+        None => "? MB".to_string(),
+    }
+}
@@ -0,0 +1,307 @@
+use once_cell::sync::Lazy;
+use std::{
+    collections::HashMap,
+    path::{Path, PathBuf},
+    process::Command,
+    sync::{Arc, Mutex},
+    time::SystemTime,
+};
+
+use crate::domain::{Container, EncoderInfo, FfmpegCapabilities, StreamKind, VideoCodec};
+
+struct CachedCapabilities {
+    modified: Option<SystemTime>,
+    capabilities: Arc<FfmpegCapabilities>,
+}
+
+// Keyed by binary path, invalidated when the binary's mtime changes
+static CAPABILITIES_CACHE: Lazy<Mutex<HashMap<PathBuf, CachedCapabilities>>> =
+    Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Lists the encoders, muxers and filters of the ffmpeg binary at `ffmpeg_path`.
+pub fn detect(ffmpeg_path: &Path) -> Result<Arc<FfmpegCapabilities>, String> {
+    let modified = std::fs::metadata(ffmpeg_path)
+        .and_then(|metadata| metadata.modified())
+        .ok();
+
+    if let Ok(cache) = CAPABILITIES_CACHE.lock() {
+        if let Some(cached) = cache.get(ffmpeg_path) {
+            if cached.modified == modified {
+                return Ok(cached.capabilities.clone());
+            }
+        }
+    }
+
+    let encoders = parse_encoders(&run_listing(ffmpeg_path, "-encoders")?);
+    let muxers = parse_muxers(&run_listing(ffmpeg_path, "-muxers")?);
+    let filters = parse_filters(&run_listing(ffmpeg_path, "-filters")?);
+    let capabilities = Arc::new(from_listings(encoders, muxers, filters));
+
+    if let Ok(mut cache) = CAPABILITIES_CACHE.lock() {
+        cache.insert(
+            ffmpeg_path.to_path_buf(),
+            CachedCapabilities {
+                modified,
+                capabilities: capabilities.clone(),
+            },
+        );
+    }
+
+    Ok(capabilities)
+}
+
+fn run_listing(ffmpeg_path: &Path, listing: &str) -> Result<String, String> {
+    let output = Command::new(ffmpeg_path)
+        .args(["-hide_banner", listing])
+        .output()
+        .map_err(|err| format!("Could not run {}: {err}", ffmpeg_path.display()))?;
+    if !output.status.success() {
+        return Err(format!(
+            "ffmpeg {listing} failed: {}",
+            String::from_utf8_lossy(&output.stderr).trim()
+        ));
+    }
+    Ok(String::from_utf8_lossy(&output.stdout).to_string())
+}
+
+fn from_listings(
+    encoders: Vec<EncoderInfo>,
+    muxers: Vec<String>,
+    filters: Vec<String>,
+) -> FfmpegCapabilities {
+    let mut capabilities = FfmpegCapabilities {
+        encoders,
+        muxers,
+        filters,
+        ..Default::default()
+    };
+    capabilities.video_codecs = VideoCodec::ALL
+        .into_iter()
+        .filter(|codec| capabilities.video_encoder_for(*codec).is_some())
+        .collect();
+    capabilities.containers = Container::ALL
+        .into_iter()
+        .filter(|container| capabilities.has_muxer(container.muxer()))
+        .collect();
+    capabilities
+}
+
+/// Parses `ffmpeg -encoders`. Entries follow a ` ------` line and look like
+/// ` V....D libx264              libx264 H.264 / AVC ...`.
+pub fn parse_encoders(listing: &str) -> Vec<EncoderInfo> {
+    listing
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("---"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let flags = parts.next()?;
+            let name = parts.next()?;
+            let kind = match flags.chars().next()? {
+                'V' => StreamKind::Video,
+                'A' => StreamKind::Audio,
+                'S' => StreamKind::Subtitle,
+                _ => StreamKind::Unknown,
+            };
+            Some(EncoderInfo {
+                name: name.to_string(),
+                kind,
+                description: parts.collect::<Vec<&str>>().join(" "),
+            })
+        })
+        .collect()
+}
+
+/// Parses `ffmpeg -muxers`. Entries follow a ` --` line and look like
+/// `  E mp4             MP4 (MPEG-4 Part 14)`; one entry may list several
+/// comma-separated names.
+pub fn parse_muxers(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .skip_while(|line| !line.trim_start().starts_with("--"))
+        .skip(1)
+        .filter_map(|line| {
+            let mut parts = line.split_whitespace();
+            let flags = parts.next()?;
+            if !flags.contains('E') {
+                return None;
+            }
+            parts.next()
+        })
+        .flat_map(|names| names.split(','))
+        .map(String::from)
+        .collect()
+}
+
+/// Parses `ffmpeg -filters`. Entries look like
+/// ` ..C scale             V->V       Scale the input video size ...`;
+/// the legend above them has no `->` column.
+pub fn parse_filters(listing: &str) -> Vec<String> {
+    listing
+        .lines()
+        .filter_map(|line| {
+            let parts: Vec<&str> = line.split_whitespace().collect();
+            match parts.as_slice() {
+                [_, name, io, ..] if io.contains("->") => Some(name.to_string()),
+                _ => None,
+            }
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Excerpt of `ffmpeg -hide_banner -encoders` of a build without libsvtav1.
+    const ENCODERS: &str = "\
+Encoders:
+ V..... = Video
+ A..... = Audio
+ S..... = Subtitle
+ .F.... = Frame-level multithreading
+ ..S... = Slice-level multithreading
+ ...X.. = Codec is experimental
+ ....B. = Supports draw_horiz_band
+ .....D = Supports direct rendering method 1
+ ------
+ V....D libx264              libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)
+ V....D libx265              libx265 H.265 / HEVC (codec hevc)
+ V....D libaom-av1           libaom AV1 (codec av1)
+ V....D libvpx-vp9           libvpx VP9 (codec vp9)
+ A....D aac                  AAC (Advanced Audio Coding)
+ A....D libopus              libopus Opus (codec opus)
+ S..... mov_text             3GPP Timed Text subtitle
+";
+
+    /// Excerpt of `ffmpeg -hide_banner -muxers`.
+    const MUXERS: &str = "\
+ File formats:
+ D. = Demuxing supported
+ .E = Muxing supported
+ --
+  E 3g2             3GP2 (3GPP2 file format)
+  E matroska        Matroska
+  E mov             QuickTime / MOV
+  E mp4             MP4 (MPEG-4 Part 14)
+ D  mpegts,mpegtsraw MPEG-TS (MPEG-2 Transport Stream)
+ DE webm,webm_chunk WebM
+";
+
+    /// Excerpt of `ffmpeg -hide_banner -filters`.
+    const FILTERS: &str = "\
+Filters:
+  T.. = Timeline support
+  .S. = Slice threading
+  ..C = Command support
+  A = Audio input/output
+  V = Video input/output
+  N = Dynamic number and/or type of input/output
+  | = Source or sink filter
+ ... abuffer           |->A       Buffer audio frames, and make them accessible to the filterchain.
+ ..C loudnorm          A->A       EBU R128 loudness normalization
+ TSC scale             V->V       Scale the input video size and/or convert the image format.
+ ..C tonemap           V->V       Conversion to/from different dynamic ranges.
+";
+
+    fn capabilities() -> FfmpegCapabilities {
+        from_listings(
+            parse_encoders(ENCODERS),
+            parse_muxers(MUXERS),
+            parse_filters(FILTERS),
+        )
+    }
+
+    fn encoder(name: &str) -> EncoderInfo {
+        EncoderInfo {
+            name: name.to_string(),
+            kind: StreamKind::Video,
+            description: String::new(),
+        }
+    }
+
+    #[test]
+    fn encoders_follow_the_separator() {
+        let encoders = parse_encoders(ENCODERS);
+
+        assert_eq!(encoders.len(), 7);
+        assert_eq!(encoders[0].name, "libx264");
+        assert_eq!(encoders[0].kind, StreamKind::Video);
+        assert_eq!(
+            encoders[0].description,
+            "libx264 H.264 / AVC / MPEG-4 AVC / MPEG-4 part 10 (codec h264)"
+        );
+        assert_eq!(encoders[4].kind, StreamKind::Audio);
+        assert_eq!(encoders[6].kind, StreamKind::Subtitle);
+    }
+
+    #[test]
+    fn only_muxing_formats_are_muxers() {
+        assert_eq!(
+            parse_muxers(MUXERS),
+            ["3g2", "matroska", "mov", "mp4", "webm", "webm_chunk"]
+        );
+    }
+
+    #[test]
+    fn filter_legend_is_skipped() {
+        assert_eq!(
+            parse_filters(FILTERS),
+            ["abuffer", "loudnorm", "scale", "tonemap"]
+        );
+    }
+
+    #[test]
+    fn codecs_and_containers_follow_the_listings() {
+        let capabilities = capabilities();
+
+        assert_eq!(capabilities.video_codecs, VideoCodec::ALL);
+        assert_eq!(
+            capabilities.containers,
+            [
+                Container::Mp4,
+                Container::Mov,
+                Container::Webm,
+                Container::Mkv
+            ]
+        );
+        assert!(capabilities.has_filter("tonemap"));
+        assert!(!capabilities.has_filter("zscale"));
+    }
+
+    #[test]
+    fn av1_falls_back_to_libaom() {
+        let capabilities = capabilities();
+        assert_eq!(
+            capabilities.video_encoder_for(VideoCodec::Av1),
+            Some("libaom-av1")
+        );
+
+        let with_svt = from_listings(
+            vec![encoder("libaom-av1"), encoder("libsvtav1")],
+            Vec::new(),
+            Vec::new(),
+        );
+        assert_eq!(
+            with_svt.video_encoder_for(VideoCodec::Av1),
+            Some("libsvtav1")
+        );
+        assert_eq!(with_svt.video_codecs, [VideoCodec::Av1]);
+    }
+
+    #[test]
+    fn codecs_without_an_encoder_are_unavailable() {
+        let capabilities = from_listings(vec![encoder("libx264")], Vec::new(), Vec::new());
+
+        assert_eq!(capabilities.video_encoder_for(VideoCodec::H265), None);
+        assert_eq!(capabilities.video_codecs, [VideoCodec::H264]);
+        assert!(capabilities.containers.is_empty());
+    }
+
+    #[test]
+    fn empty_listings_have_no_entries() {
+        assert!(parse_encoders("").is_empty());
+        assert!(parse_muxers("ffmpeg version n7.0").is_empty());
+        assert!(parse_filters("").is_empty());
+    }
+}
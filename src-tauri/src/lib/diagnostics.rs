@@ -0,0 +1,313 @@
+use shared_child::SharedChild;
+use std::{
+    collections::VecDeque,
+    io::{self, BufRead, BufReader},
+    path::Path,
+    process::{Command, ExitStatus, Output},
+};
+
+use crate::{
+    domain::{FfmpegError, FfmpegErrorKind},
+    probe,
+};
+
+/// Number of stderr lines kept for error reports.
+const STDERR_TAIL_LINES: usize = 20;
+
+/// Lower-cased stderr fragments per error kind, checked in this order.
+const STDERR_PATTERNS: [(FfmpegErrorKind, &[&str]); 6] = [
+    (
+        FfmpegErrorKind::DiskFull,
+        &["no space left on device", "disk full", "not enough space"],
+    ),
+    (
+        FfmpegErrorKind::PermissionDenied,
+        &["permission denied", "operation not permitted", "access is denied"],
+    ),
+    (FfmpegErrorKind::MissingInput, &["no such file or directory"]),
+    (
+        FfmpegErrorKind::InvalidFilter,
+        &[
+            "no such filter",
+            "error initializing filter",
+            "error reinitializing filters",
+            "error parsing filtergraph",
+            "error initializing complex filters",
+            "failed to configure",
+            "invalid too big or non positive size",
+        ],
+    ),
+    (
+        FfmpegErrorKind::UnsupportedCodec,
+        &[
+            "unknown encoder",
+            "encoder not found",
+            "unknown decoder",
+            "decoder not found",
+            "could not find tag for codec",
+            "not currently supported in container",
+            "codec not currently supported",
+            "error while opening encoder",
+            "incorrect codec parameters",
+        ],
+    ),
+    (
+        FfmpegErrorKind::DecodingError,
+        &[
+            "invalid data found when processing input",
+            "error while decoding",
+            "moov atom not found",
+            "error splitting the input into nal units",
+            "invalid nal unit",
+            "corrupt",
+        ],
+    ),
+];
+
+/// Keeps the last lines ffmpeg wrote to stderr.
+#[derive(Default)]
+pub struct StderrTail {
+    lines: VecDeque<String>,
+}
+
+impl StderrTail {
+    pub fn push(&mut self, line: &str) {
+        let line = line.trim();
+        if line.is_empty() {
+            return;
+        }
+        if self.lines.len() == STDERR_TAIL_LINES {
+            self.lines.pop_front();
+        }
+        self.lines.push_back(line.to_string());
+    }
+
+    pub fn text(&self) -> String {
+        self.lines
+            .iter()
+            .map(String::as_str)
+            .collect::<Vec<&str>>()
+            .join("\n")
+    }
+}
+
+/// Turns a failed run's exit code and stderr tail into an error the user can act on.
+pub fn classify(exit_code: Option<i32>, stderr: &str) -> FfmpegError {
+    let haystack = stderr.to_lowercase();
+    let kind = STDERR_PATTERNS
+        .iter()
+        .find(|(_, patterns)| patterns.iter().any(|pattern| haystack.contains(pattern)))
+        .map(|(kind, _)| *kind)
+        .unwrap_or(FfmpegErrorKind::Unknown);
+
+    let message = match kind {
+        FfmpegErrorKind::MissingInput => String::from("Input file or output folder does not exist."),
+        FfmpegErrorKind::UnsupportedCodec => String::from(
+            "The selected codec is not supported by this ffmpeg build or output container.",
+        ),
+        FfmpegErrorKind::DiskFull => String::from("Not enough disk space to write the output."),
+        FfmpegErrorKind::PermissionDenied => {
+            String::from("Permission denied while reading the input or writing the output.")
+        }
+        FfmpegErrorKind::InvalidFilter => {
+            String::from("Invalid crop, scale or rotation settings for this video.")
+        }
+        FfmpegErrorKind::DecodingError => {
+            String::from("The input video could not be decoded, it may be corrupted.")
+        }
+        _ => match exit_code {
+            Some(code) => format!("ffmpeg failed with exit code {code}."),
+            None => String::from("ffmpeg was terminated unexpectedly."),
+        },
+    };
+
+    FfmpegError {
+        kind,
+        message,
+        stderr: Some(stderr.trim().to_string()).filter(|stderr| !stderr.is_empty()),
+        exit_code,
+        field_errors: Vec::new(),
+    }
+}
+
+/// Error for an ffmpeg/ffprobe binary that could not be started.
+pub fn spawn_error(program: &str, err: &io::Error) -> FfmpegError {
+    let kind = match err.kind() {
+        io::ErrorKind::NotFound => FfmpegErrorKind::FfmpegNotFound,
+        io::ErrorKind::PermissionDenied => FfmpegErrorKind::PermissionDenied,
+        _ => FfmpegErrorKind::Unknown,
+    };
+    FfmpegError::new(kind, &format!("Could not start {program}: {err}"))
+}
+
+/// Drains the child's stderr, keeping the last lines for error reports.
+pub fn collect_stderr(child: &SharedChild) -> StderrTail {
+    let mut tail = StderrTail::default();
+    if let Some(stderr) = child.take_stderr() {
+        let mut reader = BufReader::new(stderr);
+        loop {
+            let mut buf: Vec<u8> = Vec::new();
+            match reader.read_until(b'\n', &mut buf) {
+                Ok(0) | Err(_) => break,
+                Ok(_) => {
+                    let line = String::from_utf8_lossy(&buf);
+                    log::debug!("stderr: {:?}", line);
+                    tail.push(&line);
+                }
+            }
+        }
+    }
+    tail
+}
+
+/// Maps a finished child's exit status to a classified error.
+pub fn check_exit(status: io::Result<ExitStatus>, stderr: &StderrTail) -> Result<(), FfmpegError> {
+    match status {
+        Ok(status) if status.success() => Ok(()),
+        Ok(status) => Err(classify(status.code(), &stderr.text())),
+        Err(err) => Err(FfmpegError::from(err.to_string())),
+    }
+}
+
+/// Runs a command to completion, classifying failures from its stderr.
+pub fn run_to_completion(mut command: Command, program: &str) -> Result<Output, FfmpegError> {
+    let output = command.output().map_err(|err| spawn_error(program, &err))?;
+    let mut stderr = StderrTail::default();
+    for line in String::from_utf8_lossy(&output.stderr).lines() {
+        stderr.push(line);
+    }
+    check_exit(Ok(output.status), &stderr)?;
+    Ok(output)
+}
+
+/// Fails when an encode left no output file, or an empty one, behind.
+pub fn check_output_file(output_path: &Path) -> Result<(), FfmpegError> {
+    match std::fs::metadata(output_path) {
+        Ok(metadata) if metadata.len() > 0 => Ok(()),
+        Ok(_) => Err(FfmpegError::new(
+            FfmpegErrorKind::Unknown,
+            "ffmpeg finished but wrote an empty output file.",
+        )),
+        Err(_) => Err(FfmpegError::new(
+            FfmpegErrorKind::Unknown,
+            "ffmpeg finished but did not write the output file.",
+        )),
+    }
+}
+
+/// Checks the output file and probes it with `ffprobe` to make sure it can be
+/// read back and holds at least one stream.
+pub fn verify_output(mut ffprobe: Command, output_path: &Path) -> Result<(), FfmpegError> {
+    check_output_file(output_path)?;
+
+    ffprobe.args(probe::ffprobe_args(&output_path.display().to_string()));
+    let undecodable = |stderr: Option<String>| FfmpegError {
+        kind: FfmpegErrorKind::DecodingError,
+        message: String::from("The compressed video could not be read back, it may be corrupted."),
+        stderr,
+        exit_code: None,
+        field_errors: Vec::new(),
+    };
+    let output = run_to_completion(ffprobe, "ffprobe").map_err(|err| undecodable(err.stderr))?;
+    let media_info = probe::parse_ffprobe_json(&String::from_utf8_lossy(&output.stdout))
+        .map_err(|err| undecodable(Some(err)))?;
+    if media_info.streams.is_empty() {
+        return Err(undecodable(None));
+    }
+    Ok(())
+}
+
+#[cfg(all(test, unix))]
+mod tests {
+    use super::*;
+    use std::{fs, os::unix::fs::PermissionsExt, path::PathBuf, process::Stdio};
+
+    /// Writes an executable shell script standing in for ffmpeg/ffprobe.
+    fn stand_in(dir: &Path, name: &str, body: &str) -> PathBuf {
+        let path = dir.join(name);
+        fs::write(&path, format!("#!/bin/sh\n{body}\n")).unwrap();
+        fs::set_permissions(&path, fs::Permissions::from_mode(0o755)).unwrap();
+        path
+    }
+
+    fn test_dir(name: &str) -> PathBuf {
+        let dir = std::env::temp_dir().join(format!(
+            "compresso-diagnostics-{}-{name}",
+            std::process::id()
+        ));
+        let _ = fs::remove_dir_all(&dir);
+        fs::create_dir_all(&dir).unwrap();
+        dir
+    }
+
+    #[test]
+    fn non_zero_exit_fails_the_encode() {
+        let dir = test_dir("non-zero-exit");
+        let output = dir.join("out.mp4");
+        let ffmpeg = stand_in(
+            &dir,
+            "ffmpeg",
+            "printf 'broken' > \"$1\"\necho 'Error while decoding stream #0:0' >&2\nexit 1",
+        );
+
+        // Spawned the same way as a compression pass.
+        let mut command = Command::new(&ffmpeg);
+        command
+            .arg(&output)
+            .stdout(Stdio::piped())
+            .stderr(Stdio::piped());
+        let child = SharedChild::spawn(&mut command).unwrap();
+        let stderr = collect_stderr(&child);
+        let err = check_exit(child.wait(), &stderr).unwrap_err();
+
+        assert_eq!(err.kind, FfmpegErrorKind::DecodingError);
+        assert_eq!(err.exit_code, Some(1));
+        assert_eq!(
+            err.stderr.as_deref(),
+            Some("Error while decoding stream #0:0")
+        );
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn clean_exit_without_output_fails_the_encode() {
+        let dir = test_dir("empty-output");
+        let output = dir.join("out.mp4");
+        let ffmpeg = stand_in(&dir, "ffmpeg", ": > \"$1\"\nexit 0");
+
+        let mut command = Command::new(&ffmpeg);
+        command.arg(&output);
+        run_to_completion(command, "ffmpeg").unwrap();
+        assert!(check_output_file(&output).is_err());
+
+        fs::remove_file(&output).unwrap();
+        assert!(check_output_file(&output).is_err());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+
+    #[test]
+    fn undecodable_output_fails_verification() {
+        let dir = test_dir("undecodable");
+        let output = dir.join("out.mp4");
+        fs::write(&output, b"not a video").unwrap();
+
+        let ffprobe = stand_in(
+            &dir,
+            "ffprobe",
+            "echo 'Invalid data found when processing input' >&2\nexit 1",
+        );
+        let err = verify_output(Command::new(&ffprobe), &output).unwrap_err();
+        assert_eq!(err.kind, FfmpegErrorKind::DecodingError);
+
+        let ffprobe = stand_in(
+            &dir,
+            "ffprobe-ok",
+            "echo '{\"streams\":[{\"index\":0,\"codec_type\":\"video\"}],\"format\":{}}'",
+        );
+        assert!(verify_output(Command::new(&ffprobe), &output).is_ok());
+
+        let _ = fs::remove_dir_all(&dir);
+    }
+}
@@ -1,7 +1,8 @@
 use serde::{Deserialize, Serialize};
-use strum::{AsRefStr, EnumProperty};
+use std::{collections::BTreeMap, fmt};
+use strum::AsRefStr;
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct CompressionResult {
     pub file_name: String,
@@ -18,10 +19,20 @@ pub struct FileMetadata {
     pub size: u64,
 }
 
+#[derive(Serialize, Deserialize)]
+#[serde(rename_all = "camelCase")]
+pub struct ResolvedVideoFile {
+    #[serde(flatten)]
+    pub metadata: FileMetadata,
+    /// Folder of the file relative to the folder it was found in, empty for
+    /// files given directly.
+    pub relative_dir: String,
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct ResolveVideoFilesResult {
-    pub files: Vec<FileMetadata>,
+    pub files: Vec<ResolvedVideoFile>,
     pub invalid_paths: Vec<String>,
     pub skipped_paths: Vec<String>,
     pub ignored_count: u64,
@@ -32,7 +43,32 @@ pub struct ResolveVideoFilesResult {
 pub struct VideoCompressionProgress {
     pub video_id: String,
     pub file_name: String,
+    /// Position in the source. For multi-pass encodes it is scaled so that the
+    /// last pass ends at the source duration.
     pub current_duration: String,
+    pub pass: u8,
+    pub total_passes: u8,
+    #[serde(flatten)]
+    pub progress: EncodeProgress,
+}
+
+/// Progress of a whole encode, computed from ffmpeg's `-progress` output and
+/// the probed source duration.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeProgress {
+    /// Completion over all passes, 0-100.
+    pub percentage: Option<f64>,
+    pub eta_seconds: Option<f64>,
+    /// Bytes written so far. Only known on the pass that writes the output.
+    pub current_size: Option<u64>,
+    /// Final size extrapolated from the current size and position.
+    pub projected_size: Option<u64>,
+    pub frame: Option<u64>,
+    pub fps: Option<f64>,
+    pub bitrate_kbps: Option<f64>,
+    pub speed: Option<f64>,
+    pub is_finished: bool,
 }
 
 #[derive(Serialize, Deserialize, Clone)]
@@ -47,37 +83,248 @@ pub struct VideoThumbnail {
 pub enum CustomEvents {
     VideoCompressionProgress,
     CancelInProgressCompression,
+    CompressionJobUpdated,
+    CompressionQueueUpdated,
 }
 
-#[derive(EnumProperty)]
-pub enum TauriEvents {
-    #[strum(props(key = "tauri://destroyed"))]
-    Destroyed,
-}
-
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone)]
 #[serde(rename_all = "camelCase")]
 pub struct CancelInProgressCompressionPayload {
     pub video_id: String,
 }
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum JobStatus {
+    Queued,
+    Running,
+    Completed,
+    Failed,
+    Cancelled,
+    /// Queued or running when the app stopped. Resumed on request only.
+    Interrupted,
+}
+
+impl JobStatus {
+    pub fn is_finished(&self) -> bool {
+        matches!(
+            self,
+            JobStatus::Completed | JobStatus::Failed | JobStatus::Cancelled
+        )
+    }
+}
+
+/// A compression job as submitted by the frontend.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct NewCompressionJob {
+    /// Reused as the video id of progress events. Generated when missing.
+    pub id: Option<String>,
+    pub video_path: String,
+    /// Where the compressed file is moved once done. Stays in the app's
+    /// assets folder when missing.
+    pub output_path: Option<String>,
+    pub options: EncodeOptions,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionJob {
+    pub id: String,
+    pub video_path: String,
+    pub output_path: Option<String>,
+    pub options: EncodeOptions,
+    pub status: JobStatus,
+    /// Number of times the job was started.
+    pub attempts: u32,
+    pub result: Option<CompressionResult>,
+    pub error: Option<FfmpegError>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct CompressionQueueSnapshot {
+    pub jobs: Vec<CompressionJob>,
+    pub concurrency: usize,
+    pub is_paused: bool,
+}
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoInfo {
     pub duration: Option<String>,
+    /// Dimensions as displayed, after the rotation of the stream.
     pub dimensions: Option<(u32, u32)>,
     pub fps: Option<f32>,
+    pub hdr: Option<HdrFormat>,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum StreamKind {
+    Video,
+    Audio,
+    Subtitle,
+    Data,
+    Attachment,
+    Unknown,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamInfo {
+    pub index: u32,
+    pub kind: StreamKind,
+    pub codec_name: Option<String>,
+    pub codec_long_name: Option<String>,
+    pub profile: Option<String>,
+    pub pix_fmt: Option<String>,
+    pub bit_depth: Option<u32>,
+    /// Coded dimensions, before rotation.
+    pub width: Option<u32>,
+    pub height: Option<u32>,
+    pub frame_rate: Option<f64>,
+    pub color_range: Option<String>,
+    pub color_space: Option<String>,
+    pub color_transfer: Option<String>,
+    pub color_primaries: Option<String>,
+    pub bit_rate: Option<u64>,
+    pub sample_rate: Option<u32>,
+    pub channels: Option<u32>,
+    pub channel_layout: Option<String>,
+    pub language: Option<String>,
+    pub title: Option<String>,
+    /// Clockwise display rotation in degrees (0, 90, 180 or 270).
+    pub rotation: Option<i32>,
+    pub duration_seconds: Option<f64>,
+    pub is_default: bool,
+    /// Cover art / thumbnails stored as a video stream.
+    pub is_attached_picture: bool,
+    pub tags: BTreeMap<String, String>,
+}
+
+impl StreamInfo {
+    /// Width and height as displayed, with a 90 or 270 degree rotation applied.
+    pub fn display_dimensions(&self) -> Option<(u32, u32)> {
+        let (width, height) = (self.width?, self.height?);
+        match self.rotation.map(|rotation| rotation.rem_euclid(180)) {
+            Some(90) => Some((height, width)),
+            _ => Some((width, height)),
+        }
+    }
+
+    /// HDR transfer of the stream, `None` for SDR.
+    pub fn hdr_format(&self) -> Option<HdrFormat> {
+        HdrFormat::from_transfer(self.color_transfer.as_deref()?)
+    }
+}
+
+/// Transfer function of an HDR video, which comes with BT.2020 primaries.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum HdrFormat {
+    /// Perceptual quantizer (SMPTE ST 2084), used by HDR10 and Dolby Vision.
+    Pq,
+    /// Hybrid log-gamma (ARIB STD-B67), used by broadcasts and phones.
+    Hlg,
+}
+
+impl HdrFormat {
+    /// Reads ffmpeg's name of a color transfer.
+    pub fn from_transfer(transfer: &str) -> Option<Self> {
+        match transfer {
+            "smpte2084" => Some(HdrFormat::Pq),
+            "arib-std-b67" => Some(HdrFormat::Hlg),
+            _ => None,
+        }
+    }
+
+    /// ffmpeg's name of the color transfer.
+    pub fn transfer(&self) -> &'static str {
+        match self {
+            HdrFormat::Pq => "smpte2084",
+            HdrFormat::Hlg => "arib-std-b67",
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FormatInfo {
+    pub format_name: Option<String>,
+    pub format_long_name: Option<String>,
+    pub duration_seconds: Option<f64>,
+    pub start_time_seconds: Option<f64>,
+    pub size: Option<u64>,
+    pub bit_rate: Option<u64>,
+    pub tags: BTreeMap<String, String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct ChapterInfo {
+    pub id: i64,
+    pub start_seconds: f64,
+    pub end_seconds: f64,
+    pub title: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct MediaInfo {
+    pub format: FormatInfo,
+    pub streams: Vec<StreamInfo>,
+    pub chapters: Vec<ChapterInfo>,
+}
+
+impl MediaInfo {
+    /// First real video stream, preferring the default one and skipping cover art.
+    pub fn primary_video_stream(&self) -> Option<&StreamInfo> {
+        let mut videos = self
+            .streams_of(StreamKind::Video)
+            .filter(|stream| !stream.is_attached_picture);
+        let first = videos.next()?;
+        if first.is_default {
+            return Some(first);
+        }
+        Some(videos.find(|stream| stream.is_default).unwrap_or(first))
+    }
+
+    pub fn streams_of(&self, kind: StreamKind) -> impl Iterator<Item = &StreamInfo> {
+        self.streams.iter().filter(move |stream| stream.kind == kind)
+    }
+
+    pub fn duration_seconds(&self) -> Option<f64> {
+        self.format
+            .duration_seconds
+            .or_else(|| self.primary_video_stream()?.duration_seconds)
+    }
+
+    pub fn has_audio(&self) -> bool {
+        self.streams_of(StreamKind::Audio).next().is_some()
+    }
+
+    /// Language of each stream of a kind, in the order ffmpeg numbers them
+    /// (`0:a:0`, `0:a:1`, ...).
+    pub fn stream_languages(&self, kind: StreamKind) -> Vec<Option<String>> {
+        self.streams_of(kind)
+            .map(|stream| stream.language.clone())
+            .collect()
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoCoordinates {
-    pub top: u32,
-    pub left: u32,
-    pub width: u32,
-    pub height: u32,
+    pub top: f64,
+    pub left: f64,
+    pub width: f64,
+    pub height: f64,
 }
 
-#[derive(Serialize, Deserialize)]
+#[derive(Serialize, Deserialize, Clone, Copy, Debug)]
 #[serde(rename_all = "camelCase")]
 pub struct VideoFlip {
     pub horizontal: bool,
@@ -92,6 +339,892 @@ pub struct VideoTransforms {
     pub flip: VideoFlip,
 }
 
+/// Part of the source video to keep, in seconds.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct TrimRange {
+    pub start_seconds: f64,
+    /// `None` keeps everything after `start_seconds`.
+    #[serde(default)]
+    pub end_seconds: Option<f64>,
+}
+
+/// One entry of the frontend's transforms history, applied in order.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum VideoTransform {
+    Crop(VideoCoordinates),
+    Rotate(i32),
+    Flip(VideoFlip),
+    /// Keeps only these ranges, joined in order. Only the last trim applies.
+    Trim(Vec<TrimRange>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Preset {
+    /// Compression-focused encode (`-preset slow`, `yuv420p`, `+faststart`).
+    #[default]
+    Ironclad,
+    /// Fast encode with fewer tuning flags.
+    Thunderbolt,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum Container {
+    Mp4,
+    Mov,
+    Webm,
+    Avi,
+    Mkv,
+}
+
+impl Container {
+    pub const ALL: [Container; 5] = [
+        Container::Mp4,
+        Container::Mov,
+        Container::Webm,
+        Container::Avi,
+        Container::Mkv,
+    ];
+
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        Self::ALL
+            .into_iter()
+            .find(|container| container.extension().eq_ignore_ascii_case(extension))
+    }
+
+    pub fn extension(&self) -> &'static str {
+        match self {
+            Container::Mp4 => "mp4",
+            Container::Mov => "mov",
+            Container::Webm => "webm",
+            Container::Avi => "avi",
+            Container::Mkv => "mkv",
+        }
+    }
+
+    /// ffmpeg muxer writing this container.
+    pub fn muxer(&self) -> &'static str {
+        match self {
+            Container::Mkv => "matroska",
+            other => other.extension(),
+        }
+    }
+
+    /// Codec used when none is picked explicitly.
+    pub fn default_video_codec(&self) -> VideoCodec {
+        match self {
+            Container::Webm => VideoCodec::Vp9,
+            _ => VideoCodec::H264,
+        }
+    }
+
+    /// Codec/container compatibility matrix.
+    pub fn supports_video_codec(&self, codec: VideoCodec) -> bool {
+        match self {
+            Container::Mkv => true,
+            Container::Mp4 => matches!(
+                codec,
+                VideoCodec::H264 | VideoCodec::H265 | VideoCodec::Av1 | VideoCodec::Vp9
+            ),
+            Container::Mov => matches!(codec, VideoCodec::H264 | VideoCodec::H265),
+            Container::Webm => matches!(codec, VideoCodec::Vp9 | VideoCodec::Av1),
+            Container::Avi => codec == VideoCodec::H264,
+        }
+    }
+
+    pub fn supports_audio_codec(&self, codec: AudioCodec) -> bool {
+        match self {
+            Container::Mkv | Container::Mp4 => true,
+            Container::Mov | Container::Avi => matches!(codec, AudioCodec::Copy | AudioCodec::Aac),
+            Container::Webm => matches!(codec, AudioCodec::Copy | AudioCodec::Opus),
+        }
+    }
+
+    /// Encoder for the subtitle streams, `copy` when the container stores
+    /// them as they are. `None` when it cannot store subtitles.
+    pub fn subtitle_encoder(&self) -> Option<&'static str> {
+        match self {
+            Container::Mp4 | Container::Mov => Some("mov_text"),
+            Container::Webm => Some("webvtt"),
+            Container::Mkv => Some("copy"),
+            Container::Avi => None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum VideoCodec {
+    H264,
+    H265,
+    Av1,
+    Vp9,
+}
+
+impl VideoCodec {
+    pub const ALL: [VideoCodec; 4] = [
+        VideoCodec::H264,
+        VideoCodec::H265,
+        VideoCodec::Av1,
+        VideoCodec::Vp9,
+    ];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "h264" | "avc" | "x264" => Some(VideoCodec::H264),
+            "h265" | "hevc" | "x265" => Some(VideoCodec::H265),
+            "av1" => Some(VideoCodec::Av1),
+            "vp9" => Some(VideoCodec::Vp9),
+            _ => None,
+        }
+    }
+
+    /// Whether the codec stores 10-bit video, which HDR needs.
+    pub fn supports_hdr(&self) -> bool {
+        !matches!(self, VideoCodec::H264)
+    }
+
+    /// ffmpeg encoders able to produce this codec, in order of preference.
+    pub fn encoders(&self) -> &'static [&'static str] {
+        match self {
+            VideoCodec::H264 => &["libx264"],
+            VideoCodec::H265 => &["libx265"],
+            VideoCodec::Av1 => &["libsvtav1", "libaom-av1"],
+            VideoCodec::Vp9 => &["libvpx-vp9"],
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum AudioCodec {
+    /// Keeps the source audio stream as is.
+    Copy,
+    Aac,
+    Opus,
+}
+
+impl AudioCodec {
+    pub const ALL: [AudioCodec; 3] = [AudioCodec::Copy, AudioCodec::Aac, AudioCodec::Opus];
+
+    pub fn from_name(name: &str) -> Option<Self> {
+        match name.to_ascii_lowercase().as_str() {
+            "copy" => Some(AudioCodec::Copy),
+            "aac" => Some(AudioCodec::Aac),
+            "opus" => Some(AudioCodec::Opus),
+            _ => None,
+        }
+    }
+
+    /// ffmpeg encoder for this codec, `None` for `Copy`.
+    pub fn encoder(&self) -> Option<&'static str> {
+        match self {
+            AudioCodec::Copy => None,
+            AudioCodec::Aac => Some("aac"),
+            AudioCodec::Opus => Some("libopus"),
+        }
+    }
+}
+
+/// Sample rates libopus accepts.
+pub const OPUS_SAMPLE_RATES: [u32; 5] = [8000, 12000, 16000, 24000, 48000];
+
+/// Audio settings. `None` fields keep ffmpeg's defaults for the container.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct AudioOptions {
+    #[serde(default)]
+    pub codec: Option<AudioCodec>,
+    #[serde(default)]
+    pub bitrate_kbps: Option<u32>,
+    /// 1 downmixes to mono.
+    #[serde(default)]
+    pub channels: Option<u8>,
+    #[serde(default)]
+    pub sample_rate: Option<u32>,
+    /// EBU R128 loudness normalization, measured in a first pass.
+    #[serde(default)]
+    pub normalize: bool,
+}
+
+impl AudioOptions {
+    /// Whether the audio is decoded and re-encoded with other settings than
+    /// ffmpeg's defaults.
+    fn changes_audio(&self) -> bool {
+        self.bitrate_kbps.is_some()
+            || self.channels.is_some()
+            || self.sample_rate.is_some()
+            || self.normalize
+    }
+}
+
+/// Picks audio or subtitle streams of the input.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum StreamSelector {
+    /// Position among the input's streams of that kind, from 0.
+    Index(u32),
+    /// Every stream tagged with this language, e.g. `eng`.
+    Language(String),
+}
+
+/// Which audio or subtitle streams of the input are kept.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum StreamSelection {
+    /// ffmpeg's own pick. When the other kind is selected, the first audio
+    /// stream and no subtitles.
+    #[default]
+    Default,
+    All,
+    /// Kept in the order they are picked.
+    Pick(Vec<StreamSelector>),
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct StreamOptions {
+    #[serde(default)]
+    pub audio: StreamSelection,
+    #[serde(default)]
+    pub subtitles: StreamSelection,
+    /// Mixes the kept audio streams into one.
+    #[serde(default)]
+    pub mix_audio: bool,
+}
+
+impl StreamOptions {
+    /// Whether ffmpeg picks the streams itself.
+    pub fn is_default(&self) -> bool {
+        self.audio == StreamSelection::Default && self.subtitles == StreamSelection::Default
+    }
+}
+
+/// What happens to the input's tags (creation time, GPS location, camera
+/// model, ...) and chapters.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum MetadataMode {
+    /// ffmpeg's own handling, which differs between containers.
+    #[default]
+    Default,
+    /// Copies every tag and the chapters.
+    Preserve,
+    /// Removes every tag and the chapters.
+    Strip,
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct MetadataOptions {
+    #[serde(default)]
+    pub mode: MetadataMode,
+    /// Gives the output the modification and access times of the input.
+    #[serde(default)]
+    pub copy_file_times: bool,
+}
+
+/// Encoding of HDR sources, SDR sources are not affected.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "lowercase")]
+#[strum(serialize_all = "lowercase")]
+pub enum HdrMode {
+    /// Tone maps to SDR BT.709, which every player shows correctly.
+    #[default]
+    Tonemap,
+    /// Keeps 10-bit HDR with its color tags, needs a codec that supports it.
+    Preserve,
+}
+
+/// Extensions of the subtitle files that can be added or burned in.
+pub const SUBTITLE_FILE_EXTENSIONS: [&str; 4] = ["srt", "vtt", "ass", "ssa"];
+
+/// Subtitle burned into the picture.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq, Eq)]
+#[serde(tag = "type", content = "value", rename_all = "camelCase")]
+pub enum SubtitleBurnIn {
+    /// Position among the input's subtitle streams, from 0.
+    Stream(u32),
+    /// Path of a subtitle file.
+    File(String),
+}
+
+/// Subtitles added to the input's own, see `StreamOptions::subtitles`.
+#[derive(Serialize, Deserialize, Clone, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub struct SubtitleOptions {
+    /// Subtitle files stored as streams after the kept ones.
+    #[serde(default)]
+    pub files: Vec<String>,
+    #[serde(default)]
+    pub burn_in: Option<SubtitleBurnIn>,
+}
+
+impl SubtitleOptions {
+    pub fn is_empty(&self) -> bool {
+        self.files.is_empty() && self.burn_in.is_none()
+    }
+}
+
+/// Whether `path` has one of the `SUBTITLE_FILE_EXTENSIONS`.
+pub fn is_subtitle_file(path: &str) -> bool {
+    std::path::Path::new(path)
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .is_some_and(|extension| {
+            SUBTITLE_FILE_EXTENSIONS
+                .iter()
+                .any(|known| known.eq_ignore_ascii_case(extension))
+        })
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EncoderInfo {
+    pub name: String,
+    pub kind: StreamKind,
+    pub description: String,
+}
+
+/// What the ffmpeg binary was built with.
+#[derive(Serialize, Deserialize, Clone, Debug, Default)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegCapabilities {
+    pub encoders: Vec<EncoderInfo>,
+    pub muxers: Vec<String>,
+    pub filters: Vec<String>,
+    /// Codecs with at least one available encoder.
+    pub video_codecs: Vec<VideoCodec>,
+    /// Containers with an available muxer.
+    pub containers: Vec<Container>,
+}
+
+impl FfmpegCapabilities {
+    pub fn has_encoder(&self, name: &str) -> bool {
+        self.encoders.iter().any(|encoder| encoder.name == name)
+    }
+
+    pub fn has_muxer(&self, name: &str) -> bool {
+        self.muxers.iter().any(|muxer| muxer == name)
+    }
+
+    pub fn has_filter(&self, name: &str) -> bool {
+        self.filters.iter().any(|filter| filter == name)
+    }
+
+    /// First available encoder for the codec, in the codec's order of preference.
+    pub fn video_encoder_for(&self, codec: VideoCodec) -> Option<&'static str> {
+        codec
+            .encoders()
+            .iter()
+            .copied()
+            .find(|encoder| self.has_encoder(encoder))
+    }
+}
+
+/// Encode settings shared by compression, quality preview and the CLI.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeOptions {
+    pub container: Container,
+    /// `None` picks the container's default codec.
+    #[serde(default)]
+    pub video_codec: Option<VideoCodec>,
+    #[serde(default)]
+    pub preset: Preset,
+    /// 0-100, higher is better. `None` uses the default CRF.
+    #[serde(default)]
+    pub quality: Option<u16>,
+    /// Encode in two passes to fit this size (in MB). Overrides `quality`.
+    #[serde(default)]
+    pub target_size_mb: Option<f64>,
+    #[serde(default)]
+    pub dimensions: Option<(u32, u32)>,
+    #[serde(default)]
+    pub fps: Option<f32>,
+    #[serde(default)]
+    pub mute_audio: bool,
+    #[serde(default)]
+    pub audio: AudioOptions,
+    #[serde(default)]
+    pub streams: StreamOptions,
+    #[serde(default)]
+    pub subtitles: SubtitleOptions,
+    #[serde(default)]
+    pub metadata: MetadataOptions,
+    #[serde(default)]
+    pub hdr: HdrMode,
+    #[serde(default)]
+    pub transforms: Vec<VideoTransform>,
+}
+
+const MAX_FPS: f32 = 240.0;
+const MIN_AUDIO_KBPS: u32 = 6;
+const MAX_AUDIO_KBPS: u32 = 512;
+const MAX_AUDIO_CHANNELS: u8 = 8;
+const MIN_SAMPLE_RATE: u32 = 8000;
+const MAX_SAMPLE_RATE: u32 = 192_000;
+const MIN_DIMENSION: u32 = 2;
+const MAX_DIMENSION: u32 = 16384;
+
+impl EncodeOptions {
+    pub fn resolved_video_codec(&self) -> VideoCodec {
+        self.video_codec
+            .unwrap_or_else(|| self.container.default_video_codec())
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors: Vec<FieldError> = Vec::new();
+
+        if let Some(codec) = self.video_codec {
+            if !self.container.supports_video_codec(codec) {
+                errors.push(FieldError::new(
+                    "videoCodec",
+                    &format!(
+                        "{} cannot be stored in {}",
+                        codec.as_ref(),
+                        self.container.extension()
+                    ),
+                ));
+            }
+        }
+
+        if let Some(quality) = self.quality {
+            if quality > 100 {
+                errors.push(FieldError::new("quality", "must be between 0 and 100"));
+            }
+        }
+
+        if let Some(target_size_mb) = self.target_size_mb {
+            if !target_size_mb.is_finite() || target_size_mb <= 0.0 {
+                errors.push(FieldError::new("targetSizeMb", "must be greater than 0"));
+            }
+        }
+
+        if let Some(fps) = self.fps {
+            if !fps.is_finite() || !(1.0..=MAX_FPS).contains(&fps) {
+                errors.push(FieldError::new(
+                    "fps",
+                    &format!("must be between 1 and {MAX_FPS}"),
+                ));
+            }
+        }
+
+        if let Some((width, height)) = self.dimensions {
+            let range = MIN_DIMENSION..=MAX_DIMENSION;
+            if !range.contains(&width) || !range.contains(&height) {
+                errors.push(FieldError::new(
+                    "dimensions",
+                    &format!("width and height must be between {MIN_DIMENSION} and {MAX_DIMENSION}"),
+                ));
+            }
+        }
+
+        let audio = &self.audio;
+        if let Some(codec) = audio.codec {
+            if !self.container.supports_audio_codec(codec) {
+                errors.push(FieldError::new(
+                    "audio.codec",
+                    &format!(
+                        "{} audio cannot be stored in {}",
+                        codec.as_ref(),
+                        self.container.extension()
+                    ),
+                ));
+            }
+            if codec == AudioCodec::Copy {
+                if audio.changes_audio() {
+                    errors.push(FieldError::new(
+                        "audio.codec",
+                        "copied audio cannot change bitrate, channels, sample rate or loudness",
+                    ));
+                }
+                if self.target_size_mb.is_some() {
+                    errors.push(FieldError::new(
+                        "audio.codec",
+                        "copied audio cannot be used with a target size",
+                    ));
+                }
+            }
+        }
+        if let Some(bitrate_kbps) = audio.bitrate_kbps {
+            if !(MIN_AUDIO_KBPS..=MAX_AUDIO_KBPS).contains(&bitrate_kbps) {
+                errors.push(FieldError::new(
+                    "audio.bitrateKbps",
+                    &format!("must be between {MIN_AUDIO_KBPS} and {MAX_AUDIO_KBPS}"),
+                ));
+            }
+        }
+        if let Some(channels) = audio.channels {
+            if !(1..=MAX_AUDIO_CHANNELS).contains(&channels) {
+                errors.push(FieldError::new(
+                    "audio.channels",
+                    &format!("must be between 1 and {MAX_AUDIO_CHANNELS}"),
+                ));
+            }
+        }
+        if let Some(sample_rate) = audio.sample_rate {
+            if !(MIN_SAMPLE_RATE..=MAX_SAMPLE_RATE).contains(&sample_rate) {
+                errors.push(FieldError::new(
+                    "audio.sampleRate",
+                    &format!("must be between {MIN_SAMPLE_RATE} and {MAX_SAMPLE_RATE}"),
+                ));
+            } else if audio.codec == Some(AudioCodec::Opus)
+                && !OPUS_SAMPLE_RATES.contains(&sample_rate)
+            {
+                errors.push(FieldError::new(
+                    "audio.sampleRate",
+                    "opus only supports 8000, 12000, 16000, 24000 and 48000",
+                ));
+            }
+        }
+
+        for (field, selection) in [
+            ("streams.audio", &self.streams.audio),
+            ("streams.subtitles", &self.streams.subtitles),
+        ] {
+            if let StreamSelection::Pick(selectors) = selection {
+                if selectors.is_empty() {
+                    errors.push(FieldError::new(field, "must pick at least one stream"));
+                }
+                let has_empty_language = selectors.iter().any(|selector| match selector {
+                    StreamSelector::Language(language) => language.trim().is_empty(),
+                    StreamSelector::Index(_) => false,
+                });
+                if has_empty_language {
+                    errors.push(FieldError::new(field, "languages must not be empty"));
+                }
+            }
+        }
+        if self.streams.subtitles != StreamSelection::Default && self.container == Container::Avi {
+            errors.push(FieldError::new(
+                "streams.subtitles",
+                "avi cannot store subtitles",
+            ));
+        }
+
+        let subtitles = &self.subtitles;
+        for (index, file) in subtitles.files.iter().enumerate() {
+            if !is_subtitle_file(file) {
+                errors.push(FieldError::new(
+                    &format!("subtitles.files[{index}]"),
+                    "must be a .srt, .vtt, .ass or .ssa file",
+                ));
+            }
+        }
+        if !subtitles.files.is_empty() && self.container == Container::Avi {
+            errors.push(FieldError::new(
+                "subtitles.files",
+                "avi cannot store subtitles",
+            ));
+        }
+        if let Some(SubtitleBurnIn::File(file)) = &subtitles.burn_in {
+            if !is_subtitle_file(file) {
+                errors.push(FieldError::new(
+                    "subtitles.burnIn",
+                    "must be a .srt, .vtt, .ass or .ssa file",
+                ));
+            }
+        }
+        if self.streams.mix_audio && audio.codec == Some(AudioCodec::Copy) {
+            errors.push(FieldError::new(
+                "streams.mixAudio",
+                "copied audio cannot be mixed",
+            ));
+        }
+
+        for (index, transform) in self.transforms.iter().enumerate() {
+            let field = format!("transforms[{index}]");
+            match transform {
+                VideoTransform::Rotate(angle) => {
+                    if angle % 90 != 0 {
+                        errors.push(FieldError::new(&field, "rotation must be a multiple of 90"));
+                    }
+                }
+                VideoTransform::Crop(crop) => {
+                    let values = [crop.top, crop.left, crop.width, crop.height];
+                    if values.iter().any(|value| !value.is_finite() || *value < 0.0)
+                        || crop.width < 1.0
+                        || crop.height < 1.0
+                    {
+                        errors.push(FieldError::new(&field, "crop must have a positive size"));
+                    }
+                }
+                VideoTransform::Flip(_) => {}
+                VideoTransform::Trim(ranges) => {
+                    if let Err(message) = validate_trim_ranges(ranges) {
+                        errors.push(FieldError::new(&field, message));
+                    }
+                    if ranges.len() > 1 && audio.codec == Some(AudioCodec::Copy) && !self.mute_audio
+                    {
+                        errors.push(FieldError::new(
+                            &field,
+                            "copied audio cannot be joined from several ranges",
+                        ));
+                    }
+                    if ranges.len() > 1
+                        && (self.streams.subtitles != StreamSelection::Default
+                            || !subtitles.files.is_empty())
+                    {
+                        errors.push(FieldError::new(
+                            &field,
+                            "subtitles cannot be kept with several ranges",
+                        ));
+                    }
+                    if ranges.len() > 1 && subtitles.burn_in.is_some() {
+                        errors.push(FieldError::new(
+                            &field,
+                            "subtitles cannot be burned in with several ranges",
+                        ));
+                    }
+                }
+            }
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
+
+/// Ranges must be in order and must not overlap; only the last may run to the end.
+pub fn validate_trim_ranges(ranges: &[TrimRange]) -> Result<(), &'static str> {
+    if ranges.is_empty() {
+        return Err("trim must keep at least one range");
+    }
+    let mut previous_end = 0.0;
+    for (index, range) in ranges.iter().enumerate() {
+        if !range.start_seconds.is_finite() || range.start_seconds < 0.0 {
+            return Err("trim ranges must start at 0 or later");
+        }
+        if range.start_seconds < previous_end {
+            return Err("trim ranges must be in order and must not overlap");
+        }
+        match range.end_seconds {
+            Some(end) if !end.is_finite() || end <= range.start_seconds => {
+                return Err("trim ranges must end after they start");
+            }
+            Some(end) => previous_end = end,
+            None if index + 1 < ranges.len() => {
+                return Err("only the last trim range can run to the end of the video");
+            }
+            None => {}
+        }
+    }
+    Ok(())
+}
+
+/// Named encode settings, shared by the app and `compresso-cli --profile`.
+#[derive(Serialize, Deserialize, Clone, Debug, PartialEq)]
+#[serde(rename_all = "camelCase")]
+pub struct EncodeProfile {
+    pub name: String,
+    /// `None` keeps the source container.
+    #[serde(default)]
+    pub container: Option<Container>,
+    #[serde(default)]
+    pub video_codec: Option<VideoCodec>,
+    #[serde(default)]
+    pub preset: Preset,
+    #[serde(default)]
+    pub quality: Option<u16>,
+    #[serde(default)]
+    pub target_size_mb: Option<f64>,
+    #[serde(default)]
+    pub fps: Option<f32>,
+    #[serde(default)]
+    pub dimensions: Option<(u32, u32)>,
+    #[serde(default)]
+    pub mute_audio: bool,
+    #[serde(default)]
+    pub audio: AudioOptions,
+    #[serde(default)]
+    pub streams: StreamOptions,
+    #[serde(default)]
+    pub metadata: MetadataOptions,
+    #[serde(default)]
+    pub hdr: HdrMode,
+}
+
+impl EncodeProfile {
+    /// Options for an output in `container`.
+    pub fn encode_options(&self, container: Container) -> EncodeOptions {
+        EncodeOptions {
+            container,
+            video_codec: self.video_codec,
+            preset: self.preset,
+            quality: self.quality,
+            target_size_mb: self.target_size_mb,
+            dimensions: self.dimensions,
+            fps: self.fps,
+            mute_audio: self.mute_audio,
+            audio: self.audio,
+            streams: self.streams.clone(),
+            subtitles: SubtitleOptions::default(),
+            metadata: self.metadata,
+            hdr: self.hdr,
+            transforms: Vec::new(),
+        }
+    }
+
+    pub fn validate(&self) -> Result<(), ValidationErrors> {
+        let mut errors: Vec<FieldError> = Vec::new();
+        if self.name.trim().is_empty() {
+            errors.push(FieldError::new("name", "must not be empty"));
+        }
+        // Mkv stores every codec, so with the source container the codec is
+        // only checked once the output is known.
+        let options = self.encode_options(self.container.unwrap_or(Container::Mkv));
+        if let Err(ValidationErrors(option_errors)) = options.validate() {
+            errors.extend(option_errors);
+        }
+
+        if errors.is_empty() {
+            Ok(())
+        } else {
+            Err(ValidationErrors(errors))
+        }
+    }
+}
+
+/// What to do when an output file already exists.
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, Default, PartialEq, Eq)]
+#[serde(rename_all = "camelCase")]
+pub enum ConflictPolicy {
+    Overwrite,
+    Skip,
+    /// Appends `_1`, `_2`, ... to the file name.
+    #[default]
+    AutoRename,
+}
+
+/// Output path of one video of a batch, rendered from a file name template.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct RenderOutputPathRequest {
+    pub video_path: String,
+    pub output_dir: String,
+    pub template: String,
+    pub options: EncodeOptions,
+    /// 1-based position of the video in the batch.
+    pub index: usize,
+    #[serde(default)]
+    pub conflict_policy: ConflictPolicy,
+    /// Paths already given to other videos of the batch.
+    #[serde(default)]
+    pub reserved: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
+}
+
+impl FieldError {
+    pub fn new(field: &str, message: &str) -> Self {
+        Self {
+            field: String::from(field),
+            message: String::from(message),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Debug)]
+pub struct ValidationErrors(pub Vec<FieldError>);
+
+impl fmt::Display for ValidationErrors {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fields: Vec<String> = self
+            .0
+            .iter()
+            .map(|error| format!("{}: {}", error.field, error.message))
+            .collect();
+        write!(f, "Invalid options. {}", fields.join("; "))
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone, Copy, Debug, PartialEq, Eq, AsRefStr)]
+#[serde(rename_all = "camelCase")]
+#[strum(serialize_all = "camelCase")]
+pub enum FfmpegErrorKind {
+    MissingInput,
+    UnsupportedCodec,
+    DiskFull,
+    PermissionDenied,
+    InvalidFilter,
+    DecodingError,
+    Cancelled,
+    FfmpegNotFound,
+    /// Options that failed validation, see `FfmpegError::field_errors`.
+    InvalidOptions,
+    /// Failures that match no other kind.
+    Unknown,
+}
+
+/// Why an ffmpeg/ffprobe run failed, sent to the frontend as the command error.
+#[derive(Serialize, Deserialize, Clone, Debug)]
+#[serde(rename_all = "camelCase")]
+pub struct FfmpegError {
+    pub kind: FfmpegErrorKind,
+    pub message: String,
+    /// Last lines ffmpeg wrote to stderr.
+    pub stderr: Option<String>,
+    pub exit_code: Option<i32>,
+    /// Per field errors of `InvalidOptions`.
+    #[serde(default)]
+    pub field_errors: Vec<FieldError>,
+}
+
+impl FfmpegError {
+    pub fn new(kind: FfmpegErrorKind, message: &str) -> Self {
+        Self {
+            kind,
+            message: String::from(message),
+            stderr: None,
+            exit_code: None,
+            field_errors: Vec::new(),
+        }
+    }
+
+    pub fn cancelled() -> Self {
+        Self::new(FfmpegErrorKind::Cancelled, "Compression was cancelled.")
+    }
+}
+
+impl fmt::Display for FfmpegError {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "{}", self.message)
+    }
+}
+
+impl From<String> for FfmpegError {
+    fn from(message: String) -> Self {
+        Self::new(FfmpegErrorKind::Unknown, &message)
+    }
+}
+
+impl From<ValidationErrors> for FfmpegError {
+    fn from(errors: ValidationErrors) -> Self {
+        let message = errors.to_string();
+        Self {
+            field_errors: errors.0,
+            ..Self::new(FfmpegErrorKind::InvalidOptions, &message)
+        }
+    }
+}
+
 #[derive(Serialize, Deserialize)]
 #[serde(rename_all = "camelCase")]
 pub struct QualityPreviewResult {
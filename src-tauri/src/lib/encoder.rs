@@ -0,0 +1,1635 @@
+use crate::{
+    domain::{
+        AudioCodec, Container, EncodeOptions, FfmpegCapabilities, HdrFormat, HdrMode, MetadataMode,
+        Preset, StreamSelection, StreamSelector, SubtitleBurnIn, TrimRange, VideoCodec,
+        VideoCoordinates, VideoTransform,
+    },
+    loudness::{self, LoudnessMeasurement},
+};
+
+pub const EXTENSIONS: [&str; 5] = ["mp4", "mov", "webm", "avi", "mkv"];
+
+/// CRF scale user quality is mapped onto. Lower the CRF, higher the quality.
+struct CrfRange {
+    min: u16,
+    max: u16,
+    default: u16,
+}
+
+const H264_CRF: CrfRange = CrfRange {
+    min: 24,
+    max: 36,
+    default: 28,
+};
+// x265 reaches x264 quality at a higher CRF
+const H265_CRF: CrfRange = CrfRange {
+    min: 26,
+    max: 38,
+    default: 30,
+};
+// libsvtav1, libaom-av1 and libvpx-vp9 use a 0-63 scale
+const AV1_CRF: CrfRange = CrfRange {
+    min: 30,
+    max: 50,
+    default: 38,
+};
+const VP9_CRF: CrfRange = CrfRange {
+    min: 28,
+    max: 46,
+    default: 35,
+};
+
+/// Audio bitrate reserved (and enforced) when encoding to a target size,
+/// unless the audio options set one.
+const TARGET_SIZE_AUDIO_KBPS: u32 = 128;
+/// Share of the target size kept free for container overhead.
+const TARGET_SIZE_MUXING_OVERHEAD: f64 = 0.02;
+const MIN_TARGET_VIDEO_KBPS: u32 = 32;
+
+#[cfg(windows)]
+const NULL_OUTPUT: &str = "NUL";
+#[cfg(not(windows))]
+const NULL_OUTPUT: &str = "/dev/null";
+
+/// Portion of the input to encode, used by quality previews.
+#[derive(Clone, Copy, Debug)]
+pub struct ClipRange {
+    pub start_seconds: f64,
+    pub duration_seconds: f64,
+}
+
+/// Input streams to encode, as positions among the input's streams of each
+/// kind (`0:a:N`, `0:s:N`), and the subtitle files added after them.
+#[derive(Clone, Debug, Default, PartialEq, Eq)]
+pub struct SelectedStreams {
+    pub audio: Vec<u32>,
+    pub subtitles: Vec<u32>,
+    pub subtitle_files: Vec<String>,
+}
+
+/// Subtitle codecs stored as pictures, which only mkv keeps and the
+/// `subtitles` filter cannot draw.
+const BITMAP_SUBTITLE_CODECS: [&str; 4] =
+    ["hdmv_pgs_subtitle", "dvd_subtitle", "dvb_subtitle", "xsub"];
+
+/// Filters tone mapping needs, missing from builds without zimg.
+const TONEMAP_FILTERS: [&str; 2] = ["zscale", "tonemap"];
+
+/// Tone maps HDR to SDR: to linear light and BT.709 primaries, through the
+/// `hable` curve, then to the BT.709 transfer and matrix in 8-bit.
+const TONEMAP_FILTER: &str = "zscale=t=linear:npl=100,format=gbrpf32le,zscale=p=bt709,tonemap=tonemap=hable:desat=0,zscale=t=bt709:m=bt709:r=tv,format=yuv420p";
+
+/// How an HDR input is encoded, see `hdr_conversion`.
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+pub enum HdrConversion {
+    /// Tone mapped to SDR BT.709.
+    Tonemap,
+    /// Kept as 10-bit video with BT.2020 primaries and the source's transfer.
+    Preserve(HdrFormat),
+}
+
+/// Everything needed to build one ffmpeg invocation.
+#[derive(Clone, Debug)]
+pub struct EncodeRequest {
+    pub input_path: String,
+    pub output_path: String,
+    pub options: EncodeOptions,
+    /// Encoder picked from the detected ffmpeg capabilities. `None` uses the
+    /// codec's preferred encoder.
+    pub video_encoder: Option<&'static str>,
+    /// Emit `-progress -` key/value pairs on stdout.
+    pub report_progress: bool,
+    /// Portion of the (trimmed) output to encode.
+    pub clip: Option<ClipRange>,
+    /// Whether the input has an audio stream. Kept ranges of a multi-range
+    /// trim are concatenated stream by stream, which fails on missing audio.
+    pub has_audio: bool,
+    /// Result of the measurement pass when normalizing loudness, see
+    /// `needs_loudness_measurement`. `None` leaves the loudness as is.
+    pub loudness: Option<LoudnessMeasurement>,
+    /// Streams kept by the options' stream selection, see `select_streams`.
+    /// `None` lets ffmpeg pick them.
+    pub streams: Option<SelectedStreams>,
+    /// Conversion of an HDR input, see `hdr_conversion`. `None` encodes the
+    /// input as is, as SDR inputs are.
+    pub hdr: Option<HdrConversion>,
+}
+
+/// Bitrates used for a two-pass target size encode.
+#[derive(Clone, Copy, Debug)]
+pub struct BitratePlan {
+    pub video_kbps: u32,
+    /// `None` when audio is muted.
+    pub audio_kbps: Option<u32>,
+}
+
+enum RateControl<'a> {
+    Crf(u16),
+    /// Single-pass average bitrate, for encoders without two-pass support.
+    Bitrate(BitratePlan),
+    TwoPass {
+        plan: BitratePlan,
+        pass: u8,
+        passlog_prefix: &'a str,
+    },
+}
+
+fn crf_range(codec: VideoCodec) -> CrfRange {
+    match codec {
+        VideoCodec::H264 => H264_CRF,
+        VideoCodec::H265 => H265_CRF,
+        VideoCodec::Av1 => AV1_CRF,
+        VideoCodec::Vp9 => VP9_CRF,
+    }
+}
+
+/// Maps user quality (0-100) onto the codec's CRF range (24-36 for H.264).
+pub fn quality_to_crf(codec: VideoCodec, quality: Option<u16>) -> u16 {
+    let range = crf_range(codec);
+    let quality = match quality {
+        Some(quality) if quality <= 100 => quality,
+        _ => return range.default,
+    };
+    let diff = (range.max - range.min) - ((range.max - range.min) * quality) / 100;
+    range.min + diff
+}
+
+/// Preferred ffmpeg encoder for a codec.
+pub fn video_encoder_for(codec: VideoCodec) -> &'static str {
+    codec.encoders()[0]
+}
+
+/// Picks the encoder for the request's codec among those the binary provides.
+pub fn select_video_encoder(
+    options: &EncodeOptions,
+    capabilities: &FfmpegCapabilities,
+) -> Result<&'static str, String> {
+    let codec = options.resolved_video_codec();
+    capabilities.video_encoder_for(codec).ok_or_else(|| {
+        format!(
+            "{} encoding is not available in this ffmpeg build (needs {}).",
+            codec.as_ref(),
+            codec.encoders().join(" or ")
+        )
+    })
+}
+
+/// Fails when the options' audio codec has no encoder in this ffmpeg build.
+pub fn check_audio_encoder(
+    options: &EncodeOptions,
+    capabilities: &FfmpegCapabilities,
+) -> Result<(), String> {
+    let Some(codec) = options.audio.codec.filter(|_| !options.mute_audio) else {
+        return Ok(());
+    };
+    match codec.encoder() {
+        Some(encoder) if !capabilities.has_encoder(encoder) => Err(format!(
+            "{} audio encoding is not available in this ffmpeg build (needs {encoder}).",
+            codec.as_ref()
+        )),
+        _ => Ok(()),
+    }
+}
+
+/// Fails when subtitles are burned in and this ffmpeg build has no
+/// `subtitles` filter, which needs libass.
+pub fn check_subtitle_filter(
+    options: &EncodeOptions,
+    capabilities: &FfmpegCapabilities,
+) -> Result<(), String> {
+    if options.subtitles.burn_in.is_some() && !capabilities.has_filter("subtitles") {
+        return Err(String::from(
+            "Burning in subtitles is not available in this ffmpeg build (needs the subtitles filter).",
+        ));
+    }
+    Ok(())
+}
+
+/// Whether the binary can tone map HDR. Unknown capabilities are assumed to.
+pub fn can_tonemap(capabilities: Option<&FfmpegCapabilities>) -> bool {
+    capabilities.is_none_or(|capabilities| {
+        TONEMAP_FILTERS
+            .iter()
+            .all(|filter| capabilities.has_filter(filter))
+    })
+}
+
+/// Conversion of an input in the `source` HDR format, `None` for SDR inputs.
+/// Without the tone mapping filters it stays `None` too, so the video is
+/// encoded as is. Fails when the codec cannot keep HDR.
+pub fn hdr_conversion(
+    options: &EncodeOptions,
+    source: Option<HdrFormat>,
+    capabilities: Option<&FfmpegCapabilities>,
+) -> Result<Option<HdrConversion>, String> {
+    let Some(format) = source else {
+        return Ok(None);
+    };
+    let codec = options.resolved_video_codec();
+    match options.hdr {
+        HdrMode::Preserve if codec.supports_hdr() => Ok(Some(HdrConversion::Preserve(format))),
+        HdrMode::Preserve => Err(format!(
+            "{} cannot keep HDR, use h265, av1 or vp9.",
+            codec.as_ref()
+        )),
+        HdrMode::Tonemap if can_tonemap(capabilities) => Ok(Some(HdrConversion::Tonemap)),
+        HdrMode::Tonemap => Ok(None),
+    }
+}
+
+fn request_video_encoder(request: &EncodeRequest) -> &'static str {
+    request
+        .video_encoder
+        .unwrap_or_else(|| video_encoder_for(request.options.resolved_video_codec()))
+}
+
+/// libvpx and libaom only run in constant quality mode with `-b:v 0`.
+fn needs_zero_bitrate_for_crf(encoder: &str) -> bool {
+    matches!(encoder, "libvpx-vp9" | "libaom-av1")
+}
+
+/// ffmpeg's libsvtav1 wrapper does not write pass statistics.
+fn supports_two_pass(encoder: &str) -> bool {
+    encoder != "libsvtav1"
+}
+
+/// Speed/efficiency trade-off flags, each encoder names them differently.
+fn speed_args(encoder: &str, preset: Preset) -> Vec<String> {
+    let is_ironclad = preset == Preset::Ironclad;
+    let args: Vec<&str> = match encoder {
+        "libx264" | "libx265" if is_ironclad => vec!["-preset", "slow"],
+        "libsvtav1" => vec!["-preset", if is_ironclad { "6" } else { "10" }],
+        "libaom-av1" => vec![
+            "-cpu-used",
+            if is_ironclad { "4" } else { "6" },
+            "-row-mt",
+            "1",
+        ],
+        "libvpx-vp9" => vec![
+            "-deadline",
+            "good",
+            "-cpu-used",
+            if is_ironclad { "2" } else { "4" },
+            "-row-mt",
+            "1",
+        ],
+        _ => vec![],
+    };
+    args.into_iter().map(String::from).collect()
+}
+
+/// Builds the `-vf` chain: transforms, optional scale, then even-dimension padding.
+pub fn build_video_filters(transforms: &[VideoTransform], dimensions: Option<(u32, u32)>) -> String {
+    let padding = "pad=ceil(iw/2)*2:ceil(ih/2)*2";
+    let pad_filter = if let Some((width, height)) = dimensions {
+        format!("scale={}:{},{}", width, height, padding)
+    } else {
+        padding.to_string()
+    };
+
+    let transform_filters = build_transform_filters(transforms);
+
+    let mut vf_filter = String::new();
+    if !transform_filters.is_empty() {
+        vf_filter.push_str(&transform_filters);
+        vf_filter.push(',');
+    }
+    vf_filter.push_str(&pad_filter);
+    vf_filter
+}
+
+fn build_transform_filters(transforms: &[VideoTransform]) -> String {
+    let mut filters: Vec<String> = Vec::new();
+    let mut latest_crop: Option<&VideoCoordinates> = None;
+
+    for transform in transforms {
+        match transform {
+            VideoTransform::Rotate(angle) => match angle % 360 {
+                -90 | 270 => filters.push("transpose=2".to_string()),
+                90 | -270 => filters.push("transpose=1".to_string()),
+                180 | -180 => filters.push("hflip,vflip".to_string()),
+                _ => {}
+            },
+            VideoTransform::Flip(flip) => {
+                if flip.horizontal {
+                    filters.push("hflip".to_string());
+                }
+                if flip.vertical {
+                    filters.push("vflip".to_string());
+                }
+            }
+            VideoTransform::Crop(crop) => {
+                latest_crop = Some(crop);
+            }
+            // Applied through the inputs, see `input_args`
+            VideoTransform::Trim(_) => {}
+        }
+    }
+
+    // Apply only the last crop
+    if let Some(c) = latest_crop {
+        let w = c.width.round() as i64;
+        let h = c.height.round() as i64;
+        let x = c.left.round() as i64;
+        let y = c.top.round() as i64;
+
+        filters.push(format!("crop={}:{}:{}:{}", w, h, x, y));
+    }
+
+    filters.join(",")
+}
+
+/// Ranges kept by the last trim transform, `None` when the whole video is kept.
+pub fn trim_ranges(transforms: &[VideoTransform]) -> Option<&[TrimRange]> {
+    transforms
+        .iter()
+        .rev()
+        .find_map(|transform| match transform {
+            VideoTransform::Trim(ranges) => Some(ranges.as_slice()),
+            _ => None,
+        })
+        .filter(|ranges| !ranges.is_empty())
+}
+
+/// Length of the encoded video: the kept ranges of a trim, clamped to the
+/// source, or the whole source. `None` when it depends on an unknown source
+/// duration.
+pub fn output_duration(
+    transforms: &[VideoTransform],
+    source_duration: Option<f64>,
+) -> Result<Option<f64>, String> {
+    let Some(ranges) = trim_ranges(transforms) else {
+        return Ok(source_duration);
+    };
+
+    let mut total_seconds = 0.0;
+    for range in ranges {
+        if let Some(source_duration) = source_duration {
+            if range.start_seconds >= source_duration {
+                return Err(format!(
+                    "The trim range starting at {:.1}s begins after the end of the video ({:.1}s).",
+                    range.start_seconds, source_duration
+                ));
+            }
+        }
+        let end_seconds = match (range.end_seconds, source_duration) {
+            (Some(end), Some(source_duration)) => end.min(source_duration),
+            (Some(end), None) => end,
+            (None, Some(source_duration)) => source_duration,
+            (None, None) => return Ok(None),
+        };
+        total_seconds += end_seconds - range.start_seconds;
+    }
+    Ok(Some(total_seconds))
+}
+
+/// Whether the streams are mapped explicitly instead of picked by ffmpeg.
+pub fn picks_streams(options: &EncodeOptions) -> bool {
+    !options.streams.is_default() || !options.subtitles.files.is_empty()
+}
+
+/// Resolves the stream selection of `options` against the languages of the
+/// input's audio and subtitle streams, see `MediaInfo::stream_languages`.
+/// `None` when ffmpeg picks the streams itself.
+pub fn select_streams(
+    options: &EncodeOptions,
+    audio_languages: &[Option<String>],
+    subtitle_languages: &[Option<String>],
+) -> Result<Option<SelectedStreams>, String> {
+    if let Some(SubtitleBurnIn::Stream(position)) = options.subtitles.burn_in {
+        if position as usize >= subtitle_languages.len() {
+            return Err(format!(
+                "There is no subtitle stream {position} to burn in, the input has {} subtitle streams.",
+                subtitle_languages.len()
+            ));
+        }
+    }
+
+    let streams = &options.streams;
+    if !picks_streams(options) {
+        return Ok(None);
+    }
+
+    let audio = match &streams.audio {
+        _ if options.mute_audio => Vec::new(),
+        StreamSelection::Default if audio_languages.is_empty() => Vec::new(),
+        StreamSelection::Default => vec![0],
+        selection => resolve_selection(selection, audio_languages, "audio")?,
+    };
+    let subtitles = match &streams.subtitles {
+        StreamSelection::Default => Vec::new(),
+        selection => resolve_selection(selection, subtitle_languages, "subtitle")?,
+    };
+    if options.audio.normalize && !streams.mix_audio && audio.len() > 1 {
+        return Err(String::from(
+            "Loudness normalization needs a single audio stream, mix the streams or keep one.",
+        ));
+    }
+
+    Ok(Some(SelectedStreams {
+        audio,
+        subtitles,
+        subtitle_files: options.subtitles.files.clone(),
+    }))
+}
+
+/// Fails on picture-based subtitles the output cannot store or burn in.
+/// `subtitle_codecs` has the codec of each of the input's subtitle streams.
+pub fn check_subtitle_codecs(
+    options: &EncodeOptions,
+    streams: Option<&SelectedStreams>,
+    subtitle_codecs: &[Option<String>],
+) -> Result<(), String> {
+    let bitmap_codec = |position: u32| {
+        subtitle_codecs
+            .get(position as usize)
+            .and_then(Option::as_deref)
+            .filter(|codec| BITMAP_SUBTITLE_CODECS.contains(codec))
+    };
+
+    if options.container != Container::Mkv {
+        for position in streams.map_or(&[][..], |streams| streams.subtitles.as_slice()) {
+            if let Some(codec) = bitmap_codec(*position) {
+                return Err(format!(
+                    "Subtitle stream {position} is a picture-based {codec} subtitle, which only mkv can store."
+                ));
+            }
+        }
+    }
+    if let Some(SubtitleBurnIn::Stream(position)) = options.subtitles.burn_in {
+        if let Some(codec) = bitmap_codec(position) {
+            return Err(format!(
+                "Subtitle stream {position} is a picture-based {codec} subtitle, only text subtitles can be burned in."
+            ));
+        }
+    }
+    Ok(())
+}
+
+/// Positions picked by `selection`, in the order they are picked.
+fn resolve_selection(
+    selection: &StreamSelection,
+    languages: &[Option<String>],
+    kind: &str,
+) -> Result<Vec<u32>, String> {
+    let selectors = match selection {
+        StreamSelection::Default => return Ok(Vec::new()),
+        StreamSelection::All => return Ok((0..languages.len() as u32).collect()),
+        StreamSelection::Pick(selectors) => selectors,
+    };
+
+    let mut positions: Vec<u32> = Vec::new();
+    for selector in selectors {
+        let matched: Vec<u32> = match selector {
+            StreamSelector::Index(index) => {
+                if *index as usize >= languages.len() {
+                    return Err(format!(
+                        "There is no {kind} stream {index}, the input has {} {kind} streams.",
+                        languages.len()
+                    ));
+                }
+                vec![*index]
+            }
+            StreamSelector::Language(language) => {
+                let matched: Vec<u32> = languages
+                    .iter()
+                    .enumerate()
+                    .filter(|(_, tag)| {
+                        tag.as_deref()
+                            .is_some_and(|tag| tag.eq_ignore_ascii_case(language.trim()))
+                    })
+                    .map(|(position, _)| position as u32)
+                    .collect();
+                if matched.is_empty() {
+                    return Err(format!("The input has no {kind} stream in '{language}'."));
+                }
+                matched
+            }
+        };
+        for position in matched {
+            if !positions.contains(&position) {
+                positions.push(position);
+            }
+        }
+    }
+    Ok(positions)
+}
+
+/// Positions of the input's audio streams that are encoded.
+fn audio_positions(request: &EncodeRequest) -> Vec<u32> {
+    match &request.streams {
+        _ if request.options.mute_audio => Vec::new(),
+        Some(streams) => streams.audio.clone(),
+        None if request.has_audio => vec![0],
+        None => Vec::new(),
+    }
+}
+
+/// Number of audio streams in the output.
+pub fn output_audio_streams(request: &EncodeRequest) -> usize {
+    let streams = audio_positions(request).len();
+    if request.options.streams.mix_audio {
+        streams.min(1)
+    } else {
+        streams
+    }
+}
+
+/// One `-i` per kept range when trimming. Seeking before `-i` decodes from the
+/// previous keyframe and drops the frames before `-ss`, so cuts are frame
+/// accurate and each range starts at timestamp 0.
+fn input_args(request: &EncodeRequest) -> Vec<String> {
+    let mut args = input_file_args(request);
+    args.extend(subtitle_file_args(request));
+    args.push(String::from("-hide_banner"));
+    if request.report_progress {
+        args.push(String::from("-progress"));
+        args.push(String::from("-"));
+    }
+    args.extend([
+        String::from("-nostats"),
+        String::from("-loglevel"),
+        String::from("error"),
+    ]);
+    if let Some(clip) = request.clip {
+        args.push(String::from("-ss"));
+        args.push(format!("{:.3}", clip.start_seconds));
+        args.push(String::from("-t"));
+        args.push(format!("{:.3}", clip.duration_seconds));
+    }
+    args
+}
+
+fn input_file_args(request: &EncodeRequest) -> Vec<String> {
+    let mut args: Vec<String> = Vec::new();
+    match trim_ranges(&request.options.transforms) {
+        Some(ranges) => {
+            for range in ranges {
+                args.extend(range_args(range));
+                args.push(String::from("-i"));
+                args.push(request.input_path.clone());
+            }
+        }
+        None => {
+            args.push(String::from("-i"));
+            args.push(request.input_path.clone());
+        }
+    }
+    args
+}
+
+/// Subtitle files are read as inputs after the video's, cut like the kept
+/// range of a trim so they stay in sync. Several ranges cannot keep
+/// subtitles, see `EncodeOptions::validate`.
+fn subtitle_file_args(request: &EncodeRequest) -> Vec<String> {
+    let Some(streams) = &request.streams else {
+        return Vec::new();
+    };
+    let range = trim_ranges(&request.options.transforms).and_then(<[TrimRange]>::first);
+    let mut args: Vec<String> = Vec::new();
+    for file in &streams.subtitle_files {
+        if let Some(range) = range {
+            args.extend(range_args(range));
+        }
+        args.push(String::from("-i"));
+        args.push(file.clone());
+    }
+    args
+}
+
+fn range_args(range: &TrimRange) -> Vec<String> {
+    let mut args = vec![String::from("-ss"), format!("{:.3}", range.start_seconds)];
+    if let Some(end_seconds) = range.end_seconds {
+        args.push(String::from("-t"));
+        args.push(format!("{:.3}", end_seconds - range.start_seconds));
+    }
+    args
+}
+
+/// Whether the loudness of the request's audio has to be measured first,
+/// with the arguments of `build_loudness_args`.
+pub fn needs_loudness_measurement(request: &EncodeRequest) -> bool {
+    request.options.audio.normalize && !audio_positions(request).is_empty()
+}
+
+/// Arguments of the `loudnorm` measurement pass over the kept audio, ignoring
+/// any preview clip so previews get the gain of the whole video. The result
+/// is printed to stderr, see `loudness::parse_measurement`.
+pub fn build_loudness_args(request: &EncodeRequest) -> Vec<String> {
+    let mut args = input_file_args(request);
+    // loudnorm prints its summary at the info level
+    args.extend([
+        String::from("-hide_banner"),
+        String::from("-nostats"),
+        String::from("-loglevel"),
+        String::from("info"),
+    ]);
+
+    let segments = trim_ranges(&request.options.transforms).map_or(1, <[TrimRange]>::len);
+    let positions = audio_positions(request);
+    let mix = request.options.streams.mix_audio && positions.len() > 1;
+    let measure_filter = loudness::measure_filter();
+    if segments > 1 || mix {
+        let (graph, outputs) =
+            build_filter_graph(segments, None, &positions, mix, Some(&measure_filter));
+        args.push(String::from("-filter_complex"));
+        args.push(graph);
+        for output in outputs {
+            args.push(String::from("-map"));
+            args.push(output);
+        }
+    } else {
+        args.extend([
+            String::from("-map"),
+            format!("0:a:{}", positions.first().copied().unwrap_or(0)),
+            String::from("-af"),
+            measure_filter,
+        ]);
+    }
+
+    args.extend([
+        String::from("-vn"),
+        String::from("-f"),
+        String::from("null"),
+        String::from(NULL_OUTPUT),
+    ]);
+    args
+}
+
+/// Audio filters, currently only loudness normalization.
+fn build_audio_filters(request: &EncodeRequest) -> Option<String> {
+    if !request.options.audio.normalize {
+        return None;
+    }
+    request.loudness.as_ref().map(loudness::normalize_filter)
+}
+
+/// Codec, bitrate, channel and sample rate flags. `plan_kbps` is the bitrate
+/// reserved by a target size plan.
+fn audio_args(request: &EncodeRequest, plan_kbps: Option<u32>) -> Vec<String> {
+    let audio = &request.options.audio;
+    let mut args: Vec<String> = Vec::new();
+    match audio.codec {
+        Some(AudioCodec::Copy) => {
+            args.push(String::from("-c:a"));
+            args.push(String::from("copy"));
+            return args;
+        }
+        Some(codec) => {
+            if let Some(encoder) = codec.encoder() {
+                args.push(String::from("-c:a"));
+                args.push(encoder.to_string());
+            }
+        }
+        None => {}
+    }
+    if let Some(kbps) = plan_kbps.or(audio.bitrate_kbps) {
+        args.push(String::from("-b:a"));
+        args.push(format!("{kbps}k"));
+    }
+    if let Some(channels) = audio.channels {
+        args.push(String::from("-ac"));
+        args.push(channels.to_string());
+    }
+    let sample_rate = audio
+        .sample_rate
+        .or_else(|| build_audio_filters(request).map(|_| loudness::DEFAULT_SAMPLE_RATE));
+    if let Some(sample_rate) = sample_rate {
+        args.push(String::from("-ar"));
+        args.push(sample_rate.to_string());
+    }
+    args
+}
+
+/// Codec of the kept subtitles, converted to one the container stores.
+fn subtitle_args(request: &EncodeRequest) -> Vec<String> {
+    let kept = request.streams.as_ref().map_or(0, |streams| {
+        streams.subtitles.len() + streams.subtitle_files.len()
+    });
+    match request.options.container.subtitle_encoder() {
+        Some(encoder) if kept > 0 => vec![String::from("-c:s"), encoder.to_string()],
+        _ => Vec::new(),
+    }
+}
+
+/// Color tags of the output of an HDR input, which the encoders write into
+/// the bitstream.
+fn color_args(request: &EncodeRequest) -> Vec<String> {
+    let (primaries, transfer, matrix) = match request.hdr {
+        None => return Vec::new(),
+        Some(HdrConversion::Tonemap) => ("bt709", "bt709", "bt709"),
+        Some(HdrConversion::Preserve(format)) => ("bt2020", format.transfer(), "bt2020nc"),
+    };
+    [
+        "-color_primaries",
+        primaries,
+        "-color_trc",
+        transfer,
+        "-colorspace",
+        matrix,
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
+/// Tags and chapters of the output, see `MetadataMode`. Stream tags are
+/// mapped along with their streams unless stripped.
+fn metadata_args(request: &EncodeRequest) -> Vec<String> {
+    let args: &[&str] = match request.options.metadata.mode {
+        MetadataMode::Default => &[],
+        // Chapters of the first range would not match the joined ranges.
+        MetadataMode::Preserve
+            if trim_ranges(&request.options.transforms)
+                .is_some_and(|ranges| ranges.len() > 1) =>
+        {
+            &["-map_metadata", "0", "-map_chapters", "-1"]
+        }
+        MetadataMode::Preserve => &["-map_metadata", "0", "-map_chapters", "0"],
+        MetadataMode::Strip => &[
+            "-map_metadata",
+            "-1",
+            "-map_metadata:s",
+            "-1",
+            "-map_chapters",
+            "-1",
+        ],
+    };
+    args.iter().map(|arg| arg.to_string()).collect()
+}
+
+/// Escapes a filter option value, then the filter graph around it, so paths
+/// keep their `:`, `\`, `'` and `,` (e.g. `C:\Videos\a.srt`).
+fn escape_filter_value(value: &str) -> String {
+    let escape = |value: &str, special: &[char]| {
+        let mut escaped = String::with_capacity(value.len());
+        for character in value.chars() {
+            if special.contains(&character) {
+                escaped.push('\\');
+            }
+            escaped.push(character);
+        }
+        escaped
+    };
+    escape(
+        &escape(value, &['\\', '\'', ':']),
+        &['\\', '\'', '[', ']', ',', ';'],
+    )
+}
+
+/// `subtitles` filter drawing the burned in subtitle. It reads the subtitles
+/// from the start of their file, so a trimmed video is shifted back to the
+/// source's timestamps while they are drawn.
+fn burn_in_filter(request: &EncodeRequest) -> Option<String> {
+    let mut filter = match request.options.subtitles.burn_in.as_ref()? {
+        SubtitleBurnIn::Stream(position) => format!(
+            "subtitles=filename={}:si={position}",
+            escape_filter_value(&request.input_path)
+        ),
+        SubtitleBurnIn::File(path) => format!("subtitles=filename={}", escape_filter_value(path)),
+    };
+    let start_seconds = trim_ranges(&request.options.transforms)
+        .and_then(<[TrimRange]>::first)
+        .map_or(0.0, |range| range.start_seconds);
+    if start_seconds > 0.0 {
+        filter = format!("setpts=PTS+{start_seconds:.3}/TB,{filter},setpts=PTS-STARTPTS");
+    }
+    Some(filter)
+}
+
+/// `-vf` and `-af` with the video and audio filters, and the maps of the
+/// selected streams. Joining the ranges of a multi-range trim or mixing audio
+/// streams instead takes a `-filter_complex` graph that ends with those
+/// filters, and maps the graph's outputs. `video_only` leaves out audio and
+/// subtitles.
+fn filter_args(request: &EncodeRequest, video_only: bool) -> Vec<String> {
+    let options = &request.options;
+    let mut video_filters = build_video_filters(&options.transforms, options.dimensions);
+    if request.hdr == Some(HdrConversion::Tonemap) {
+        video_filters.push(',');
+        video_filters.push_str(TONEMAP_FILTER);
+    }
+    // Drawn last, at the output size.
+    if let Some(burn_in) = burn_in_filter(request) {
+        video_filters.push(',');
+        video_filters.push_str(&burn_in);
+    }
+    let audio_filters = build_audio_filters(request);
+    let segments = trim_ranges(&options.transforms).map_or(1, <[TrimRange]>::len);
+    let positions = if video_only {
+        Vec::new()
+    } else {
+        audio_positions(request)
+    };
+    let mix = options.streams.mix_audio && positions.len() > 1;
+    let (subtitles, subtitle_files) = match &request.streams {
+        Some(streams) if !video_only => (
+            streams.subtitles.as_slice(),
+            streams.subtitle_files.as_slice(),
+        ),
+        _ => (&[][..], &[][..]),
+    };
+
+    let mut args: Vec<String> = Vec::new();
+    if segments > 1 || mix {
+        let (graph, outputs) = build_filter_graph(
+            segments,
+            Some(&video_filters),
+            &positions,
+            mix,
+            audio_filters.as_deref(),
+        );
+        args.push(String::from("-filter_complex"));
+        args.push(graph);
+        for output in outputs {
+            args.push(String::from("-map"));
+            args.push(output);
+        }
+    } else {
+        if request.streams.is_some() {
+            args.push(String::from("-map"));
+            args.push(String::from("0:v:0"));
+            for position in &positions {
+                args.push(String::from("-map"));
+                args.push(format!("0:a:{position}"));
+            }
+        }
+        args.push(String::from("-vf"));
+        args.push(video_filters);
+        if let Some(audio_filters) = audio_filters.filter(|_| !positions.is_empty()) {
+            args.push(String::from("-af"));
+            args.push(audio_filters);
+        }
+    }
+    for position in subtitles {
+        args.push(String::from("-map"));
+        args.push(format!("0:s:{position}"));
+    }
+    // Subtitle files come after the inputs of the video, see `input_args`.
+    for index in 0..subtitle_files.len() {
+        args.push(String::from("-map"));
+        args.push(format!("{}:s:0", segments + index));
+    }
+    args
+}
+
+/// Filter graph that joins the inputs of a multi-range trim with `concat` and
+/// mixes the audio streams at `positions` into one when `mix` is set, before
+/// applying the filters. `video_filters` is `None` for audio-only graphs.
+/// Returns the graph and the labels of its outputs.
+fn build_filter_graph(
+    segments: usize,
+    video_filters: Option<&str>,
+    positions: &[u32],
+    mix: bool,
+    audio_filters: Option<&str>,
+) -> (String, Vec<String>) {
+    let mut chains: Vec<String> = Vec::new();
+    let mut outputs: Vec<String> = Vec::new();
+
+    let audio_sources: Vec<String> = if segments > 1 {
+        let mut inputs = String::new();
+        for index in 0..segments {
+            if video_filters.is_some() {
+                inputs.push_str(&format!("[{index}:v:0]"));
+            }
+            for position in positions {
+                inputs.push_str(&format!("[{index}:a:{position}]"));
+            }
+        }
+        // Joined audio goes straight to the output unless filtered or mixed.
+        let audio_label = if mix || audio_filters.is_some() {
+            "ajoined"
+        } else {
+            "a"
+        };
+        let audio_sources: Vec<String> = (0..positions.len())
+            .map(|index| format!("[{audio_label}{index}]"))
+            .collect();
+        let video_output = if video_filters.is_some() {
+            "[joined]"
+        } else {
+            ""
+        };
+        chains.push(format!(
+            "{inputs}concat=n={segments}:v={}:a={}{video_output}{}",
+            u8::from(video_filters.is_some()),
+            positions.len(),
+            audio_sources.concat()
+        ));
+        audio_sources
+    } else {
+        positions
+            .iter()
+            .map(|position| format!("[0:a:{position}]"))
+            .collect()
+    };
+
+    if let Some(video_filters) = video_filters {
+        let video_source = if segments > 1 { "[joined]" } else { "[0:v:0]" };
+        chains.push(format!("{video_source}{video_filters}[v]"));
+        outputs.push(String::from("[v]"));
+    }
+
+    if mix {
+        let mut chain = format!(
+            "{}amix=inputs={}:duration=longest",
+            audio_sources.concat(),
+            audio_sources.len()
+        );
+        if let Some(audio_filters) = audio_filters {
+            chain.push(',');
+            chain.push_str(audio_filters);
+        }
+        chain.push_str("[a]");
+        chains.push(chain);
+        outputs.push(String::from("[a]"));
+    } else {
+        for (index, source) in audio_sources.iter().enumerate() {
+            let output = format!("[a{index}]");
+            if let Some(audio_filters) = audio_filters {
+                chains.push(format!("{source}{audio_filters}{output}"));
+            }
+            outputs.push(output);
+        }
+    }
+
+    (chains.join(";"), outputs)
+}
+
+/// Splits a target file size (in MB, 1 MB = 1,000,000 bytes) into video and
+/// audio bitrates for the given duration. `audio_kbps` is reserved for each of
+/// the `audio_streams` output streams.
+pub fn plan_target_size(
+    target_size_mb: f64,
+    duration_seconds: f64,
+    audio_kbps: Option<u32>,
+    audio_streams: usize,
+) -> Result<BitratePlan, String> {
+    if !(duration_seconds.is_finite() && duration_seconds > 0.0) {
+        return Err(String::from("Video duration is required to compress to a target size."));
+    }
+
+    let total_kbps =
+        (target_size_mb * 8_000.0 / duration_seconds) * (1.0 - TARGET_SIZE_MUXING_OVERHEAD);
+    let video_kbps = total_kbps - f64::from(audio_kbps.unwrap_or(0)) * audio_streams as f64;
+
+    if video_kbps < f64::from(MIN_TARGET_VIDEO_KBPS) {
+        return Err(format!(
+            "Target size of {target_size_mb} MB is too small for a video of {duration_seconds:.0} seconds."
+        ));
+    }
+
+    Ok(BitratePlan {
+        video_kbps: video_kbps.floor() as u32,
+        audio_kbps,
+    })
+}
+
+/// Audio bitrate to reserve in a target size plan, `None` when muted.
+pub fn target_size_audio_kbps(options: &EncodeOptions) -> Option<u32> {
+    if options.mute_audio {
+        return None;
+    }
+    Some(options.audio.bitrate_kbps.unwrap_or(TARGET_SIZE_AUDIO_KBPS))
+}
+
+/// Turns an encode request into the ffmpeg argument vector.
+pub fn build_ffmpeg_args(request: &EncodeRequest) -> Vec<String> {
+    let crf = quality_to_crf(
+        request.options.resolved_video_codec(),
+        request.options.quality,
+    );
+    build_args(request, RateControl::Crf(crf))
+}
+
+/// Argument vectors for every pass of a target size encode. Usually two: the
+/// first only writes the `passlog_prefix` stats files and discards its output.
+/// Encoders without two-pass support get a single average bitrate pass.
+pub fn build_target_size_args(
+    request: &EncodeRequest,
+    plan: BitratePlan,
+    passlog_prefix: &str,
+) -> Vec<Vec<String>> {
+    let encoder = request_video_encoder(request);
+    if !supports_two_pass(encoder) {
+        return vec![build_args(request, RateControl::Bitrate(plan))];
+    }
+    [1, 2]
+        .into_iter()
+        .map(|pass| {
+            build_args(
+                request,
+                RateControl::TwoPass {
+                    plan,
+                    pass,
+                    passlog_prefix,
+                },
+            )
+        })
+        .collect()
+}
+
+fn build_args(request: &EncodeRequest, rate_control: RateControl) -> Vec<String> {
+    let mut args = input_args(request);
+
+    let options = &request.options;
+    let codec = options.resolved_video_codec();
+    let encoder = request_video_encoder(request);
+    let is_ironclad = options.preset == Preset::Ironclad;
+
+    let pix_fmt = match request.hdr {
+        Some(HdrConversion::Preserve(_)) => Some("yuv420p10le"),
+        _ if is_ironclad => Some("yuv420p"),
+        _ => None,
+    };
+    if let Some(pix_fmt) = pix_fmt {
+        args.push(String::from("-pix_fmt"));
+        args.push(String::from(pix_fmt));
+    }
+    args.push(String::from("-c:v"));
+    args.push(encoder.to_string());
+    args.extend(color_args(request));
+
+    match &rate_control {
+        RateControl::Crf(crf) => {
+            if is_ironclad || needs_zero_bitrate_for_crf(encoder) {
+                args.push(String::from("-b:v"));
+                args.push(String::from("0"));
+            }
+            args.extend(speed_args(encoder, options.preset));
+            if is_ironclad && codec == VideoCodec::H264 {
+                args.push(String::from("-qp"));
+                args.push(String::from("0"));
+            }
+            args.push(String::from("-crf"));
+            args.push(crf.to_string());
+            if encoder == "libx265" {
+                args.push(String::from("-x265-params"));
+                args.push(String::from("log-level=error"));
+            }
+        }
+        RateControl::Bitrate(plan) => {
+            args.push(String::from("-b:v"));
+            args.push(format!("{}k", plan.video_kbps));
+            args.extend(speed_args(encoder, options.preset));
+        }
+        RateControl::TwoPass {
+            plan,
+            pass,
+            passlog_prefix,
+        } => {
+            args.push(String::from("-b:v"));
+            args.push(format!("{}k", plan.video_kbps));
+            args.extend(speed_args(encoder, options.preset));
+            if encoder == "libx265" {
+                // x265 keeps its own stats file; quoting protects Windows drive colons
+                args.push(String::from("-x265-params"));
+                args.push(format!(
+                    "log-level=error:pass={pass}:stats='{passlog_prefix}-x265.log'"
+                ));
+            } else {
+                args.extend([
+                    String::from("-pass"),
+                    pass.to_string(),
+                    String::from("-passlogfile"),
+                    passlog_prefix.to_string(),
+                ]);
+            }
+        }
+    }
+
+    let mut movflags: Vec<&str> = Vec::new();
+    if is_ironclad {
+        movflags.push("+faststart");
+    }
+    // Keeps tags mp4 has no standard atom for, e.g. the camera model.
+    if options.metadata.mode == MetadataMode::Preserve
+        && matches!(options.container, Container::Mp4 | Container::Mov)
+    {
+        movflags.push("+use_metadata_tags");
+    }
+    if !movflags.is_empty() {
+        args.push(String::from("-movflags"));
+        args.push(movflags.concat());
+    }
+
+    // Apple players only accept HEVC tagged as hvc1
+    if codec == VideoCodec::H265 && matches!(options.container, Container::Mp4 | Container::Mov) {
+        args.push(String::from("-tag:v"));
+        args.push(String::from("hvc1"));
+    }
+
+    let is_first_pass = matches!(rate_control, RateControl::TwoPass { pass: 1, .. });
+    args.extend(filter_args(request, is_first_pass));
+
+    if let Some(fps) = options.fps {
+        args.push(String::from("-r"));
+        args.push(fps.to_string());
+    }
+
+    if is_first_pass {
+        args.extend([
+            String::from("-an"),
+            String::from("-f"),
+            String::from("null"),
+            String::from(NULL_OUTPUT),
+            String::from("-y"),
+        ]);
+        return args;
+    }
+
+    let audio_kbps = match rate_control {
+        RateControl::Bitrate(plan) | RateControl::TwoPass { plan, .. } => plan.audio_kbps,
+        RateControl::Crf(_) => None,
+    };
+    if options.mute_audio {
+        args.push(String::from("-an"));
+    } else {
+        args.extend(audio_args(request, audio_kbps));
+    }
+    args.extend(subtitle_args(request));
+    args.extend(metadata_args(request));
+
+    args.push(request.output_path.clone());
+    args.push(String::from("-y"));
+
+    args
+}
+
+/// Arguments for the near-lossless, muted reference clip shown next to a
+/// compressed quality preview. Uses the same filters so both clips line up.
+pub fn build_preview_source_args(request: &EncodeRequest) -> Vec<String> {
+    let mut args = input_args(request);
+
+    args.extend([
+        String::from("-pix_fmt"),
+        String::from("yuv420p"),
+        String::from("-c:v"),
+        String::from("libx264"),
+        String::from("-preset"),
+        String::from("veryfast"),
+        String::from("-crf"),
+        String::from("18"),
+        String::from("-movflags"),
+        String::from("+faststart"),
+    ]);
+    args.extend(filter_args(request, true));
+
+    if let Some(fps) = request.options.fps {
+        args.push(String::from("-r"));
+        args.push(fps.to_string());
+    }
+
+    args.push(String::from("-an"));
+    args.push(request.output_path.clone());
+    args.push(String::from("-y"));
+
+    args
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn request(options: serde_json::Value) -> EncodeRequest {
+        EncodeRequest {
+            input_path: String::from("in.mp4"),
+            output_path: String::from("out.mp4"),
+            options: serde_json::from_value(options).unwrap(),
+            video_encoder: None,
+            report_progress: false,
+            clip: None,
+            has_audio: true,
+            loudness: None,
+            streams: None,
+            hdr: None,
+        }
+    }
+
+    fn two_ranges() -> serde_json::Value {
+        json!([{
+            "type": "trim",
+            "value": [
+                { "startSeconds": 0, "endSeconds": 5 },
+                { "startSeconds": 10 }
+            ]
+        }])
+    }
+
+    /// Whether `expected` appears in `args` as consecutive arguments.
+    fn has_args(args: &[String], expected: &[&str]) -> bool {
+        args.windows(expected.len()).any(|window| {
+            window
+                .iter()
+                .zip(expected)
+                .all(|(arg, expected)| arg == expected)
+        })
+    }
+
+    fn value_of<'a>(args: &'a [String], flag: &str) -> Option<&'a str> {
+        args.iter()
+            .position(|arg| arg == flag)
+            .and_then(|index| args.get(index + 1))
+            .map(String::as_str)
+    }
+
+    #[test]
+    fn quality_maps_onto_the_codec_crf_range() {
+        assert_eq!(quality_to_crf(VideoCodec::H264, None), 28);
+        assert_eq!(quality_to_crf(VideoCodec::H264, Some(100)), 24);
+        assert_eq!(quality_to_crf(VideoCodec::H264, Some(0)), 36);
+        assert_eq!(quality_to_crf(VideoCodec::H265, None), 30);
+        assert_eq!(quality_to_crf(VideoCodec::Av1, Some(100)), 30);
+        assert_eq!(quality_to_crf(VideoCodec::Vp9, Some(0)), 46);
+        assert_eq!(quality_to_crf(VideoCodec::Vp9, Some(101)), 35);
+    }
+
+    #[test]
+    fn ironclad_h264_encodes_slow_with_faststart() {
+        let args = build_ffmpeg_args(&request(json!({ "container": "mp4" })));
+
+        assert!(has_args(
+            &args,
+            &[
+                "-pix_fmt", "yuv420p", "-c:v", "libx264", "-b:v", "0", "-preset", "slow", "-qp",
+                "0", "-crf", "28"
+            ]
+        ));
+        assert_eq!(value_of(&args, "-movflags"), Some("+faststart"));
+        assert!(args.ends_with(&[String::from("out.mp4"), String::from("-y")]));
+    }
+
+    #[test]
+    fn thunderbolt_leaves_the_encoder_defaults() {
+        let args = build_ffmpeg_args(&request(json!({
+            "container": "mp4",
+            "preset": "thunderbolt",
+            "quality": 100
+        })));
+
+        assert!(has_args(&args, &["-c:v", "libx264", "-crf", "24"]));
+        assert!(!args.iter().any(|arg| arg == "-pix_fmt" || arg == "-preset"));
+        assert!(!args.iter().any(|arg| arg == "-movflags"));
+    }
+
+    #[test]
+    fn each_codec_gets_its_crf_and_speed_flags() {
+        let args = build_ffmpeg_args(&request(json!({
+            "container": "mkv",
+            "videoCodec": "h265",
+            "preset": "thunderbolt"
+        })));
+        assert!(has_args(
+            &args,
+            &[
+                "-c:v",
+                "libx265",
+                "-crf",
+                "30",
+                "-x265-params",
+                "log-level=error"
+            ]
+        ));
+
+        let args = build_ffmpeg_args(&request(json!({
+            "container": "webm",
+            "videoCodec": "vp9",
+            "preset": "thunderbolt"
+        })));
+        assert!(has_args(
+            &args,
+            &[
+                "-c:v",
+                "libvpx-vp9",
+                "-b:v",
+                "0",
+                "-deadline",
+                "good",
+                "-cpu-used",
+                "4",
+                "-row-mt",
+                "1",
+                "-crf",
+                "35"
+            ]
+        ));
+
+        let args = build_ffmpeg_args(&request(json!({
+            "container": "mkv",
+            "videoCodec": "av1"
+        })));
+        assert!(has_args(
+            &args,
+            &[
+                "-c:v",
+                "libsvtav1",
+                "-b:v",
+                "0",
+                "-preset",
+                "6",
+                "-crf",
+                "38"
+            ]
+        ));
+    }
+
+    #[test]
+    fn h265_in_mp4_is_tagged_hvc1() {
+        let args = build_ffmpeg_args(&request(json!({
+            "container": "mp4",
+            "videoCodec": "h265"
+        })));
+
+        assert_eq!(value_of(&args, "-tag:v"), Some("hvc1"));
+    }
+
+    #[test]
+    fn target_size_runs_two_passes_sharing_the_passlog() {
+        let plan = BitratePlan {
+            video_kbps: 900,
+            audio_kbps: Some(128),
+        };
+        let passes = build_target_size_args(
+            &request(json!({ "container": "mp4", "targetSizeMb": 10 })),
+            plan,
+            "/tmp/compresso-1",
+        );
+        assert_eq!(passes.len(), 2);
+
+        let first = &passes[0];
+        assert!(has_args(first, &["-b:v", "900k"]));
+        assert!(has_args(
+            first,
+            &["-pass", "1", "-passlogfile", "/tmp/compresso-1"]
+        ));
+        assert!(first.ends_with(&[
+            String::from("-an"),
+            String::from("-f"),
+            String::from("null"),
+            String::from(NULL_OUTPUT),
+            String::from("-y"),
+        ]));
+        assert!(!first.iter().any(|arg| arg == "out.mp4" || arg == "-crf"));
+
+        let second = &passes[1];
+        assert!(has_args(
+            second,
+            &["-pass", "2", "-passlogfile", "/tmp/compresso-1"]
+        ));
+        assert_eq!(value_of(second, "-b:a"), Some("128k"));
+        assert!(second.ends_with(&[String::from("out.mp4"), String::from("-y")]));
+    }
+
+    #[test]
+    fn x265_two_pass_keeps_its_own_stats_file() {
+        let plan = BitratePlan {
+            video_kbps: 900,
+            audio_kbps: None,
+        };
+        let passes = build_target_size_args(
+            &request(json!({ "container": "mkv", "videoCodec": "h265", "muteAudio": true })),
+            plan,
+            "/tmp/compresso-1",
+        );
+
+        assert_eq!(
+            value_of(&passes[0], "-x265-params"),
+            Some("log-level=error:pass=1:stats='/tmp/compresso-1-x265.log'")
+        );
+        assert_eq!(
+            value_of(&passes[1], "-x265-params"),
+            Some("log-level=error:pass=2:stats='/tmp/compresso-1-x265.log'")
+        );
+        assert!(!passes[1].iter().any(|arg| arg == "-pass"));
+        assert!(passes[1].iter().any(|arg| arg == "-an"));
+    }
+
+    #[test]
+    fn svt_av1_target_size_is_a_single_bitrate_pass() {
+        let plan = BitratePlan {
+            video_kbps: 900,
+            audio_kbps: Some(128),
+        };
+        let passes = build_target_size_args(
+            &request(json!({ "container": "mkv", "videoCodec": "av1" })),
+            plan,
+            "/tmp/compresso-1",
+        );
+
+        assert_eq!(passes.len(), 1);
+        assert!(has_args(&passes[0], &["-b:v", "900k"]));
+        assert!(!passes[0].iter().any(|arg| arg == "-pass"));
+    }
+
+    #[test]
+    fn multi_range_trim_concatenates_one_input_per_range() {
+        let args = build_ffmpeg_args(&request(json!({
+            "container": "mp4",
+            "transforms": two_ranges()
+        })));
+
+        assert!(has_args(
+            &args,
+            &["-ss", "0.000", "-t", "5.000", "-i", "in.mp4", "-ss", "10.000", "-i", "in.mp4"]
+        ));
+        assert_eq!(
+            value_of(&args, "-filter_complex"),
+            Some(
+                "[0:v:0][0:a:0][1:v:0][1:a:0]concat=n=2:v=1:a=1[joined][a0];\
+                 [joined]pad=ceil(iw/2)*2:ceil(ih/2)*2[v]"
+            )
+        );
+        assert!(has_args(&args, &["-map", "[v]", "-map", "[a0]"]));
+        assert!(!args.iter().any(|arg| arg == "-vf"));
+    }
+
+    #[test]
+    fn mixed_audio_streams_go_through_amix() {
+        let mut request = request(json!({
+            "container": "mp4",
+            "preset": "thunderbolt",
+            "streams": { "audio": { "type": "all" }, "mixAudio": true }
+        }));
+        request.streams = Some(SelectedStreams {
+            audio: vec![0, 1],
+            ..SelectedStreams::default()
+        });
+        let args = build_ffmpeg_args(&request);
+
+        assert_eq!(
+            value_of(&args, "-filter_complex"),
+            Some(
+                "[0:v:0]pad=ceil(iw/2)*2:ceil(ih/2)*2[v];\
+                 [0:a:0][0:a:1]amix=inputs=2:duration=longest[a]"
+            )
+        );
+        assert!(has_args(&args, &["-map", "[v]", "-map", "[a]"]));
+    }
+
+    #[test]
+    fn subtitle_files_are_mapped_after_the_video_inputs() {
+        let mut request = request(json!({
+            "container": "mkv",
+            "preset": "thunderbolt",
+            "transforms": [{
+                "type": "trim",
+                "value": [{ "startSeconds": 2, "endSeconds": 6 }]
+            }]
+        }));
+        request.streams = Some(SelectedStreams {
+            audio: vec![0],
+            subtitles: vec![1],
+            subtitle_files: vec![String::from("a.srt"), String::from("b.ass")],
+        });
+        let args = build_ffmpeg_args(&request);
+
+        assert!(has_args(
+            &args,
+            &[
+                "-ss", "2.000", "-t", "4.000", "-i", "in.mp4", "-ss", "2.000", "-t", "4.000", "-i",
+                "a.srt", "-ss", "2.000", "-t", "4.000", "-i", "b.ass"
+            ]
+        ));
+        assert!(has_args(
+            &args,
+            &[
+                "-map",
+                "0:v:0",
+                "-map",
+                "0:a:0",
+                "-vf",
+                "pad=ceil(iw/2)*2:ceil(ih/2)*2",
+                "-map",
+                "0:s:1",
+                "-map",
+                "1:s:0",
+                "-map",
+                "2:s:0"
+            ]
+        ));
+        assert_eq!(value_of(&args, "-c:s"), Some("copy"));
+    }
+
+    #[test]
+    fn default_metadata_adds_no_arguments() {
+        let request = request(json!({ "container": "mp4" }));
+
+        assert!(metadata_args(&request).is_empty());
+    }
+
+    #[test]
+    fn preserved_metadata_keeps_tags_and_chapters() {
+        let request = request(json!({
+            "container": "mp4",
+            "metadata": { "mode": "preserve" }
+        }));
+
+        assert_eq!(
+            metadata_args(&request),
+            ["-map_metadata", "0", "-map_chapters", "0"]
+        );
+        assert_eq!(
+            value_of(&build_ffmpeg_args(&request), "-movflags"),
+            Some("+faststart+use_metadata_tags")
+        );
+    }
+
+    #[test]
+    fn preserved_metadata_drops_chapters_of_joined_ranges() {
+        let request = request(json!({
+            "container": "mp4",
+            "metadata": { "mode": "preserve" },
+            "transforms": two_ranges()
+        }));
+
+        assert_eq!(
+            metadata_args(&request),
+            ["-map_metadata", "0", "-map_chapters", "-1"]
+        );
+    }
+
+    #[test]
+    fn stripped_metadata_drops_global_and_stream_tags() {
+        let request = request(json!({
+            "container": "mkv",
+            "metadata": { "mode": "strip" }
+        }));
+
+        assert_eq!(
+            metadata_args(&request),
+            [
+                "-map_metadata",
+                "-1",
+                "-map_metadata:s",
+                "-1",
+                "-map_chapters",
+                "-1"
+            ]
+        );
+    }
+
+    #[test]
+    fn sdr_sources_need_no_hdr_conversion() {
+        let options = request(json!({
+            "container": "mp4",
+            "videoCodec": "h264",
+            "hdr": "preserve"
+        }))
+        .options;
+
+        assert_eq!(hdr_conversion(&options, None, None), Ok(None));
+    }
+
+    #[test]
+    fn preserving_hdr_needs_a_10_bit_codec() {
+        let h264 = request(json!({
+            "container": "mp4",
+            "videoCodec": "h264",
+            "hdr": "preserve"
+        }))
+        .options;
+        let h265 = EncodeOptions {
+            video_codec: Some(VideoCodec::H265),
+            ..h264.clone()
+        };
+
+        assert_eq!(
+            hdr_conversion(&h264, Some(HdrFormat::Pq), None),
+            Err(String::from("h264 cannot keep HDR, use h265, av1 or vp9."))
+        );
+        assert_eq!(
+            hdr_conversion(&h265, Some(HdrFormat::Pq), None),
+            Ok(Some(HdrConversion::Preserve(HdrFormat::Pq)))
+        );
+    }
+
+    #[test]
+    fn preserving_hdr_in_avi_fails_with_its_default_codec() {
+        let options = request(json!({ "container": "avi", "hdr": "preserve" })).options;
+
+        assert_eq!(
+            hdr_conversion(&options, Some(HdrFormat::Hlg), None),
+            Err(String::from("h264 cannot keep HDR, use h265, av1 or vp9."))
+        );
+    }
+
+    #[test]
+    fn tone_mapping_without_the_zscale_filter_encodes_as_is() {
+        let options = request(json!({ "container": "mp4", "hdr": "tonemap" })).options;
+        let capabilities = FfmpegCapabilities {
+            filters: vec![String::from("tonemap")],
+            ..FfmpegCapabilities::default()
+        };
+
+        assert_eq!(
+            hdr_conversion(&options, Some(HdrFormat::Hlg), Some(&capabilities)),
+            Ok(None)
+        );
+        assert_eq!(
+            hdr_conversion(&options, Some(HdrFormat::Hlg), None),
+            Ok(Some(HdrConversion::Tonemap))
+        );
+    }
+}
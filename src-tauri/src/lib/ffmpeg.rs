@@ -1,57 +1,113 @@
-use crate::domain::{
-    CancelInProgressCompressionPayload, CompressionResult, CustomEvents, TauriEvents,
-    QualityPreviewResult, VideoCompressionProgress, VideoInfo, VideoThumbnail,
+use crate::{
+    capabilities,
+    diagnostics::{self, StderrTail},
+    domain::{
+        CancelInProgressCompressionPayload, CompressionResult, Container, CustomEvents,
+        EncodeOptions, FfmpegCapabilities, FfmpegError, FfmpegErrorKind, MediaInfo,
+        QualityPreviewResult, StreamInfo, StreamKind, VideoCompressionProgress, VideoInfo,
+        VideoThumbnail,
+    },
+    encoder::{self, ClipRange, EncodeRequest, HdrConversion, SelectedStreams},
+    fs,
+    loudness::{self, LoudnessMeasurement},
+    probe,
+    progress::{ProgressParser, ProgressSample, ProgressTracker},
 };
 use crossbeam_channel::{Receiver, Sender};
 use nanoid::nanoid;
-use once_cell::sync::Lazy;
-use regex::Regex;
-use serde_json::Value;
 use shared_child::SharedChild;
 use std::{
-    io::{BufRead, BufReader},
+    io::{self, BufReader},
     path::{Path, PathBuf},
-    process::{Command, Stdio},
+    process::{Command, ExitStatus, Output, Stdio},
     sync::{Arc, Mutex},
 };
-use strum::EnumProperty;
 use tauri::{AppHandle, Emitter, Listener, Manager};
 use tauri_plugin_shell::ShellExt;
 
-// Static regex patterns compiled once at first use
-static OUT_TIME_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"out_time=(?P<out_time>.*?)\n").expect("out_time regex pattern is invalid"));
-static DURATION_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"Duration: (?P<duration>.*?),").expect("duration regex pattern is invalid"));
-static DIMENSION_RE: Lazy<Regex> = Lazy::new(|| {
-    Regex::new(r"Video:.*?,.*? (?P<width>\d{2,5})x(?P<height>\d{2,5})")
-        .expect("dimension regex pattern is invalid")
-});
-static FPS_RE: Lazy<Regex> =
-    Lazy::new(|| Regex::new(r"(?P<fps>\d+(\.\d+)?) fps").expect("fps regex pattern is invalid"));
-
-fn get_out_time_re() -> &'static Regex {
-    &OUT_TIME_RE
+/// Formats seconds as `HH:MM:SS.ff..` with the given number of decimals.
+fn format_seconds_as_duration(seconds: f64, decimals: usize) -> String {
+    let scale = 10f64.powi(decimals as i32);
+    let seconds = (seconds.max(0.0) * scale).round() / scale;
+    let hours = (seconds / 3600.0).floor();
+    let minutes = ((seconds - hours * 3600.0) / 60.0).floor();
+    let rest = seconds - hours * 3600.0 - minutes * 60.0;
+    let width = if decimals > 0 { decimals + 3 } else { 2 };
+    format!(
+        "{:02}:{:02}:{:0width$.decimals$}",
+        hours as u64,
+        minutes as u64,
+        rest,
+        width = width,
+        decimals = decimals
+    )
 }
 
-fn get_duration_re() -> &'static Regex {
-    &DURATION_RE
+fn missing_input_error() -> FfmpegError {
+    FfmpegError::new(
+        FfmpegErrorKind::MissingInput,
+        "File does not exist in given path.",
+    )
 }
 
-fn get_dimension_re() -> &'static Regex {
-    &DIMENSION_RE
+/// Streams kept by the options' stream selection, which needs the probed
+/// streams unless ffmpeg picks them.
+fn select_streams(
+    options: &EncodeOptions,
+    media_info: Option<&MediaInfo>,
+) -> Result<Option<SelectedStreams>, FfmpegError> {
+    match media_info {
+        Some(media_info) => {
+            let streams = encoder::select_streams(
+                options,
+                &media_info.stream_languages(StreamKind::Audio),
+                &media_info.stream_languages(StreamKind::Subtitle),
+            )?;
+            let subtitle_codecs: Vec<Option<String>> = media_info
+                .streams_of(StreamKind::Subtitle)
+                .map(|stream| stream.codec_name.clone())
+                .collect();
+            encoder::check_subtitle_codecs(options, streams.as_ref(), &subtitle_codecs)?;
+            Ok(streams)
+        }
+        None if !encoder::picks_streams(options) => Ok(None),
+        None => Err(FfmpegError::from(String::from(
+            "Could not read the streams of the video to select them.",
+        ))),
+    }
 }
 
-fn get_fps_re() -> &'static Regex {
-    &FPS_RE
-}
+/// ffmpeg processes started by the app. They keep running when the window is
+/// closed and are killed when the app exits.
+#[derive(Default)]
+pub struct RunningProcesses(Mutex<Vec<Arc<SharedChild>>>);
+
+impl RunningProcesses {
+    fn add(app: &AppHandle, child: &Arc<SharedChild>) {
+        if let Some(processes) = app.try_state::<RunningProcesses>() {
+            if let Ok(mut processes) = processes.0.lock() {
+                processes.push(child.clone());
+            }
+        }
+    }
 
-fn parse_duration_to_seconds(duration: &str) -> Option<f64> {
-    let mut parts = duration.split(':');
-    let hours = parts.next()?.trim().parse::<f64>().ok()?;
-    let minutes = parts.next()?.trim().parse::<f64>().ok()?;
-    let seconds = parts.next()?.trim().parse::<f64>().ok()?;
-    Some((hours * 3600.0) + (minutes * 60.0) + seconds)
+    fn remove(app: &AppHandle, child: &Arc<SharedChild>) {
+        if let Some(processes) = app.try_state::<RunningProcesses>() {
+            if let Ok(mut processes) = processes.0.lock() {
+                processes.retain(|process| !Arc::ptr_eq(process, child));
+            }
+        }
+    }
+
+    pub fn kill_all(&self) {
+        if let Ok(processes) = self.0.lock() {
+            for process in processes.iter() {
+                if let Err(err) = process.kill() {
+                    log::error!("child process could not be killed {}", err);
+                }
+            }
+        }
+    }
 }
 
 pub struct FFMPEG {
@@ -60,20 +116,16 @@ pub struct FFMPEG {
     assets_dir: PathBuf,
 }
 
-const EXTENSIONS: [&str; 5] = ["mp4", "mov", "webm", "avi", "mkv"];
-
-type VideoInfoTaskResult = (u8, Option<String>, Option<(u32, u32)>, Option<f32>);
-
 impl FFMPEG {
-    pub fn new(app: &tauri::AppHandle) -> Result<Self, String> {
+    pub fn new(app: &tauri::AppHandle) -> Result<Self, FfmpegError> {
         match app.shell().sidecar("compresso_ffmpeg") {
             Ok(command) => {
                 let app_data_dir = match app.path().app_data_dir() {
                     Ok(path_buf) => path_buf,
                     Err(_) => {
-                        return Err(String::from(
+                        return Err(FfmpegError::from(String::from(
                             "Application app directory is not setup correctly.",
-                        ));
+                        )));
                     }
                 };
                 let assets_dir: PathBuf = [PathBuf::from(&app_data_dir), PathBuf::from("assets")]
@@ -86,274 +138,187 @@ impl FFMPEG {
                     assets_dir,
                 })
             }
-            Err(err) => Err(format!("[ffmpeg-sidecar]: {:?}", err.to_string())),
+            Err(err) => Err(FfmpegError::new(
+                FfmpegErrorKind::FfmpegNotFound,
+                &format!("[ffmpeg-sidecar]: {:?}", err.to_string()),
+            )),
         }
     }
 
     /// Compresses a video from a path
-    #[allow(clippy::too_many_arguments)]
     pub async fn compress_video(
         &mut self,
         video_path: &str,
-        convert_to_extension: &str,
-        preset_name: Option<&str>,
         video_id: Option<&str>,
-        should_mute_video: bool,
-        quality: u16,
-        dimensions: Option<(u32, u32)>,
-        fps: Option<&str>,
-        transforms_history: Option<&Vec<Value>>,
-    ) -> Result<CompressionResult, String> {
-        if !EXTENSIONS.contains(&convert_to_extension) {
-            return Err(String::from("Invalid convert to extension."));
+        options: &EncodeOptions,
+    ) -> Result<CompressionResult, FfmpegError> {
+        if !Path::exists(Path::new(video_path)) {
+            return Err(missing_input_error());
         }
 
+        options.validate()?;
+
         let id = match video_id {
             Some(id) => String::from(id),
             None => nanoid!(),
         };
-        let id_clone1 = id.clone();
-        let id_clone2 = id.clone();
 
-        let file_name = format!("{}.{}", id, convert_to_extension);
-        let file_name_clone = file_name.clone();
+        let file_name = format!("{}.{}", id, options.container.extension());
 
         let output_file: PathBuf = [self.assets_dir.clone(), PathBuf::from(&file_name)]
             .iter()
             .collect();
 
-        let output_path = &output_file.display().to_string();
-
-        let max_crf: u16 = 36;
-        let min_crf: u16 = 24; // Lower the CRF, higher the quality
-        let default_crf: u16 = 28;
-        let compression_quality = if (0..=100).contains(&quality) {
-            let diff = (max_crf - min_crf) - ((max_crf - min_crf) * quality) / 100;
-            format!("{}", min_crf + diff)
-        } else {
-            format!("{default_crf}")
+        let media_info = match self.probe_media(video_path).await {
+            Ok(media_info) => Some(media_info),
+            Err(err) if options.target_size_mb.is_some() || encoder::picks_streams(options) => {
+                return Err(err)
+            }
+            Err(err) => {
+                log::error!("[ffprobe] duration could not be read {}", err);
+                None
+            }
         };
-        let compression_quality_str = compression_quality.as_str();
-
-        let codec = "libx264";
-
-        let mut preset = match preset_name {
-            Some(preset) => match preset {
-                "thunderbolt" => {
-                    let args = vec![
-                        "-i",
-                        &video_path,
-                        "-hide_banner",
-                        "-progress",
-                        "-",
-                        "-nostats",
-                        "-loglevel",
-                        "error",
-                        "-c:v",
-                        codec,
-                        "-crf",
-                        compression_quality_str,
-                    ];
-                    args
-                }
-                _ => {
-                    let args = vec![
-                        "-i",
-                        &video_path,
-                        "-hide_banner",
-                        "-progress",
-                        "-",
-                        "-nostats",
-                        "-loglevel",
-                        "error",
-                        "-pix_fmt",
-                        "yuv420p",
-                        "-c:v",
-                        codec,
-                        "-b:v",
-                        "0",
-                        "-movflags",
-                        "+faststart",
-                        "-preset",
-                        "slow",
-                        "-qp",
-                        "0",
-                        "-crf",
-                        compression_quality_str,
-                    ];
-                    args
+        // Progress and target size bitrates follow the trimmed duration.
+        let probed_duration = encoder::output_duration(
+            &options.transforms,
+            media_info.as_ref().and_then(MediaInfo::duration_seconds),
+        )?;
+
+        let mut request = EncodeRequest {
+            input_path: String::from(video_path),
+            output_path: output_file.display().to_string(),
+            options: options.clone(),
+            video_encoder: self.pick_video_encoder(options)?,
+            report_progress: true,
+            clip: None,
+            has_audio: media_info.as_ref().is_none_or(MediaInfo::has_audio),
+            loudness: None,
+            streams: select_streams(options, media_info.as_ref())?,
+            hdr: self.hdr_conversion(options, media_info.as_ref())?,
+        };
+        request.loudness = self.measure_loudness(&request, &id).await?;
+
+        let result = match options.target_size_mb {
+            Some(target_size_mb) => {
+                let duration_seconds = probed_duration.ok_or_else(|| {
+                    FfmpegError::from(String::from(
+                        "Could not read video duration to compress to a target size.",
+                    ))
+                })?;
+                let plan = encoder::plan_target_size(
+                    target_size_mb,
+                    duration_seconds,
+                    encoder::target_size_audio_kbps(options),
+                    encoder::output_audio_streams(&request),
+                )?;
+                let passlog_prefix = self.assets_dir.join(format!("{id}-passlog"));
+                let passes = encoder::build_target_size_args(
+                    &request,
+                    plan,
+                    &passlog_prefix.display().to_string(),
+                );
+                let total_passes = passes.len() as u8;
+
+                let mut result = Ok(());
+                for (index, args) in passes.into_iter().enumerate() {
+                    let pass =
+                        ProgressTracker::pass(Some(duration_seconds), index as u8 + 1, total_passes);
+                    result = self.run_compression_pass(args, &id, &file_name, pass).await;
+                    if result.is_err() {
+                        break;
+                    }
                 }
-            },
+                self.delete_passlog_files(&id);
+                result
+            }
             None => {
-                let args = vec![
-                    "-i",
-                    &video_path,
-                    "-hide_banner",
-                    "-progress",
-                    "-",
-                    "-nostats",
-                    "-loglevel",
-                    "error",
-                    "-c:v",
-                    codec,
-                    "-crf",
-                    compression_quality_str,
-                ];
-                args
+                let args = encoder::build_ffmpeg_args(&request);
+                let pass = ProgressTracker::single(probed_duration);
+                self.run_compression_pass(args, &id, &file_name, pass)
+                    .await
             }
         };
-        // Transforms
-        let transform_filters = if let Some(transforms) = transforms_history {
-            self.build_ffmpeg_filters(transforms)
-        } else {
-            String::from("")
-        };
 
-        // Dimensions
-        let padding = "pad=ceil(iw/2)*2:ceil(ih/2)*2";
-        let pad_filter = if let Some((width, height)) = dimensions {
-            format!("scale={}:{},{}", width, height, padding)
-        } else {
-            padding.to_string()
+        let result = match result {
+            Ok(()) => self.verify_output(&output_file).await,
+            Err(err) => Err(err),
         };
-
-        let mut vf_filter = String::new();
-
-        if !transform_filters.is_empty() {
-            vf_filter.push_str(&transform_filters);
-            vf_filter.push(',')
-        }
-
-        vf_filter.push_str(&pad_filter);
-
-        println!(">>>>>Final vf filter {}", vf_filter);
-
-        preset.push("-vf");
-        preset.push(&vf_filter);
-
-        // FPS
-        if let Some(fps_val) = fps {
-            preset.push("-r");
-            preset.push(fps_val);
-        }
-
-        // Webm
-        if convert_to_extension == "webm" {
-            preset.push("-c:v");
-            preset.push("libvpx-vp9");
+        if result.is_err() {
+            let _ = std::fs::remove_file(&output_file);
         }
+        result?;
 
-        // Mute Audio
-        if should_mute_video {
-            preset.push("-an")
+        if options.metadata.copy_file_times {
+            fs::copy_file_times(Path::new(video_path), &output_file).map_err(|err| {
+                FfmpegError::from(format!("Could not copy the file times of the video: {err}"))
+            })?;
         }
 
-        preset.push(output_path);
-        preset.push("-y");
+        Ok(CompressionResult {
+            file_name,
+            file_path: output_file.display().to_string(),
+        })
+    }
 
-        let command = self
-            .ffmpeg
-            .args(preset)
+    /// Runs one ffmpeg encode, forwarding progress to the frontend and
+    /// honoring cancel events. It does not depend on any window being open.
+    async fn run_compression_pass(
+        &self,
+        args: Vec<String>,
+        id: &str,
+        file_name: &str,
+        pass: ProgressTracker,
+    ) -> Result<(), FfmpegError> {
+        log::debug!("[ffmpeg] compress args: {:?}", args);
+        let id_clone1 = String::from(id);
+        let file_name_clone = String::from(file_name);
+
+        let mut command = self.sidecar_command()?;
+        command
+            .args(args)
             .stdout(Stdio::piped())
             .stderr(Stdio::piped());
 
-        match SharedChild::spawn(command) {
+        match SharedChild::spawn(&mut command) {
             Ok(child) => {
                 let cp = Arc::new(child);
-                #[cfg(debug_assertions)]
                 let cp_clone1 = cp.clone();
                 let cp_clone2 = cp.clone();
                 let cp_clone3 = cp.clone();
                 let cp_clone4 = cp.clone();
-
-                let window = match self.app.get_webview_window("main") {
-                    Some(window) => window,
-                    None => return Err(String::from("Could not attach to main window")),
-                };
-                let destroy_event_id = if let Some(event_key) = TauriEvents::Destroyed.get_str("key") {
-                    Some(window.listen(event_key, move |_| {
-                        log::info!("[tauri] window destroyed");
-                        match cp.kill() {
-                            Ok(_) => {
-                                log::info!("child process killed.");
-                            }
-                            Err(err) => {
-                                log::error!(
-                                    "child process could not be killed {}",
-                                    err.to_string()
-                                );
-                            }
-                        }
-                    }))
-                } else {
-                    log::error!("TauriEvents::Destroyed missing 'key' property");
-                    None
-                };
+                RunningProcesses::add(&self.app, &cp);
 
                 let should_cancel = Arc::new(Mutex::new(false));
                 let should_cancel_clone = Arc::clone(&should_cancel);
 
-                let cancel_event_id = window.listen(
-                    CustomEvents::CancelInProgressCompression.as_ref(),
-                    move |evt| {
-                        let payload_str = evt.payload();
-                        let payload_opt: Option<CancelInProgressCompressionPayload> =
-                            serde_json::from_str(payload_str).ok();
-                        if let Some(payload) = payload_opt {
-                            let video_id = id_clone2.as_str();
-                            if payload.video_id == video_id {
-                                log::info!("compression requested to cancel.");
-                                match cp_clone4.kill() {
-                                    Ok(_) => {
-                                        log::info!("child process killed.");
-                                    }
-                                    Err(err) => {
-                                        log::error!(
-                                            "child process could not be killed {}",
-                                            err.to_string()
-                                        );
-                                    }
-                                };
-                                if let Ok(mut guard) = should_cancel_clone.lock() {
-                                    *guard = true;
-                                } else {
-                                    log::error!("Failed to acquire should_cancel mutex lock");
-                                }
-                            }
+                let cancel_event_id = self.listen_for_cancel(id, move || {
+                    log::info!("compression requested to cancel.");
+                    match cp_clone4.kill() {
+                        Ok(_) => {
+                            log::info!("child process killed.");
                         }
-                    },
-                );
-
-                #[cfg(debug_assertions)]
-                tokio::spawn(async move {
-                    if let Some(stderr) = cp_clone1.take_stderr() {
-                        let mut reader = BufReader::new(stderr);
-
-                        loop {
-                            let mut buf: Vec<u8> = Vec::new();
-                            match tauri::utils::io::read_line(&mut reader, &mut buf) {
-                                Ok(n) => {
-                                    if n == 0 {
-                                        break;
-                                    }
-                                    if let Ok(val) = std::str::from_utf8(&buf) {
-                                        log::debug!("stderr: {:?}", val);
-                                    }
-                                }
-                                Err(_) => {
-                                    break;
-                                }
-                            }
+                        Err(err) => {
+                            log::error!("child process could not be killed {}", err.to_string());
                         }
+                    };
+                    if let Ok(mut guard) = should_cancel_clone.lock() {
+                        *guard = true;
+                    } else {
+                        log::error!("Failed to acquire should_cancel mutex lock");
                     }
                 });
 
-                let (tx, rx): (Sender<String>, Receiver<String>) = crossbeam_channel::unbounded();
+                let stderr_thread: tokio::task::JoinHandle<StderrTail> =
+                    tokio::spawn(async move { diagnostics::collect_stderr(&cp_clone1) });
+
+                let (tx, rx): (Sender<ProgressSample>, Receiver<ProgressSample>) =
+                    crossbeam_channel::unbounded();
 
-                let thread: tokio::task::JoinHandle<u8> = tokio::spawn(async move {
+                let thread: tokio::task::JoinHandle<io::Result<ExitStatus>> = tokio::spawn(async move {
                     if let Some(stdout) = cp_clone2.take_stdout() {
                         let mut reader = BufReader::new(stdout);
-                        let out_time_re = get_out_time_re();
+                        let mut parser = ProgressParser::default();
                         loop {
                             let mut buf: Vec<u8> = Vec::new();
                             match tauri::utils::io::read_line(&mut reader, &mut buf) {
@@ -363,11 +328,8 @@ impl FFMPEG {
                                     }
                                     if let Ok(output) = std::str::from_utf8(&buf) {
                                         log::debug!("stdout: {:?}", output);
-                                        if let Some(cap) = out_time_re.captures(output) {
-                                            let out_time = &cap["out_time"];
-                                            if !out_time.is_empty() {
-                                                tx.try_send(String::from(out_time)).ok();
-                                            }
+                                        if let Some(sample) = parser.push_line(output) {
+                                            tx.try_send(sample).ok();
                                         }
                                     }
                                 }
@@ -378,10 +340,7 @@ impl FFMPEG {
                         }
                     }
 
-                    if cp_clone2.wait().is_ok() {
-                        return 0;
-                    }
-                    1
+                    cp_clone2.wait()
                 });
 
                 let app_clone = self.app.clone();
@@ -389,39 +348,37 @@ impl FFMPEG {
                     let file_name_clone_str = file_name_clone.as_str();
                     let id_clone_str = id_clone1.as_str();
 
-                    while let Ok(current_duration) = rx.recv() {
+                    while let Ok(sample) = rx.recv() {
+                        let Some(current_seconds) = pass.overall_seconds(&sample) else {
+                            continue;
+                        };
                         let video_progress = VideoCompressionProgress {
                             video_id: String::from(id_clone_str),
                             file_name: String::from(file_name_clone_str),
-                            current_duration,
+                            current_duration: format_seconds_as_duration(current_seconds, 6),
+                            pass: pass.pass,
+                            total_passes: pass.total_passes,
+                            progress: pass.report(&sample),
                         };
-                        if let Some(window) = app_clone.get_webview_window("main") {
-                            window
-                                .emit(
-                                    CustomEvents::VideoCompressionProgress.as_ref(),
-                                    video_progress,
-                                )
-                                .ok();
-                        }
+                        app_clone
+                            .emit(
+                                CustomEvents::VideoCompressionProgress.as_ref(),
+                                video_progress,
+                            )
+                            .ok();
                     }
                 });
 
-                let message: String = match thread.await {
-                    Ok(exit_status) => {
-                        if exit_status == 1 {
-                            String::from("Video is corrupted.")
-                        } else {
-                            String::from("")
-                        }
-                    }
-                    Err(err) => err.to_string(),
+                let exit_status = thread.await;
+                let stderr = stderr_thread.await.unwrap_or_default();
+                let result = match exit_status {
+                    Ok(status) => diagnostics::check_exit(status, &stderr),
+                    Err(err) => Err(FfmpegError::from(err.to_string())),
                 };
 
                 // Cleanup
-                if let Some(event_id) = destroy_event_id {
-                    window.unlisten(event_id);
-                }
-                window.unlisten(cancel_event_id);
+                self.app.unlisten(cancel_event_id);
+                RunningProcesses::remove(&self.app, &cp);
                 match cp_clone3.kill() {
                     Ok(_) => {
                         log::info!("child process killed.");
@@ -438,31 +395,165 @@ impl FFMPEG {
                     false
                 };
                 if is_cancelled {
-                    return Err(String::from("CANCELLED"));
+                    return Err(FfmpegError::cancelled());
                 }
 
-                if !message.is_empty() {
-                    return Err(message);
-                }
+                result?;
             }
             Err(err) => {
-                return Err(err.to_string());
+                return Err(diagnostics::spawn_error("ffmpeg", &err));
             }
         };
 
-        Ok(CompressionResult {
-            file_name,
-            file_path: output_file.display().to_string(),
-        })
+        Ok(())
+    }
+
+    /// Calls `on_cancel` when a cancel of the video `id` is requested. Cancels
+    /// are emitted by the frontend and the queue alike.
+    fn listen_for_cancel<F>(&self, id: &str, on_cancel: F) -> tauri::EventId
+    where
+        F: Fn() + Send + 'static,
+    {
+        let id = String::from(id);
+        self.app.listen_any(
+            CustomEvents::CancelInProgressCompression.as_ref(),
+            move |evt| {
+                let payload_opt: Option<CancelInProgressCompressionPayload> =
+                    serde_json::from_str(evt.payload()).ok();
+                if let Some(payload) = payload_opt {
+                    if payload.video_id == id {
+                        on_cancel();
+                    }
+                }
+            },
+        )
+    }
+
+    /// Runs a command to completion off the async runtime.
+    async fn run_blocking(command: Command, program: &'static str) -> Result<Output, FfmpegError> {
+        tokio::task::spawn_blocking(move || diagnostics::run_to_completion(command, program))
+            .await
+            .map_err(|err| FfmpegError::from(err.to_string()))?
+    }
+
+    /// Removes the stats files written by two-pass encodes (`<id>-passlog-0.log`,
+    /// `.mbtree` and their temp variants).
+    fn delete_passlog_files(&self, id: &str) {
+        let prefix = format!("{id}-passlog");
+        if let Ok(entries) = std::fs::read_dir(&self.assets_dir) {
+            for entry in entries.flatten() {
+                if entry.file_name().to_string_lossy().starts_with(&prefix) {
+                    if let Err(err) = std::fs::remove_file(entry.path()) {
+                        log::error!("passlog file could not be deleted {}", err);
+                    }
+                }
+            }
+        }
+    }
+
+    /// Encoders, muxers and filters of the bundled ffmpeg, cached per binary.
+    pub fn capabilities(&self) -> Result<Arc<FfmpegCapabilities>, String> {
+        let command = self.sidecar_command().map_err(|err| err.to_string())?;
+        capabilities::detect(Path::new(command.get_program()))
+    }
+
+    /// Encoder for the options' codec, after checking the audio codec too.
+    /// Falls back to the codec's preferred encoder when capabilities cannot
+    /// be read.
+    fn pick_video_encoder(
+        &self,
+        options: &EncodeOptions,
+    ) -> Result<Option<&'static str>, FfmpegError> {
+        match self.capabilities() {
+            Ok(capabilities) => encoder::check_audio_encoder(options, &capabilities)
+                .and_then(|_| encoder::check_subtitle_filter(options, &capabilities))
+                .and_then(|_| encoder::select_video_encoder(options, &capabilities))
+                .map(Some)
+                .map_err(|err| FfmpegError::new(FfmpegErrorKind::UnsupportedCodec, &err)),
+            Err(err) => {
+                log::error!("[ffmpeg] capabilities could not be detected {}", err);
+                Ok(None)
+            }
+        }
+    }
+
+    /// Conversion of the input's HDR video, see `encoder::hdr_conversion`. It
+    /// is encoded as is when the build cannot tone map it.
+    fn hdr_conversion(
+        &self,
+        options: &EncodeOptions,
+        media_info: Option<&MediaInfo>,
+    ) -> Result<Option<HdrConversion>, FfmpegError> {
+        let source = media_info
+            .and_then(MediaInfo::primary_video_stream)
+            .and_then(StreamInfo::hdr_format);
+        let capabilities = self.capabilities().ok();
+        let conversion = encoder::hdr_conversion(options, source, capabilities.as_deref())
+            .map_err(|err| FfmpegError::new(FfmpegErrorKind::UnsupportedCodec, &err))?;
+        if source.is_some() && conversion.is_none() {
+            log::warn!("[ffmpeg] tone mapping needs the zscale and tonemap filters, encoding the HDR video as is");
+        }
+        Ok(conversion)
+    }
+
+    /// Runs the `loudnorm` measurement pass when the request normalizes loudness.
+    /// The pass cannot be stopped midway, a cancel of the video `id` requested
+    /// meanwhile fails it once it is done.
+    async fn measure_loudness(
+        &self,
+        request: &EncodeRequest,
+        id: &str,
+    ) -> Result<Option<LoudnessMeasurement>, FfmpegError> {
+        if !encoder::needs_loudness_measurement(request) {
+            return Ok(None);
+        }
+        let args = encoder::build_loudness_args(request);
+        log::debug!("[ffmpeg] loudness args: {:?}", args);
+        let mut command = self.sidecar_command()?;
+        command.args(args);
+
+        let should_cancel = Arc::new(Mutex::new(false));
+        let should_cancel_clone = Arc::clone(&should_cancel);
+        let cancel_event_id = self.listen_for_cancel(id, move || {
+            log::info!("loudness measurement requested to cancel.");
+            if let Ok(mut guard) = should_cancel_clone.lock() {
+                *guard = true;
+            }
+        });
+        let output = Self::run_blocking(command, "ffmpeg").await;
+        self.app.unlisten(cancel_event_id);
+        if should_cancel.lock().is_ok_and(|guard| *guard) {
+            return Err(FfmpegError::cancelled());
+        }
+
+        let output = output?;
+        let measurement = loudness::parse_measurement(&String::from_utf8_lossy(&output.stderr));
+        if measurement.is_none() {
+            log::error!("[ffmpeg] loudness could not be measured, keeping it as is");
+        }
+        Ok(measurement)
+    }
+
+    fn sidecar_command(&self) -> Result<Command, FfmpegError> {
+        self.app
+            .shell()
+            .sidecar("compresso_ffmpeg")
+            .map(Command::from)
+            .map_err(|err| {
+                FfmpegError::new(
+                    FfmpegErrorKind::FfmpegNotFound,
+                    &format!("[ffmpeg-sidecar]: {:?}", err),
+                )
+            })
     }
 
     /// Generates a .jpeg thumbnail image from a video path
     pub async fn generate_video_thumbnail(
         &mut self,
         video_path: &str,
-    ) -> Result<VideoThumbnail, String> {
+    ) -> Result<VideoThumbnail, FfmpegError> {
         if !Path::exists(Path::new(video_path)) {
-            return Err(String::from("File does not exist in given path."));
+            return Err(missing_input_error());
         }
         let id = nanoid!();
         let file_name = format!("{}.jpg", id);
@@ -470,63 +561,40 @@ impl FFMPEG {
             .iter()
             .collect();
 
-        let command = self.ffmpeg.args([
-            "-i",
-            video_path,
-            "-ss",
-            "00:00:01.00",
-            "-vframes",
-            "1",
-            &output_path.display().to_string(),
-            "-y",
-        ]);
+        let command = self
+            .ffmpeg
+            .args([
+                "-i",
+                video_path,
+                "-ss",
+                "00:00:01.00",
+                "-vframes",
+                "1",
+                &output_path.display().to_string(),
+                "-y",
+            ])
+            .stderr(Stdio::piped());
 
         match SharedChild::spawn(command) {
             Ok(child) => {
                 let cp = Arc::new(child);
                 let cp_clone1 = cp.clone();
                 let cp_clone2 = cp.clone();
+                RunningProcesses::add(&self.app, &cp);
 
-                let window = match self.app.get_webview_window("main") {
-                    Some(window) => window,
-                    None => return Err(String::from("Could not attach to main window")),
-                };
-                let destroy_event_id = if let Some(event_key) = TauriEvents::Destroyed.get_str("key") {
-                    Some(window.listen(event_key, move |_| match cp.kill() {
-                        Ok(_) => {
-                            log::info!("child process killed.");
-                        }
-                        Err(err) => {
-                            log::error!("child process could not be killed {}", err.to_string());
-                        }
-                    }))
-                } else {
-                    log::error!("TauriEvents::Destroyed missing 'key' property");
-                    None
-                };
+                let thread: tokio::task::JoinHandle<Result<(), FfmpegError>> =
+                    tokio::spawn(async move {
+                        let stderr = diagnostics::collect_stderr(&cp_clone1);
+                        diagnostics::check_exit(cp_clone1.wait(), &stderr)
+                    });
 
-                let thread: tokio::task::JoinHandle<u8> = tokio::spawn(async move {
-                    if cp_clone1.wait().is_ok() {
-                        return 0;
-                    }
-                    1
-                });
-
-                let message: String = match thread.await {
-                    Ok(exit_status) => {
-                        if exit_status == 1 {
-                            String::from("Video is corrupted.")
-                        } else {
-                            String::from("")
-                        }
-                    }
-                    Err(err) => err.to_string(),
+                let result = match thread.await {
+                    Ok(result) => result,
+                    Err(err) => Err(FfmpegError::from(err.to_string())),
                 };
 
                 // Cleanup
-                if let Some(event_id) = destroy_event_id {
-                    window.unlisten(event_id);
-                }
+                RunningProcesses::remove(&self.app, &cp);
                 match cp_clone2.kill() {
                     Ok(_) => {
                         log::info!("child process killed.");
@@ -535,11 +603,9 @@ impl FFMPEG {
                         log::error!("child process could not be killed {}", err.to_string());
                     }
                 }
-                if !message.is_empty() {
-                    return Err(message);
-                }
+                result?;
             }
-            Err(err) => return Err(err.to_string()),
+            Err(err) => return Err(diagnostics::spawn_error("ffmpeg", &err)),
         };
         Ok(VideoThumbnail {
             id,
@@ -548,37 +614,28 @@ impl FFMPEG {
         })
     }
 
-    #[allow(clippy::too_many_arguments)]
     pub async fn generate_quality_preview(
         &mut self,
         video_path: &str,
-        convert_to_extension: &str,
-        preset_name: Option<&str>,
-        should_mute_video: bool,
-        quality: u16,
-        dimensions: Option<(u32, u32)>,
-        fps: Option<&str>,
-        transforms_history: Option<&Vec<Value>>,
+        options: &EncodeOptions,
         preview_seconds: Option<u16>,
-    ) -> Result<QualityPreviewResult, String> {
+    ) -> Result<QualityPreviewResult, FfmpegError> {
         if !Path::exists(Path::new(video_path)) {
-            return Err(String::from("File does not exist in given path."));
+            return Err(missing_input_error());
         }
 
-        if !EXTENSIONS.contains(&convert_to_extension) {
-            return Err(String::from("Invalid convert to extension."));
-        }
+        options.validate()?;
 
         let preview_seconds_value = preview_seconds.unwrap_or(20).clamp(1, 120);
-        let preview_duration_arg = preview_seconds_value.to_string();
         let preview_duration_f64 = f64::from(preview_seconds_value);
 
-        let middle_seek_seconds = self
-            .get_video_info(video_path)
-            .await
-            .ok()
-            .and_then(|info| info.duration)
-            .and_then(|duration| parse_duration_to_seconds(duration.as_str()))
+        let media_info = self.probe_media(video_path).await.ok();
+        // The clip is taken from the middle of the trimmed video.
+        let total_seconds = encoder::output_duration(
+            &options.transforms,
+            media_info.as_ref().and_then(MediaInfo::duration_seconds),
+        )?;
+        let middle_seek_seconds = total_seconds
             .map(|total_seconds| {
                 if total_seconds > preview_duration_f64 {
                     ((total_seconds / 2.0) - (preview_duration_f64 / 2.0)).max(0.0)
@@ -587,19 +644,19 @@ impl FFMPEG {
                 }
             })
             .unwrap_or(0.0);
-        let seek_arg = format!("{middle_seek_seconds:.3}");
 
         let id = nanoid!();
 
         let source_file_name = format!("{}-preview-source.mp4", id);
-        let compressed_preview_extension = if convert_to_extension == "webm" {
-            "webm"
+        let compressed_preview_container = if options.container == Container::Webm {
+            Container::Webm
         } else {
-            "mp4"
+            Container::Mp4
         };
         let compressed_file_name = format!(
             "{}-preview-compressed.{}",
-            id, compressed_preview_extension
+            id,
+            compressed_preview_container.extension()
         );
 
         let source_output: PathBuf = [self.assets_dir.clone(), PathBuf::from(&source_file_name)]
@@ -610,176 +667,86 @@ impl FFMPEG {
                 .iter()
                 .collect();
 
-        let max_crf: u16 = 36;
-        let min_crf: u16 = 24;
-        let default_crf: u16 = 28;
-        let compression_quality = if (0..=100).contains(&quality) {
-            let diff = (max_crf - min_crf) - ((max_crf - min_crf) * quality) / 100;
-            format!("{}", min_crf + diff)
-        } else {
-            format!("{default_crf}")
+        let clip = ClipRange {
+            start_seconds: middle_seek_seconds,
+            duration_seconds: preview_duration_f64,
         };
-
-        let transform_filters = if let Some(transforms) = transforms_history {
-            self.build_ffmpeg_filters(transforms)
-        } else {
-            String::from("")
+        // Previews play the picture and sound only.
+        let preview_streams =
+            select_streams(options, media_info.as_ref())?.map(|streams| SelectedStreams {
+                subtitles: Vec::new(),
+                subtitle_files: Vec::new(),
+                ..streams
+            });
+        let source_request = EncodeRequest {
+            input_path: String::from(video_path),
+            output_path: source_output.display().to_string(),
+            options: EncodeOptions {
+                container: Container::Mp4,
+                mute_audio: true,
+                ..options.clone()
+            },
+            video_encoder: self.pick_video_encoder(options)?,
+            report_progress: false,
+            clip: Some(clip),
+            has_audio: media_info.as_ref().is_none_or(MediaInfo::has_audio),
+            loudness: None,
+            streams: preview_streams,
+            hdr: self.hdr_conversion(options, media_info.as_ref())?,
         };
-
-        let padding = "pad=ceil(iw/2)*2:ceil(ih/2)*2";
-        let pad_filter = if let Some((width, height)) = dimensions {
-            format!("scale={}:{},{}", width, height, padding)
-        } else {
-            padding.to_string()
+        let source_args = encoder::build_preview_source_args(&source_request);
+        let mut compressed_request = EncodeRequest {
+            output_path: compressed_output.display().to_string(),
+            options: EncodeOptions {
+                container: compressed_preview_container,
+                video_codec: Some(options.resolved_video_codec()),
+                ..options.clone()
+            },
+            ..source_request
+        };
+        compressed_request.loudness = self.measure_loudness(&compressed_request, &id).await?;
+        // Target size previews use the bitrate planned for the whole video.
+        let compressed_passes = match options.target_size_mb {
+            Some(target_size_mb) => {
+                let plan = encoder::plan_target_size(
+                    target_size_mb,
+                    total_seconds.unwrap_or(0.0),
+                    encoder::target_size_audio_kbps(options),
+                    encoder::output_audio_streams(&compressed_request),
+                )?;
+                let passlog_prefix = self.assets_dir.join(format!("{id}-passlog"));
+                encoder::build_target_size_args(
+                    &compressed_request,
+                    plan,
+                    &passlog_prefix.display().to_string(),
+                )
+            }
+            None => vec![encoder::build_ffmpeg_args(&compressed_request)],
         };
 
-        let mut vf_filter = String::new();
-        if !transform_filters.is_empty() {
-            vf_filter.push_str(&transform_filters);
-            vf_filter.push(',');
-        }
-        vf_filter.push_str(&pad_filter);
-
-        let mut source_args: Vec<String> = vec![
-            String::from("-i"),
-            String::from(video_path),
-            String::from("-hide_banner"),
-            String::from("-nostats"),
-            String::from("-loglevel"),
-            String::from("error"),
-            String::from("-ss"),
-            seek_arg.clone(),
-            String::from("-t"),
-            preview_duration_arg.clone(),
-            String::from("-pix_fmt"),
-            String::from("yuv420p"),
-            String::from("-c:v"),
-            String::from("libx264"),
-            String::from("-preset"),
-            String::from("veryfast"),
-            String::from("-crf"),
-            String::from("18"),
-            String::from("-movflags"),
-            String::from("+faststart"),
-            String::from("-vf"),
-            vf_filter.clone(),
-        ];
-        if let Some(fps_val) = fps {
-            source_args.push(String::from("-r"));
-            source_args.push(String::from(fps_val));
-        }
-        source_args.push(String::from("-an"));
-        source_args.push(source_output.display().to_string());
-        source_args.push(String::from("-y"));
-
-        let mut source_command = Command::from(
-            self.app
-                .shell()
-                .sidecar("compresso_ffmpeg")
-                .map_err(|err| format!("[ffmpeg-sidecar]: {:?}", err))?,
-        );
+        let mut source_command = self.sidecar_command()?;
         source_command.args(source_args);
-        let source_status = source_command.status().map_err(|err| err.to_string())?;
-        if !source_status.success() {
+        if let Err(err) = Self::run_blocking(source_command, "ffmpeg").await {
             let _ = std::fs::remove_file(&source_output);
-            return Err(String::from("Could not generate source preview."));
-        }
-
-        let mut compressed_args: Vec<String> = match preset_name {
-            Some("thunderbolt") => vec![
-                String::from("-i"),
-                String::from(video_path),
-                String::from("-hide_banner"),
-                String::from("-nostats"),
-                String::from("-loglevel"),
-                String::from("error"),
-                String::from("-ss"),
-                seek_arg.clone(),
-                String::from("-t"),
-                preview_duration_arg.clone(),
-                String::from("-c:v"),
-                String::from("libx264"),
-                String::from("-crf"),
-                compression_quality.clone(),
-            ],
-            Some(_) => vec![
-                String::from("-i"),
-                String::from(video_path),
-                String::from("-hide_banner"),
-                String::from("-nostats"),
-                String::from("-loglevel"),
-                String::from("error"),
-                String::from("-ss"),
-                seek_arg.clone(),
-                String::from("-t"),
-                preview_duration_arg.clone(),
-                String::from("-pix_fmt"),
-                String::from("yuv420p"),
-                String::from("-c:v"),
-                String::from("libx264"),
-                String::from("-b:v"),
-                String::from("0"),
-                String::from("-movflags"),
-                String::from("+faststart"),
-                String::from("-preset"),
-                String::from("slow"),
-                String::from("-qp"),
-                String::from("0"),
-                String::from("-crf"),
-                compression_quality.clone(),
-            ],
-            None => vec![
-                String::from("-i"),
-                String::from(video_path),
-                String::from("-hide_banner"),
-                String::from("-nostats"),
-                String::from("-loglevel"),
-                String::from("error"),
-                String::from("-ss"),
-                seek_arg,
-                String::from("-t"),
-                preview_duration_arg,
-                String::from("-c:v"),
-                String::from("libx264"),
-                String::from("-crf"),
-                compression_quality.clone(),
-            ],
-        };
-
-        compressed_args.push(String::from("-vf"));
-        compressed_args.push(vf_filter);
-
-        if let Some(fps_val) = fps {
-            compressed_args.push(String::from("-r"));
-            compressed_args.push(String::from(fps_val));
-        }
-
-        if compressed_preview_extension == "webm" {
-            compressed_args.push(String::from("-c:v"));
-            compressed_args.push(String::from("libvpx-vp9"));
+            return Err(err);
         }
 
-        if should_mute_video {
-            compressed_args.push(String::from("-an"));
+        let mut compressed_result = Ok(());
+        for compressed_args in compressed_passes {
+            let mut compressed_command = self.sidecar_command()?;
+            compressed_command.args(compressed_args);
+            compressed_result = Self::run_blocking(compressed_command, "ffmpeg")
+                .await
+                .map(|_| ());
+            if compressed_result.is_err() {
+                break;
+            }
         }
-
-        compressed_args.push(compressed_output.display().to_string());
-        compressed_args.push(String::from("-y"));
-
-        let mut compressed_command = Command::from(
-            self.app
-                .shell()
-                .sidecar("compresso_ffmpeg")
-                .map_err(|err| format!("[ffmpeg-sidecar]: {:?}", err))?,
-        );
-        compressed_command.args(compressed_args);
-        let compressed_status = compressed_command
-            .status()
-            .map_err(|err| err.to_string())?;
-        if !compressed_status.success() {
+        self.delete_passlog_files(&id);
+        if let Err(err) = compressed_result {
             let _ = std::fs::remove_file(&source_output);
             let _ = std::fs::remove_file(&compressed_output);
-            return Err(String::from("Could not generate compressed preview."));
+            return Err(err);
         }
 
         Ok(QualityPreviewResult {
@@ -794,162 +761,57 @@ impl FFMPEG {
         self.assets_dir.display().to_string()
     }
 
-    pub async fn get_video_info(&mut self, video_path: &str) -> Result<VideoInfo, String> {
+    /// Reads container, stream and chapter details with the ffprobe sidecar.
+    pub async fn probe_media(&self, video_path: &str) -> Result<MediaInfo, FfmpegError> {
         if !Path::exists(Path::new(video_path)) {
-            return Err(String::from("File does not exist in given path."));
+            return Err(missing_input_error());
         }
 
-        let command = self
-            .ffmpeg
-            .args(["-i", video_path, "-hide_banner"])
-            .stderr(Stdio::piped()); // Capture stderr for metadata parsing
-
-        match SharedChild::spawn(command) {
-            Ok(child) => {
-                let cp = Arc::new(child);
-                let cp_clone1 = cp.clone();
-                let cp_clone2 = cp.clone();
-
-                let window = match self.app.get_webview_window("main") {
-                    Some(window) => window,
-                    None => return Err(String::from("Could not attach to main window")),
-                };
-
-                let destroy_event_id = if let Some(event_key) = TauriEvents::Destroyed.get_str("key") {
-                    Some(window.listen(event_key, move |_| match cp.kill() {
-                        Ok(_) => log::info!("child process killed."),
-                        Err(err) => log::error!("child process could not be killed {}", err),
-                    }))
-                } else {
-                    log::error!("TauriEvents::Destroyed missing 'key' property");
-                    None
-                };
-
-                let thread: tokio::task::JoinHandle<VideoInfoTaskResult> =
-                    tokio::task::spawn(async move {
-                    let mut duration: Option<String> = None;
-                    let mut dimensions: Option<(u32, u32)> = None;
-                    let mut fps: Option<f32> = None;
-
-                    if let Some(stderr) = cp_clone1.take_stderr() {
-                        let reader = BufReader::new(stderr);
-                        let duration_re = get_duration_re();
-                        let dimension_re = get_dimension_re();
-                        let fps_re = get_fps_re();
-
-                        for line_res in reader.lines() {
-                            if let Ok(line) = line_res {
-                                if duration.is_none() {
-                                    if let Some(cap) = duration_re.captures(&line) {
-                                        duration = Some(cap["duration"].to_string());
-                                    }
-                                }
-                                if dimensions.is_none() {
-                                    if let Some(cap) = dimension_re.captures(&line) {
-                                        if let (Ok(w), Ok(h)) = (
-                                            cap["width"].parse::<u32>(),
-                                            cap["height"].parse::<u32>(),
-                                        ) {
-                                            dimensions = Some((w, h));
-                                        }
-                                    }
-                                }
-                                if fps.is_none() {
-                                    if let Some(cap) = fps_re.captures(&line) {
-                                        if let Ok(parsed_fps) = cap["fps"].parse::<f32>() {
-                                            fps = Some(parsed_fps);
-                                        }
-                                    }
-                                }
-                                if duration.is_some() && dimensions.is_some() && fps.is_some() {
-                                    break;
-                                }
-                            } else {
-                                break;
-                            }
-                        }
-                    }
+        let mut command = self.ffprobe_command()?;
+        command.args(probe::ffprobe_args(video_path));
 
-                    if cp_clone1.wait().is_ok() {
-                        (0, duration, dimensions, fps)
-                    } else {
-                        (1, duration, dimensions, fps)
-                    }
-                });
+        let output = Self::run_blocking(command, "ffprobe").await?;
 
-                let result = match thread.await {
-                    Ok((exit_status, duration, dimensions, fps)) => {
-                        if exit_status == 1 {
-                            Err("Video file is corrupted".to_string())
-                        } else {
-                            Ok(VideoInfo {
-                                duration,
-                                dimensions,
-                                fps,
-                            })
-                        }
-                    }
-                    Err(err) => Err(err.to_string()),
-                };
-
-                // Cleanup
-                if let Some(event_id) = destroy_event_id {
-                    window.unlisten(event_id);
-                }
-                if let Err(err) = cp_clone2.kill() {
-                    log::error!("child process could not be killed {}", err);
-                }
-
-                result
-            }
-            Err(err) => Err(err.to_string()),
-        }
+        Ok(probe::parse_ffprobe_json(&String::from_utf8_lossy(
+            &output.stdout,
+        ))?)
     }
 
-    fn build_ffmpeg_filters(&self, actions: &Vec<Value>) -> String {
-        let mut filters: Vec<String> = Vec::new();
-        let mut latest_crop: Option<&Value> = None;
-
-        for action in actions {
-            let action_type = action["type"].as_str().unwrap_or("");
-
-            match action_type {
-                "rotate" => {
-                    let angle = action["value"].as_i64().unwrap_or(0);
-                    match angle % 360 {
-                        -90 | 270 => filters.push("transpose=2".to_string()),
-                        90 | -270 => filters.push("transpose=1".to_string()),
-                        180 | -180 => filters.push("hflip,vflip".to_string()),
-                        _ => {}
-                    }
-                }
-                "flip" => {
-                    if let Some(flip_obj) = action["value"].as_object() {
-                        if flip_obj.get("horizontal").and_then(|v| v.as_bool()) == Some(true) {
-                            filters.push("hflip".to_string());
-                        }
-                        if flip_obj.get("vertical").and_then(|v| v.as_bool()) == Some(true) {
-                            filters.push("vflip".to_string());
-                        }
-                    }
-                }
-                "crop" => {
-                    latest_crop = Some(&action["value"]);
-                }
-                _ => {}
-            }
-        }
-
-        // Apply only the last crop
-        if let Some(c) = latest_crop {
-            let w = c["width"].as_f64().unwrap_or(0.0).round() as i64;
-            let h = c["height"].as_f64().unwrap_or(0.0).round() as i64;
-            let x = c["left"].as_f64().unwrap_or(0.0).round() as i64;
-            let y = c["top"].as_f64().unwrap_or(0.0).round() as i64;
+    /// Fails when an encode that exited cleanly left no readable video behind.
+    async fn verify_output(&self, output_file: &Path) -> Result<(), FfmpegError> {
+        let command = self.ffprobe_command()?;
+        let output_file = output_file.to_path_buf();
+        tokio::task::spawn_blocking(move || diagnostics::verify_output(command, &output_file))
+            .await
+            .map_err(|err| FfmpegError::from(err.to_string()))?
+    }
 
-            filters.push(format!("crop={}:{}:{}:{}", w, h, x, y));
-        }
+    fn ffprobe_command(&self) -> Result<Command, FfmpegError> {
+        self.app
+            .shell()
+            .sidecar("compresso_ffprobe")
+            .map(Command::from)
+            .map_err(|err| {
+                FfmpegError::new(
+                    FfmpegErrorKind::FfmpegNotFound,
+                    &format!("[ffprobe-sidecar]: {:?}", err),
+                )
+            })
+    }
 
-        filters.join(",")
+    pub async fn get_video_info(&mut self, video_path: &str) -> Result<VideoInfo, FfmpegError> {
+        let media_info = self.probe_media(video_path).await?;
+        let video_stream = media_info.primary_video_stream();
+
+        Ok(VideoInfo {
+            duration: media_info
+                .duration_seconds()
+                .map(|seconds| format_seconds_as_duration(seconds, 2)),
+            dimensions: video_stream.and_then(StreamInfo::display_dimensions),
+            fps: video_stream
+                .and_then(|stream| stream.frame_rate)
+                .map(|fps| ((fps * 100.0).round() / 100.0) as f32),
+            hdr: video_stream.and_then(StreamInfo::hdr_format),
+        })
     }
 }
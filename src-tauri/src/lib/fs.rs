@@ -1,3 +1,4 @@
+use filetime::FileTime;
 use std::{
     fs,
     path::{Path, PathBuf},
@@ -72,12 +73,32 @@ pub fn get_image_dimension(image_path: &str) -> Result<(u32, u32), String> {
     }
 }
 
-/// Copies file from one path to another path
-pub async fn copy_file(from: &str, to: &str) -> std::io::Result<u64> {
+/// Copies file from one path to another path, creating missing folders of the target.
+/// With `keep_file_times` the copy also gets the file times of `from`; the copy
+/// still succeeds when they cannot be set.
+pub async fn copy_file(from: &str, to: &str, keep_file_times: bool) -> std::io::Result<u64> {
+    if let Some(dir) = Path::new(to).parent() {
+        tokio::fs::create_dir_all(dir).await?;
+    }
     let result = tokio::fs::copy(from, to).await?;
+    if keep_file_times {
+        if let Err(err) = copy_file_times(Path::new(from), Path::new(to)) {
+            log::warn!("[fs] file times could not be copied to {}: {}", to, err);
+        }
+    }
     Ok(result)
 }
 
+/// Gives `to` the modification and access times of `from`.
+pub fn copy_file_times(from: &Path, to: &Path) -> std::io::Result<()> {
+    let metadata = fs::metadata(from)?;
+    filetime::set_file_times(
+        to,
+        FileTime::from_last_access_time(&metadata),
+        FileTime::from_last_modification_time(&metadata),
+    )
+}
+
 /// Deletes file from the given path
 pub async fn delete_file(path: &str) -> std::io::Result<()> {
     tokio::fs::remove_file(path).await
@@ -91,7 +112,7 @@ pub async fn delete_stale_files(
     let mut entries = tokio::fs::read_dir(path).await?;
     let now = SystemTime::now()
         .duration_since(time::UNIX_EPOCH)
-        .map_err(|e| std::io::Error::new(std::io::ErrorKind::Other, e))?;
+        .map_err(std::io::Error::other)?;
     let mut deleted_files: Vec<PathBuf> = vec![];
     while let Some(entry) = entries.next_entry().await? {
         let metadata = entry.metadata().await?;
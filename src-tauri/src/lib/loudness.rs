@@ -0,0 +1,77 @@
+use serde::Deserialize;
+
+/// Integrated loudness target, in LUFS.
+pub const TARGET_INTEGRATED: f64 = -16.0;
+/// Maximum true peak, in dBTP.
+pub const TARGET_TRUE_PEAK: f64 = -1.5;
+/// Loudness range target, in LU.
+pub const TARGET_LOUDNESS_RANGE: f64 = 11.0;
+/// `loudnorm` outputs 192 kHz audio, which is resampled to this rate unless
+/// the options pick one.
+pub const DEFAULT_SAMPLE_RATE: u32 = 48_000;
+
+/// Loudness of the input, as printed by a `loudnorm` measurement pass.
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub struct LoudnessMeasurement {
+    pub integrated: f64,
+    pub true_peak: f64,
+    pub loudness_range: f64,
+    pub threshold: f64,
+    pub target_offset: f64,
+}
+
+/// `loudnorm` prints every value as a string.
+#[derive(Deserialize)]
+struct LoudnormOutput {
+    input_i: String,
+    input_tp: String,
+    input_lra: String,
+    input_thresh: String,
+    target_offset: String,
+}
+
+fn targets() -> String {
+    format!("I={TARGET_INTEGRATED}:TP={TARGET_TRUE_PEAK}:LRA={TARGET_LOUDNESS_RANGE}")
+}
+
+/// Filter of the measurement pass, which prints its result as JSON.
+pub fn measure_filter() -> String {
+    format!("loudnorm={}:print_format=json", targets())
+}
+
+/// Filter applying a measurement. Linear mode changes the gain only, so the
+/// dynamics of the source are kept.
+pub fn normalize_filter(measurement: &LoudnessMeasurement) -> String {
+    format!(
+        "loudnorm={}:measured_I={:.2}:measured_TP={:.2}:measured_LRA={:.2}:measured_thresh={:.2}:offset={:.2}:linear=true",
+        targets(),
+        measurement.integrated,
+        measurement.true_peak,
+        measurement.loudness_range,
+        measurement.threshold,
+        measurement.target_offset
+    )
+}
+
+/// Reads the JSON block `loudnorm` prints at the end of a measurement pass.
+/// `None` when it is missing, or for silent audio, which measures `-inf`.
+pub fn parse_measurement(stderr: &str) -> Option<LoudnessMeasurement> {
+    let start = stderr.rfind('{')?;
+    let end = start + stderr[start..].find('}')?;
+    let output: LoudnormOutput = serde_json::from_str(&stderr[start..=end]).ok()?;
+    let value = |value: &str| {
+        value
+            .trim()
+            .parse::<f64>()
+            .ok()
+            .filter(|value| value.is_finite())
+    };
+
+    Some(LoudnessMeasurement {
+        integrated: value(&output.input_i)?,
+        true_peak: value(&output.input_tp)?,
+        loudness_range: value(&output.input_lra)?,
+        threshold: value(&output.input_thresh)?,
+        target_offset: value(&output.target_offset)?,
+    })
+}
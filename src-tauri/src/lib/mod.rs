@@ -1,5 +1,14 @@
+pub mod capabilities;
+pub mod diagnostics;
 pub mod domain;
+pub mod encoder;
 pub mod ffmpeg;
 pub mod fs;
+pub mod loudness;
+pub mod naming;
+pub mod probe;
+pub mod profiles;
+pub mod progress;
+pub mod queue;
 pub mod sys;
 pub mod tauri_commands;
@@ -0,0 +1,504 @@
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
+
+use crate::domain::{ConflictPolicy, Container, EncodeOptions};
+
+/// Template used when none is given, e.g. `clip_compressed.mp4`.
+pub const DEFAULT_NAME_TEMPLATE: &str = "{stem}_compressed.{ext}";
+
+/// Characters no file name may contain on any of the supported platforms.
+const ILLEGAL_CHARACTERS: [char; 9] = ['<', '>', ':', '"', '/', '\\', '|', '?', '*'];
+
+const RESERVED_WINDOWS_NAMES: [&str; 22] = [
+    "CON", "PRN", "AUX", "NUL", "COM1", "COM2", "COM3", "COM4", "COM5", "COM6", "COM7", "COM8",
+    "COM9", "LPT1", "LPT2", "LPT3", "LPT4", "LPT5", "LPT6", "LPT7", "LPT8", "LPT9",
+];
+
+const MAX_FILE_NAME_BYTES: usize = 255;
+
+#[derive(Clone, Copy, Debug, PartialEq, Eq)]
+enum Variable {
+    Stem,
+    Ext,
+    Date,
+    Width,
+    Height,
+    Quality,
+    Preset,
+    Codec,
+    Index,
+    Parent,
+}
+
+impl Variable {
+    const ALL: [Variable; 10] = [
+        Variable::Stem,
+        Variable::Ext,
+        Variable::Date,
+        Variable::Width,
+        Variable::Height,
+        Variable::Quality,
+        Variable::Preset,
+        Variable::Codec,
+        Variable::Index,
+        Variable::Parent,
+    ];
+
+    fn name(self) -> &'static str {
+        match self {
+            Variable::Stem => "stem",
+            Variable::Ext => "ext",
+            Variable::Date => "date",
+            Variable::Width => "width",
+            Variable::Height => "height",
+            Variable::Quality => "quality",
+            Variable::Preset => "preset",
+            Variable::Codec => "codec",
+            Variable::Index => "index",
+            Variable::Parent => "parent",
+        }
+    }
+
+    fn from_name(name: &str) -> Option<Self> {
+        Variable::ALL
+            .into_iter()
+            .find(|variable| variable.name() == name)
+    }
+}
+
+#[derive(Clone, Debug)]
+enum Part {
+    Text(String),
+    Variable(Variable),
+}
+
+/// Parsed output file name template, e.g. `{stem}_{width}x{height}.{ext}`.
+/// `{{` and `}}` stand for literal braces.
+#[derive(Clone, Debug)]
+pub struct NameTemplate {
+    template: String,
+    parts: Vec<Part>,
+}
+
+impl Default for NameTemplate {
+    fn default() -> Self {
+        Self::parse(DEFAULT_NAME_TEMPLATE).expect("default template is valid")
+    }
+}
+
+impl NameTemplate {
+    pub fn parse(template: &str) -> Result<Self, String> {
+        if template.trim().is_empty() {
+            return Err(String::from("The name template is empty."));
+        }
+
+        let mut parts: Vec<Part> = Vec::new();
+        let mut text = String::new();
+        let mut chars = template.chars();
+        while let Some(char) = chars.next() {
+            match char {
+                '{' if chars.as_str().starts_with('{') => {
+                    chars.next();
+                    text.push('{');
+                }
+                '}' if chars.as_str().starts_with('}') => {
+                    chars.next();
+                    text.push('}');
+                }
+                '{' => {
+                    let rest = chars.as_str();
+                    let end = rest.find('}').ok_or_else(|| {
+                        format!("Unclosed \"{{\" in name template \"{template}\".")
+                    })?;
+                    let name = &rest[..end];
+                    let variable = Variable::from_name(name.trim()).ok_or_else(|| {
+                        let names: Vec<String> = Variable::ALL
+                            .iter()
+                            .map(|variable| format!("{{{}}}", variable.name()))
+                            .collect();
+                        format!(
+                            "Unknown variable {{{name}}} in name template. Available: {}",
+                            names.join(", ")
+                        )
+                    })?;
+                    chars = rest[end + 1..].chars();
+                    if !text.is_empty() {
+                        parts.push(Part::Text(std::mem::take(&mut text)));
+                    }
+                    parts.push(Part::Variable(variable));
+                }
+                '}' => {
+                    return Err(format!(
+                        "Unexpected \"}}\" in name template \"{template}\", use \"}}}}\" for a literal brace."
+                    ));
+                }
+                char if is_illegal_character(char) => {
+                    return Err(format!(
+                        "The name template cannot contain {char:?}, file names may not use any of {}.",
+                        ILLEGAL_CHARACTERS.iter().collect::<String>()
+                    ));
+                }
+                char => text.push(char),
+            }
+        }
+        if !text.is_empty() {
+            parts.push(Part::Text(text));
+        }
+
+        Ok(Self {
+            template: template.to_string(),
+            parts,
+        })
+    }
+
+    pub fn as_str(&self) -> &str {
+        &self.template
+    }
+
+    /// Whether rendering needs the video's dimensions, which may require a probe.
+    pub fn needs_dimensions(&self) -> bool {
+        self.parts.iter().any(|part| {
+            matches!(
+                part,
+                Part::Variable(Variable::Width) | Part::Variable(Variable::Height)
+            )
+        })
+    }
+
+    /// File name for one output. The container extension is appended when the
+    /// template does not end with it.
+    pub fn render(&self, context: &NameContext) -> Result<String, String> {
+        let mut name = String::new();
+        for part in &self.parts {
+            match part {
+                Part::Text(text) => name.push_str(text),
+                Part::Variable(variable) => name.push_str(&sanitize(&context.value(*variable)?)),
+            }
+        }
+
+        let extension = format!(".{}", context.container.extension());
+        if !name.to_lowercase().ends_with(&extension) {
+            name.push_str(&extension);
+        }
+
+        validate_file_name(&name)?;
+        Ok(name)
+    }
+}
+
+/// Values the template variables stand for, for one input file.
+#[derive(Clone, Debug)]
+pub struct NameContext {
+    pub stem: String,
+    pub container: Container,
+    /// Local date as `YYYY-MM-DD`.
+    pub date: String,
+    /// Output dimensions, `None` when unknown.
+    pub dimensions: Option<(u32, u32)>,
+    pub quality: String,
+    pub preset: String,
+    pub codec: String,
+    /// 1-based position of the file in its batch.
+    pub index: usize,
+    /// Name of the folder that contains the input.
+    pub parent: String,
+}
+
+impl NameContext {
+    /// `source_dimensions` are used unless the options resize the video.
+    pub fn new(
+        input_path: &Path,
+        options: &EncodeOptions,
+        source_dimensions: Option<(u32, u32)>,
+        index: usize,
+    ) -> Self {
+        let quality = match (options.target_size_mb, options.quality) {
+            (Some(target_size_mb), _) => format!("{target_size_mb}MB"),
+            (None, Some(quality)) => quality.to_string(),
+            (None, None) => String::from("default"),
+        };
+
+        Self {
+            stem: file_name_part(input_path.file_stem()).unwrap_or_else(|| "output".to_string()),
+            container: options.container,
+            date: chrono::Local::now().format("%Y-%m-%d").to_string(),
+            dimensions: options.dimensions.or(source_dimensions),
+            quality,
+            preset: options.preset.as_ref().to_string(),
+            codec: options.resolved_video_codec().as_ref().to_string(),
+            index,
+            parent: file_name_part(input_path.parent().and_then(Path::file_name))
+                .unwrap_or_default(),
+        }
+    }
+
+    fn value(&self, variable: Variable) -> Result<String, String> {
+        let dimension = |pick: fn((u32, u32)) -> u32| {
+            self.dimensions
+                .map(|dimensions| pick(dimensions).to_string())
+                .ok_or_else(|| {
+                    format!(
+                        "Could not read the video dimensions for {{{}}}.",
+                        variable.name()
+                    )
+                })
+        };
+        Ok(match variable {
+            Variable::Stem => self.stem.clone(),
+            Variable::Ext => self.container.extension().to_string(),
+            Variable::Date => self.date.clone(),
+            Variable::Width => dimension(|(width, _)| width)?,
+            Variable::Height => dimension(|(_, height)| height)?,
+            Variable::Quality => self.quality.clone(),
+            Variable::Preset => self.preset.clone(),
+            Variable::Codec => self.codec.clone(),
+            Variable::Index => self.index.to_string(),
+            Variable::Parent => self.parent.clone(),
+        })
+    }
+}
+
+/// Rejects names that cannot be created on Windows, macOS or Linux.
+pub fn validate_file_name(name: &str) -> Result<(), String> {
+    if name.is_empty() || name == "." || name == ".." {
+        return Err(format!("\"{name}\" is not a valid file name."));
+    }
+    if let Some(char) = name.chars().find(|char| is_illegal_character(*char)) {
+        return Err(format!("File name \"{name}\" contains {char:?}."));
+    }
+    if name.ends_with('.') || name.ends_with(' ') {
+        return Err(format!(
+            "File name \"{name}\" cannot end with a dot or a space."
+        ));
+    }
+    let base = name.split('.').next().unwrap_or_default().trim_end();
+    if RESERVED_WINDOWS_NAMES
+        .iter()
+        .any(|reserved| reserved.eq_ignore_ascii_case(base))
+    {
+        return Err(format!("File name \"{name}\" is reserved on Windows."));
+    }
+    if name.len() > MAX_FILE_NAME_BYTES {
+        return Err(format!(
+            "File name \"{name}\" is longer than {MAX_FILE_NAME_BYTES} bytes."
+        ));
+    }
+    Ok(())
+}
+
+/// Applies `policy` when `candidate` already exists or is in `reserved`, the
+/// paths already given to other outputs of the same batch. Reserved paths are
+/// never overwritten, even with `ConflictPolicy::Overwrite`. `None` means the
+/// file should be skipped.
+pub fn resolve_conflict(
+    candidate: PathBuf,
+    policy: ConflictPolicy,
+    reserved: &HashSet<PathBuf>,
+) -> Option<PathBuf> {
+    let is_taken = |path: &Path| path.exists() || reserved.contains(path);
+    if !is_taken(&candidate) {
+        return Some(candidate);
+    }
+
+    match policy {
+        ConflictPolicy::Overwrite if !reserved.contains(&candidate) => Some(candidate),
+        ConflictPolicy::Skip => None,
+        _ => {
+            let stem = file_name_part(candidate.file_stem()).unwrap_or_default();
+            let extension = candidate
+                .extension()
+                .map(|ext| format!(".{}", ext.to_string_lossy()))
+                .unwrap_or_default();
+            let mut index = 1u32;
+            loop {
+                let numbered = candidate.with_file_name(format!("{stem}_{index}{extension}"));
+                if !is_taken(&numbered) {
+                    return Some(numbered);
+                }
+                index += 1;
+            }
+        }
+    }
+}
+
+fn is_illegal_character(char: char) -> bool {
+    ILLEGAL_CHARACTERS.contains(&char) || char.is_control()
+}
+
+/// Variable values come from other file names, which may use characters this
+/// platform allows but others do not.
+fn sanitize(value: &str) -> String {
+    value
+        .chars()
+        .map(|char| {
+            if is_illegal_character(char) {
+                '_'
+            } else {
+                char
+            }
+        })
+        .collect()
+}
+
+fn file_name_part(part: Option<&std::ffi::OsStr>) -> Option<String> {
+    part.map(|part| part.to_string_lossy().to_string())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    fn context() -> NameContext {
+        NameContext {
+            stem: String::from("clip"),
+            container: Container::Mp4,
+            date: String::from("2024-05-01"),
+            dimensions: Some((1280, 720)),
+            quality: String::from("70"),
+            preset: String::from("ironclad"),
+            codec: String::from("h264"),
+            index: 3,
+            parent: String::from("holiday"),
+        }
+    }
+
+    fn render(template: &str) -> Result<String, String> {
+        NameTemplate::parse(template)?.render(&context())
+    }
+
+    #[test]
+    fn variables_are_expanded() {
+        assert_eq!(
+            render("{index}_{stem}_{width}x{height}_{date}.{ext}").unwrap(),
+            "3_clip_1280x720_2024-05-01.mp4"
+        );
+        assert_eq!(
+            render("{parent}-{codec}-{preset}-{quality}").unwrap(),
+            "holiday-h264-ironclad-70.mp4"
+        );
+    }
+
+    #[test]
+    fn default_template_adds_the_compressed_suffix() {
+        assert_eq!(
+            NameTemplate::default().render(&context()).unwrap(),
+            "clip_compressed.mp4"
+        );
+    }
+
+    #[test]
+    fn doubled_braces_are_literal() {
+        assert_eq!(render("{{{stem}}}").unwrap(), "{clip}.mp4");
+    }
+
+    #[test]
+    fn extension_is_appended_only_when_missing() {
+        assert_eq!(render("{stem}").unwrap(), "clip.mp4");
+        assert_eq!(render("{stem}.MP4").unwrap(), "clip.MP4");
+    }
+
+    #[test]
+    fn malformed_templates_are_rejected() {
+        assert!(NameTemplate::parse("  ").is_err());
+        assert!(NameTemplate::parse("{stem").is_err());
+        assert!(NameTemplate::parse("stem}").is_err());
+        assert!(NameTemplate::parse("{name}")
+            .unwrap_err()
+            .contains("Unknown variable {name}"));
+    }
+
+    #[test]
+    fn illegal_characters_in_templates_are_rejected() {
+        for template in ["a/b", "a\\b", "a:b", "a?b", "a*b", "a|b", "a\tb"] {
+            assert!(NameTemplate::parse(template).is_err(), "{template}");
+        }
+    }
+
+    #[test]
+    fn illegal_characters_in_values_are_replaced() {
+        let context = NameContext {
+            stem: String::from("what? a*clip"),
+            ..context()
+        };
+        let name = NameTemplate::parse("{stem}").unwrap().render(&context);
+
+        assert_eq!(name.unwrap(), "what_ a_clip.mp4");
+    }
+
+    #[test]
+    fn dimensions_are_required_when_used() {
+        let context = NameContext {
+            dimensions: None,
+            ..context()
+        };
+        let template = NameTemplate::parse("{stem}_{height}").unwrap();
+
+        assert!(template.needs_dimensions());
+        assert!(template.render(&context).is_err());
+        assert!(!NameTemplate::parse("{stem}").unwrap().needs_dimensions());
+    }
+
+    #[test]
+    fn invalid_file_names_are_rejected() {
+        assert!(validate_file_name("..").is_err());
+        assert!(validate_file_name("clip.").is_err());
+        assert!(validate_file_name("con.mp4").is_err());
+        assert!(validate_file_name(&format!("{}.mp4", "a".repeat(252))).is_err());
+        assert!(validate_file_name("console.mp4").is_ok());
+    }
+
+    #[test]
+    fn context_reads_the_input_path_and_options() {
+        let options: EncodeOptions = serde_json::from_value(json!({
+            "container": "mkv",
+            "targetSizeMb": 25.0
+        }))
+        .unwrap();
+        let context = NameContext::new(
+            Path::new("/videos/trip/beach.mov"),
+            &options,
+            Some((1920, 1080)),
+            1,
+        );
+
+        assert_eq!(context.stem, "beach");
+        assert_eq!(context.parent, "trip");
+        assert_eq!(context.quality, "25MB");
+        assert_eq!(context.dimensions, Some((1920, 1080)));
+        assert_eq!(
+            context.date,
+            chrono::Local::now().format("%Y-%m-%d").to_string()
+        );
+    }
+
+    #[test]
+    fn conflicts_follow_the_policy() {
+        let dir = std::env::temp_dir().join(format!("compresso-naming-{}", nanoid::nanoid!()));
+        std::fs::create_dir_all(&dir).unwrap();
+        let existing = dir.join("clip.mp4");
+        std::fs::write(&existing, b"").unwrap();
+        let free = dir.join("other.mp4");
+        let reserved: HashSet<PathBuf> = [dir.join("clip_1.mp4"), free.clone()].into();
+
+        let resolve =
+            |candidate: &Path, policy| resolve_conflict(candidate.to_path_buf(), policy, &reserved);
+        let auto_renamed = resolve(&existing, ConflictPolicy::AutoRename);
+        let overwritten = resolve(&existing, ConflictPolicy::Overwrite);
+        let skipped = resolve(&existing, ConflictPolicy::Skip);
+        let reserved_overwrite = resolve(&free, ConflictPolicy::Overwrite);
+        std::fs::remove_dir_all(&dir).unwrap();
+
+        assert_eq!(auto_renamed, Some(dir.join("clip_2.mp4")));
+        assert_eq!(overwritten, Some(existing.clone()));
+        assert_eq!(skipped, None);
+        // Paths given to other outputs of the batch are never overwritten.
+        assert_eq!(reserved_overwrite, Some(dir.join("other_1.mp4")));
+        assert_eq!(
+            resolve(&dir.join("new.mp4"), ConflictPolicy::Skip),
+            Some(dir.join("new.mp4"))
+        );
+    }
+}
@@ -0,0 +1,464 @@
+use serde::Deserialize;
+use serde_json::Value;
+use std::collections::BTreeMap;
+
+use crate::domain::{ChapterInfo, FormatInfo, MediaInfo, StreamInfo, StreamKind};
+
+/// ffprobe arguments producing the JSON parsed by [`parse_ffprobe_json`].
+pub fn ffprobe_args(video_path: &str) -> Vec<String> {
+    [
+        "-v",
+        "error",
+        "-print_format",
+        "json",
+        "-show_format",
+        "-show_streams",
+        "-show_chapters",
+        video_path,
+    ]
+    .iter()
+    .map(|arg| arg.to_string())
+    .collect()
+}
+
+#[derive(Deserialize, Default)]
+struct RawProbe {
+    #[serde(default)]
+    streams: Vec<RawStream>,
+    #[serde(default)]
+    format: RawFormat,
+    #[serde(default)]
+    chapters: Vec<RawChapter>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawStream {
+    index: u32,
+    codec_type: Option<String>,
+    codec_name: Option<String>,
+    codec_long_name: Option<String>,
+    profile: Option<String>,
+    pix_fmt: Option<String>,
+    bits_per_raw_sample: Option<String>,
+    width: Option<u32>,
+    height: Option<u32>,
+    avg_frame_rate: Option<String>,
+    r_frame_rate: Option<String>,
+    color_range: Option<String>,
+    color_space: Option<String>,
+    color_transfer: Option<String>,
+    color_primaries: Option<String>,
+    bit_rate: Option<String>,
+    sample_rate: Option<String>,
+    channels: Option<u32>,
+    channel_layout: Option<String>,
+    duration: Option<String>,
+    #[serde(default)]
+    disposition: BTreeMap<String, i64>,
+    #[serde(default)]
+    tags: BTreeMap<String, Value>,
+    #[serde(default)]
+    side_data_list: Vec<BTreeMap<String, Value>>,
+}
+
+#[derive(Deserialize, Default)]
+struct RawFormat {
+    format_name: Option<String>,
+    format_long_name: Option<String>,
+    duration: Option<String>,
+    start_time: Option<String>,
+    size: Option<String>,
+    bit_rate: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, Value>,
+}
+
+#[derive(Deserialize)]
+struct RawChapter {
+    id: i64,
+    start_time: Option<String>,
+    end_time: Option<String>,
+    #[serde(default)]
+    tags: BTreeMap<String, Value>,
+}
+
+/// Parses the output of `ffprobe -print_format json -show_format -show_streams -show_chapters`.
+pub fn parse_ffprobe_json(json: &str) -> Result<MediaInfo, String> {
+    let raw: RawProbe =
+        serde_json::from_str(json).map_err(|err| format!("Could not read media info: {err}"))?;
+
+    let streams = raw.streams.into_iter().map(into_stream_info).collect();
+
+    let format = FormatInfo {
+        format_name: raw.format.format_name,
+        format_long_name: raw.format.format_long_name,
+        duration_seconds: parse_number(raw.format.duration.as_deref()),
+        start_time_seconds: parse_number(raw.format.start_time.as_deref()),
+        size: parse_number(raw.format.size.as_deref()),
+        bit_rate: parse_number(raw.format.bit_rate.as_deref()),
+        tags: string_tags(raw.format.tags),
+    };
+
+    let chapters = raw
+        .chapters
+        .into_iter()
+        .map(|chapter| {
+            let tags = string_tags(chapter.tags);
+            ChapterInfo {
+                id: chapter.id,
+                start_seconds: parse_number(chapter.start_time.as_deref()).unwrap_or(0.0),
+                end_seconds: parse_number(chapter.end_time.as_deref()).unwrap_or(0.0),
+                title: tag(&tags, "title"),
+            }
+        })
+        .collect();
+
+    Ok(MediaInfo {
+        format,
+        streams,
+        chapters,
+    })
+}
+
+fn into_stream_info(raw: RawStream) -> StreamInfo {
+    let kind = match raw.codec_type.as_deref() {
+        Some("video") => StreamKind::Video,
+        Some("audio") => StreamKind::Audio,
+        Some("subtitle") => StreamKind::Subtitle,
+        Some("data") => StreamKind::Data,
+        Some("attachment") => StreamKind::Attachment,
+        _ => StreamKind::Unknown,
+    };
+    let rotation = stream_rotation(&raw.side_data_list, &raw.tags);
+    let tags = string_tags(raw.tags);
+
+    let bit_depth = parse_number(raw.bits_per_raw_sample.as_deref()).or_else(|| {
+        if kind == StreamKind::Video {
+            raw.pix_fmt.as_deref().map(bit_depth_from_pix_fmt)
+        } else {
+            None
+        }
+    });
+
+    let frame_rate = if kind == StreamKind::Video {
+        parse_frame_rate(raw.avg_frame_rate.as_deref())
+            .or_else(|| parse_frame_rate(raw.r_frame_rate.as_deref()))
+    } else {
+        None
+    };
+
+    StreamInfo {
+        index: raw.index,
+        kind,
+        codec_name: raw.codec_name,
+        codec_long_name: raw.codec_long_name,
+        profile: raw.profile,
+        pix_fmt: raw.pix_fmt,
+        bit_depth,
+        width: raw.width,
+        height: raw.height,
+        frame_rate,
+        color_range: raw.color_range,
+        color_space: raw.color_space,
+        color_transfer: raw.color_transfer,
+        color_primaries: raw.color_primaries,
+        bit_rate: parse_number(raw.bit_rate.as_deref()),
+        sample_rate: parse_number(raw.sample_rate.as_deref()),
+        channels: raw.channels,
+        channel_layout: raw.channel_layout,
+        language: tag(&tags, "language").filter(|lang| lang != "und"),
+        title: tag(&tags, "title"),
+        rotation,
+        duration_seconds: parse_number(raw.duration.as_deref()),
+        is_default: raw.disposition.get("default") == Some(&1),
+        is_attached_picture: raw.disposition.get("attached_pic") == Some(&1),
+        tags,
+    }
+}
+
+/// Reads the display matrix rotation (counter-clockwise in ffprobe) or the
+/// legacy `rotate` tag (clockwise) and normalizes it to clockwise degrees.
+fn stream_rotation(
+    side_data_list: &[BTreeMap<String, Value>],
+    tags: &BTreeMap<String, Value>,
+) -> Option<i32> {
+    let from_side_data = side_data_list
+        .iter()
+        .find_map(|side_data| side_data.get("rotation")?.as_f64())
+        .map(|rotation| -rotation);
+    let from_tag = || {
+        tags.get("rotate")
+            .and_then(|value| value.as_str())
+            .and_then(|value| value.trim().parse::<f64>().ok())
+    };
+    let degrees = from_side_data.or_else(from_tag)?;
+    Some(((degrees.round() as i32) % 360 + 360) % 360)
+}
+
+/// High bit depth pixel formats carry the depth before an endianness suffix,
+/// e.g. `yuv420p10le` or `p010le`. Everything else is treated as 8-bit.
+fn bit_depth_from_pix_fmt(pix_fmt: &str) -> u32 {
+    let Some(base) = pix_fmt
+        .strip_suffix("le")
+        .or_else(|| pix_fmt.strip_suffix("be"))
+    else {
+        return 8;
+    };
+    let digits_start = base
+        .rfind(|c: char| !c.is_ascii_digit())
+        .map(|index| index + 1)
+        .unwrap_or(0);
+    base[digits_start..]
+        .parse::<u32>()
+        .ok()
+        .filter(|depth| (9..=16).contains(depth))
+        .unwrap_or(8)
+}
+
+fn parse_frame_rate(value: Option<&str>) -> Option<f64> {
+    let value = value?;
+    let rate = match value.split_once('/') {
+        Some((num, den)) => {
+            let num = num.trim().parse::<f64>().ok()?;
+            let den = den.trim().parse::<f64>().ok()?;
+            if den == 0.0 {
+                return None;
+            }
+            num / den
+        }
+        None => value.trim().parse::<f64>().ok()?,
+    };
+    if rate.is_finite() && rate > 0.0 {
+        Some(rate)
+    } else {
+        None
+    }
+}
+
+fn parse_number<T: std::str::FromStr>(value: Option<&str>) -> Option<T> {
+    value?.trim().parse::<T>().ok()
+}
+
+fn string_tags(tags: BTreeMap<String, Value>) -> BTreeMap<String, String> {
+    tags.into_iter()
+        .map(|(key, value)| {
+            let value = match value {
+                Value::String(value) => value,
+                other => other.to_string(),
+            };
+            (key, value)
+        })
+        .collect()
+}
+
+/// Case-insensitive tag lookup, containers differ in how they spell keys.
+fn tag(tags: &BTreeMap<String, String>, key: &str) -> Option<String> {
+    tags.iter()
+        .find(|(name, _)| name.eq_ignore_ascii_case(key))
+        .map(|(_, value)| value.clone())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::domain::HdrFormat;
+    use serde_json::json;
+
+    /// Trimmed `ffprobe` output of a phone video with cover art, a commentary
+    /// track and subtitles.
+    const PHONE_VIDEO: &str = r#"{
+        "streams": [
+            {
+                "index": 0,
+                "codec_name": "mjpeg",
+                "codec_type": "video",
+                "width": 600,
+                "height": 600,
+                "pix_fmt": "yuvj420p",
+                "avg_frame_rate": "0/0",
+                "r_frame_rate": "90000/1",
+                "disposition": { "default": 0, "attached_pic": 1 }
+            },
+            {
+                "index": 1,
+                "codec_name": "hevc",
+                "codec_long_name": "H.265 / HEVC (High Efficiency Video Coding)",
+                "profile": "Main 10",
+                "codec_type": "video",
+                "width": 3840,
+                "height": 2160,
+                "pix_fmt": "yuv420p10le",
+                "color_range": "tv",
+                "color_space": "bt2020nc",
+                "color_transfer": "arib-std-b67",
+                "color_primaries": "bt2020",
+                "avg_frame_rate": "30000/1001",
+                "r_frame_rate": "30/1",
+                "duration": "12.345000",
+                "bit_rate": "45000000",
+                "disposition": { "default": 1, "attached_pic": 0 },
+                "tags": { "language": "und", "handler_name": "Core Media Video" },
+                "side_data_list": [
+                    { "side_data_type": "Display Matrix", "rotation": -90 }
+                ]
+            },
+            {
+                "index": 2,
+                "codec_name": "aac",
+                "codec_type": "audio",
+                "sample_rate": "48000",
+                "channels": 2,
+                "channel_layout": "stereo",
+                "bit_rate": "192000",
+                "disposition": { "default": 1 },
+                "tags": { "LANGUAGE": "eng", "title": "Commentary" }
+            },
+            {
+                "index": 3,
+                "codec_name": "mov_text",
+                "codec_type": "subtitle",
+                "tags": { "language": "jpn" }
+            }
+        ],
+        "chapters": [
+            { "id": 0, "start_time": "0.000000", "end_time": "6.000000", "tags": { "title": "Intro" } },
+            { "id": 1, "start_time": "6.000000", "end_time": "12.345000" }
+        ],
+        "format": {
+            "format_name": "mov,mp4,m4a,3gp,3g2,mj2",
+            "format_long_name": "QuickTime / MOV",
+            "duration": "12.345000",
+            "start_time": "0.000000",
+            "size": "69500000",
+            "bit_rate": "45038000",
+            "tags": { "major_brand": "qt  ", "creation_time": "2024-05-01T10:00:00.000000Z" }
+        }
+    }"#;
+
+    fn tags(value: serde_json::Value) -> BTreeMap<String, Value> {
+        serde_json::from_value(value).unwrap()
+    }
+
+    #[test]
+    fn parses_format_streams_and_chapters() {
+        let info = parse_ffprobe_json(PHONE_VIDEO).unwrap();
+
+        assert_eq!(
+            info.format.format_long_name.as_deref(),
+            Some("QuickTime / MOV")
+        );
+        assert_eq!(info.format.duration_seconds, Some(12.345));
+        assert_eq!(info.format.size, Some(69_500_000));
+        assert_eq!(info.format.tags["major_brand"], "qt  ");
+        assert_eq!(info.streams.len(), 4);
+        assert_eq!(info.chapters.len(), 2);
+        assert_eq!(info.chapters[0].title.as_deref(), Some("Intro"));
+        assert_eq!(info.chapters[1].title, None);
+        assert_eq!(info.chapters[1].end_seconds, 12.345);
+    }
+
+    #[test]
+    fn primary_video_skips_cover_art() {
+        let info = parse_ffprobe_json(PHONE_VIDEO).unwrap();
+        let video = info.primary_video_stream().unwrap();
+
+        assert_eq!(video.index, 1);
+        assert_eq!(video.bit_depth, Some(10));
+        assert_eq!(video.rotation, Some(90));
+        assert_eq!(video.display_dimensions(), Some((2160, 3840)));
+        assert_eq!(video.hdr_format(), Some(HdrFormat::Hlg));
+        assert!((video.frame_rate.unwrap() - 29.97).abs() < 0.001);
+        // `und` is no language.
+        assert_eq!(video.language, None);
+        assert!(info.streams[0].is_attached_picture);
+    }
+
+    #[test]
+    fn audio_and_subtitle_tags_are_read_case_insensitively() {
+        let info = parse_ffprobe_json(PHONE_VIDEO).unwrap();
+
+        assert_eq!(
+            info.stream_languages(StreamKind::Audio),
+            [Some(String::from("eng"))]
+        );
+        assert_eq!(
+            info.stream_languages(StreamKind::Subtitle),
+            [Some(String::from("jpn"))]
+        );
+        let audio = info.streams_of(StreamKind::Audio).next().unwrap();
+        assert_eq!(audio.title.as_deref(), Some("Commentary"));
+        assert_eq!(audio.sample_rate, Some(48_000));
+        assert_eq!(audio.frame_rate, None);
+        assert_eq!(audio.bit_depth, None);
+        assert!(info.has_audio());
+    }
+
+    #[test]
+    fn missing_sections_parse_as_empty() {
+        let info = parse_ffprobe_json("{}").unwrap();
+
+        assert!(info.streams.is_empty());
+        assert!(info.chapters.is_empty());
+        assert_eq!(info.duration_seconds(), None);
+        assert!(parse_ffprobe_json("not json").is_err());
+    }
+
+    #[test]
+    fn side_data_rotation_is_flipped_to_clockwise() {
+        let side_data = vec![tags(json!({ "rotation": 90 }))];
+
+        assert_eq!(stream_rotation(&side_data, &BTreeMap::new()), Some(270));
+        assert_eq!(
+            stream_rotation(&[tags(json!({ "rotation": -90.0 }))], &BTreeMap::new()),
+            Some(90)
+        );
+    }
+
+    #[test]
+    fn legacy_rotate_tag_is_already_clockwise() {
+        assert_eq!(
+            stream_rotation(&[], &tags(json!({ "rotate": "90" }))),
+            Some(90)
+        );
+        assert_eq!(
+            stream_rotation(&[], &tags(json!({ "rotate": "-180" }))),
+            Some(180)
+        );
+        assert_eq!(stream_rotation(&[], &tags(json!({ "rotate": "x" }))), None);
+        assert_eq!(stream_rotation(&[], &BTreeMap::new()), None);
+    }
+
+    #[test]
+    fn side_data_wins_over_the_rotate_tag() {
+        let side_data = vec![tags(
+            json!({ "side_data_type": "Display Matrix", "rotation": 180 }),
+        )];
+
+        assert_eq!(
+            stream_rotation(&side_data, &tags(json!({ "rotate": "90" }))),
+            Some(180)
+        );
+    }
+
+    #[test]
+    fn bit_depth_comes_from_the_pixel_format() {
+        assert_eq!(bit_depth_from_pix_fmt("yuv420p"), 8);
+        assert_eq!(bit_depth_from_pix_fmt("yuvj420p"), 8);
+        assert_eq!(bit_depth_from_pix_fmt("yuv420p10le"), 10);
+        assert_eq!(bit_depth_from_pix_fmt("yuv444p12be"), 12);
+        assert_eq!(bit_depth_from_pix_fmt("p010le"), 10);
+        assert_eq!(bit_depth_from_pix_fmt("gray16le"), 16);
+        // Little-endian 8-bit formats have no depth before the suffix.
+        assert_eq!(bit_depth_from_pix_fmt("rgb565le"), 8);
+    }
+
+    #[test]
+    fn frame_rates_are_read_from_fractions() {
+        assert_eq!(parse_frame_rate(Some("30/1")), Some(30.0));
+        assert_eq!(parse_frame_rate(Some("25")), Some(25.0));
+        assert_eq!(parse_frame_rate(Some("0/0")), None);
+        assert_eq!(parse_frame_rate(Some("30/0")), None);
+        assert_eq!(parse_frame_rate(Some("abc")), None);
+        assert_eq!(parse_frame_rate(None), None);
+    }
+}
@@ -0,0 +1,374 @@
+use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use std::{
+    fs,
+    path::{Path, PathBuf},
+    sync::Mutex,
+};
+
+use crate::domain::EncodeProfile;
+
+/// Name of the profiles file inside the app config directory.
+pub const PROFILES_FILE_NAME: &str = "profiles.toml";
+
+/// Same as `identifier` in `tauri.conf.json`, lets the CLI find the app config directory.
+const APP_IDENTIFIER: &str = "com.compresso.desktop";
+
+// Serializes read-modify-write cycles on the profiles file
+static PROFILES_LOCK: Lazy<Mutex<()>> = Lazy::new(|| Mutex::new(()));
+
+#[derive(Serialize, Deserialize, Default)]
+struct ProfilesFile {
+    #[serde(default)]
+    profiles: Vec<EncodeProfile>,
+}
+
+/// Profiles file the app writes to, for callers without an `AppHandle`.
+pub fn default_profiles_path() -> Option<PathBuf> {
+    dirs::config_dir().map(|dir| dir.join(APP_IDENTIFIER).join(PROFILES_FILE_NAME))
+}
+
+/// Reads the profiles at `path`, TOML unless the extension is `.json`. A
+/// missing file has no profiles.
+pub fn read_profiles(path: &Path) -> Result<Vec<EncodeProfile>, String> {
+    let contents = match fs::read_to_string(path) {
+        Ok(contents) => contents,
+        Err(err) if err.kind() == std::io::ErrorKind::NotFound => return Ok(Vec::new()),
+        Err(err) => return Err(format!("Could not read {}: {err}", path.display())),
+    };
+    let file: ProfilesFile = if is_json(path) {
+        serde_json::from_str(&contents).map_err(|err| err.to_string())
+    } else {
+        toml::from_str(&contents).map_err(|err| err.to_string())
+    }
+    .map_err(|err| format!("Invalid profiles file {}: {err}", path.display()))?;
+    Ok(file.profiles)
+}
+
+pub fn write_profiles(path: &Path, profiles: &[EncodeProfile]) -> Result<(), String> {
+    let file = ProfilesFile {
+        profiles: profiles.to_vec(),
+    };
+    let contents = if is_json(path) {
+        serde_json::to_string_pretty(&file).map_err(|err| err.to_string())?
+    } else {
+        toml::to_string_pretty(&file).map_err(|err| err.to_string())?
+    };
+    if let Some(dir) = path.parent() {
+        fs::create_dir_all(dir).map_err(|err| err.to_string())?;
+    }
+    // Written next to the real file first so a crash never leaves half a file.
+    let temp_path = path.with_extension("tmp");
+    fs::write(&temp_path, contents)
+        .and_then(|_| fs::rename(&temp_path, path))
+        .map_err(|err| format!("Could not write {}: {err}", path.display()))
+}
+
+/// Profile names are matched case-insensitively.
+pub fn find_profile<'a>(profiles: &'a [EncodeProfile], name: &str) -> Option<&'a EncodeProfile> {
+    profiles
+        .iter()
+        .find(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+}
+
+pub fn load_profile(path: &Path, name: &str) -> Result<EncodeProfile, String> {
+    let profiles = read_profiles(path)?;
+    find_profile(&profiles, name).cloned().ok_or_else(|| {
+        let names: Vec<&str> = profiles
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect();
+        if names.is_empty() {
+            format!(
+                "No profile named \"{name}\", {} has no profiles.",
+                path.display()
+            )
+        } else {
+            format!(
+                "No profile named \"{name}\". Available: {}",
+                names.join(", ")
+            )
+        }
+    })
+}
+
+pub fn create_profile(path: &Path, profile: EncodeProfile) -> Result<Vec<EncodeProfile>, String> {
+    modify(path, |profiles| {
+        let profile = normalized(profile)?;
+        if find_profile(profiles, &profile.name).is_some() {
+            return Err(format!(
+                "A profile named \"{}\" already exists.",
+                profile.name
+            ));
+        }
+        profiles.push(profile);
+        Ok(())
+    })
+}
+
+/// Replaces the profile called `name`, which may be renamed.
+pub fn update_profile(
+    path: &Path,
+    name: &str,
+    profile: EncodeProfile,
+) -> Result<Vec<EncodeProfile>, String> {
+    modify(path, |profiles| {
+        let profile = normalized(profile)?;
+        let index = position(profiles, name)?;
+        if let Some(other) = find_profile(profiles, &profile.name) {
+            if !other.name.eq_ignore_ascii_case(name.trim()) {
+                return Err(format!(
+                    "A profile named \"{}\" already exists.",
+                    profile.name
+                ));
+            }
+        }
+        profiles[index] = profile;
+        Ok(())
+    })
+}
+
+pub fn delete_profile(path: &Path, name: &str) -> Result<Vec<EncodeProfile>, String> {
+    modify(path, |profiles| {
+        let index = position(profiles, name)?;
+        profiles.remove(index);
+        Ok(())
+    })
+}
+
+/// Adds the profiles of another file, replacing profiles with the same name.
+pub fn import_profiles(path: &Path, from: &Path) -> Result<Vec<EncodeProfile>, String> {
+    if !from.exists() {
+        return Err(format!("File does not exist: {}", from.display()));
+    }
+    let imported = read_profiles(from)?;
+    modify(path, |profiles| {
+        for profile in imported {
+            let profile = normalized(profile)?;
+            match position(profiles, &profile.name) {
+                Ok(index) => profiles[index] = profile,
+                Err(_) => profiles.push(profile),
+            }
+        }
+        Ok(())
+    })
+}
+
+/// Writes the named profiles, or all when `names` is empty, to `to`.
+pub fn export_profiles(path: &Path, to: &Path, names: &[String]) -> Result<(), String> {
+    let profiles = read_profiles(path)?;
+    let exported = if names.is_empty() {
+        profiles
+    } else {
+        names
+            .iter()
+            .map(|name| position(&profiles, name).map(|index| profiles[index].clone()))
+            .collect::<Result<Vec<EncodeProfile>, String>>()?
+    };
+    write_profiles(to, &exported)
+}
+
+fn modify(
+    path: &Path,
+    change: impl FnOnce(&mut Vec<EncodeProfile>) -> Result<(), String>,
+) -> Result<Vec<EncodeProfile>, String> {
+    let _guard = PROFILES_LOCK.lock().map_err(|err| err.to_string())?;
+    let mut profiles = read_profiles(path)?;
+    change(&mut profiles)?;
+    write_profiles(path, &profiles)?;
+    Ok(profiles)
+}
+
+fn position(profiles: &[EncodeProfile], name: &str) -> Result<usize, String> {
+    profiles
+        .iter()
+        .position(|profile| profile.name.eq_ignore_ascii_case(name.trim()))
+        .ok_or_else(|| format!("No profile named \"{name}\"."))
+}
+
+fn normalized(mut profile: EncodeProfile) -> Result<EncodeProfile, String> {
+    profile.name = profile.name.trim().to_string();
+    profile
+        .validate()
+        .map_err(|errors| format!("Profile \"{}\": {errors}", profile.name))?;
+    Ok(profile)
+}
+
+fn is_json(path: &Path) -> bool {
+    path.extension()
+        .and_then(|ext| ext.to_str())
+        .map(|ext| ext.eq_ignore_ascii_case("json"))
+        .unwrap_or(false)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use nanoid::nanoid;
+    use serde_json::json;
+
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            Self(std::env::temp_dir().join(format!("compresso-profiles-{}", nanoid!())))
+        }
+
+        fn path(&self, name: &str) -> PathBuf {
+            self.0.join(name)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn profile(name: &str, quality: u16) -> EncodeProfile {
+        serde_json::from_value(json!({
+            "name": name,
+            "container": "mp4",
+            "videoCodec": "h265",
+            "quality": quality
+        }))
+        .unwrap()
+    }
+
+    fn names(profiles: &[EncodeProfile]) -> Vec<&str> {
+        profiles
+            .iter()
+            .map(|profile| profile.name.as_str())
+            .collect()
+    }
+
+    #[test]
+    fn missing_file_has_no_profiles() {
+        let dir = TempDir::new();
+
+        assert!(read_profiles(&dir.path(PROFILES_FILE_NAME))
+            .unwrap()
+            .is_empty());
+        assert_eq!(
+            load_profile(&dir.path(PROFILES_FILE_NAME), "web").unwrap_err(),
+            format!(
+                "No profile named \"web\", {} has no profiles.",
+                dir.path(PROFILES_FILE_NAME).display()
+            )
+        );
+    }
+
+    #[test]
+    fn created_profiles_are_saved_and_loaded() {
+        let dir = TempDir::new();
+        let path = dir.path(PROFILES_FILE_NAME);
+
+        create_profile(&path, profile("  Web ", 60)).unwrap();
+        create_profile(&path, profile("Archive", 90)).unwrap();
+
+        assert!(!path.with_extension("tmp").exists());
+        assert_eq!(names(&read_profiles(&path).unwrap()), ["Web", "Archive"]);
+        assert_eq!(load_profile(&path, "web").unwrap(), profile("Web", 60));
+        assert_eq!(
+            load_profile(&path, "mobile").unwrap_err(),
+            "No profile named \"mobile\". Available: Web, Archive"
+        );
+    }
+
+    #[test]
+    fn json_files_are_read_and_written_as_json() {
+        let dir = TempDir::new();
+        let path = dir.path("profiles.json");
+
+        write_profiles(&path, &[profile("Web", 60)]).unwrap();
+
+        let json: serde_json::Value =
+            serde_json::from_str(&fs::read_to_string(&path).unwrap()).unwrap();
+        assert_eq!(json["profiles"][0]["videoCodec"], "h265");
+        assert_eq!(read_profiles(&path).unwrap(), [profile("Web", 60)]);
+    }
+
+    #[test]
+    fn invalid_files_are_reported() {
+        let dir = TempDir::new();
+        let path = dir.path(PROFILES_FILE_NAME);
+        fs::create_dir_all(&dir.0).unwrap();
+        fs::write(&path, "profiles = 1").unwrap();
+
+        assert!(read_profiles(&path)
+            .unwrap_err()
+            .starts_with("Invalid profiles file"));
+    }
+
+    #[test]
+    fn names_must_be_unique_and_not_empty() {
+        let dir = TempDir::new();
+        let path = dir.path(PROFILES_FILE_NAME);
+        create_profile(&path, profile("Web", 60)).unwrap();
+
+        assert_eq!(
+            create_profile(&path, profile("WEB", 70)).unwrap_err(),
+            "A profile named \"WEB\" already exists."
+        );
+        assert_eq!(
+            create_profile(&path, profile(" ", 70)).unwrap_err(),
+            "Profile \"\": Invalid options. name: must not be empty"
+        );
+        assert!(create_profile(&path, profile("Sharp", 150))
+            .unwrap_err()
+            .contains("quality: must be between 0 and 100"));
+        assert_eq!(names(&read_profiles(&path).unwrap()), ["Web"]);
+    }
+
+    #[test]
+    fn updates_may_rename_but_not_collide() {
+        let dir = TempDir::new();
+        let path = dir.path(PROFILES_FILE_NAME);
+        create_profile(&path, profile("Web", 60)).unwrap();
+        create_profile(&path, profile("Archive", 90)).unwrap();
+
+        update_profile(&path, "web", profile("web", 65)).unwrap();
+        update_profile(&path, "Web", profile("Mobile", 50)).unwrap();
+        assert_eq!(
+            update_profile(&path, "Mobile", profile("archive", 50)).unwrap_err(),
+            "A profile named \"archive\" already exists."
+        );
+        assert_eq!(
+            update_profile(&path, "Web", profile("Web", 50)).unwrap_err(),
+            "No profile named \"Web\"."
+        );
+        assert_eq!(
+            read_profiles(&path).unwrap(),
+            [profile("Mobile", 50), profile("Archive", 90)]
+        );
+    }
+
+    #[test]
+    fn deleted_profiles_are_gone() {
+        let dir = TempDir::new();
+        let path = dir.path(PROFILES_FILE_NAME);
+        create_profile(&path, profile("Web", 60)).unwrap();
+        create_profile(&path, profile("Archive", 90)).unwrap();
+
+        assert_eq!(names(&delete_profile(&path, "WEB").unwrap()), ["Archive"]);
+        assert_eq!(names(&read_profiles(&path).unwrap()), ["Archive"]);
+        assert!(delete_profile(&path, "Web").is_err());
+    }
+
+    #[test]
+    fn imports_replace_profiles_with_the_same_name() {
+        let dir = TempDir::new();
+        let path = dir.path(PROFILES_FILE_NAME);
+        let shared = dir.path("shared.json");
+        create_profile(&path, profile("Web", 60)).unwrap();
+        write_profiles(&shared, &[profile("web", 70), profile("Archive", 90)]).unwrap();
+
+        let profiles = import_profiles(&path, &shared).unwrap();
+        assert_eq!(profiles, [profile("web", 70), profile("Archive", 90)]);
+
+        let exported = dir.path("exported.toml");
+        export_profiles(&path, &exported, &[String::from("archive")]).unwrap();
+        assert_eq!(read_profiles(&exported).unwrap(), [profile("Archive", 90)]);
+        assert!(import_profiles(&path, &dir.path("missing.toml")).is_err());
+    }
+}
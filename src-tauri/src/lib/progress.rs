@@ -0,0 +1,321 @@
+use crate::domain::EncodeProgress;
+
+/// Values of one `-progress` block. ffmpeg writes `key=value` lines and ends
+/// every block with `progress=continue` or `progress=end`.
+#[derive(Clone, Debug, Default)]
+pub struct ProgressSample {
+    pub out_time_us: Option<u64>,
+    pub frame: Option<u64>,
+    pub fps: Option<f64>,
+    pub bitrate_kbps: Option<f64>,
+    pub total_size: Option<u64>,
+    pub speed: Option<f64>,
+    pub is_end: bool,
+}
+
+/// Collects `-progress` lines into samples.
+#[derive(Default)]
+pub struct ProgressParser {
+    sample: ProgressSample,
+}
+
+impl ProgressParser {
+    /// Feeds one stdout line, returning the sample once its block is complete.
+    pub fn push_line(&mut self, line: &str) -> Option<ProgressSample> {
+        let (key, value) = line.trim().split_once('=')?;
+        let value = value.trim();
+        let sample = &mut self.sample;
+        match key {
+            "out_time_us" => sample.out_time_us = value.parse().ok(),
+            // Older builds only print `out_time_ms`, which is in microseconds too.
+            "out_time_ms" if sample.out_time_us.is_none() => {
+                sample.out_time_us = value.parse().ok()
+            }
+            "out_time" if sample.out_time_us.is_none() => {
+                sample.out_time_us =
+                    parse_timestamp_seconds(value).map(|seconds| (seconds * 1_000_000.0) as u64)
+            }
+            "frame" => sample.frame = value.parse().ok(),
+            "fps" => sample.fps = value.parse().ok(),
+            "bitrate" => {
+                sample.bitrate_kbps = value.trim_end_matches("kbits/s").trim().parse().ok()
+            }
+            "total_size" => sample.total_size = value.parse().ok(),
+            "speed" => sample.speed = value.trim_end_matches('x').trim().parse().ok(),
+            "progress" => {
+                let mut sample = std::mem::take(&mut self.sample);
+                sample.is_end = value == "end";
+                return Some(sample);
+            }
+            _ => {}
+        }
+        None
+    }
+}
+
+/// Turns samples of one pass into progress of the whole encode. Each pass
+/// reads the full source, so an encode of `total_passes` passes covers the
+/// source duration that many times.
+#[derive(Clone, Copy, Debug)]
+pub struct ProgressTracker {
+    pub duration_seconds: Option<f64>,
+    pub pass: u8,
+    pub total_passes: u8,
+}
+
+impl ProgressTracker {
+    pub fn single(duration_seconds: Option<f64>) -> Self {
+        Self {
+            duration_seconds,
+            pass: 1,
+            total_passes: 1,
+        }
+    }
+
+    pub fn pass(duration_seconds: Option<f64>, pass: u8, total_passes: u8) -> Self {
+        Self {
+            duration_seconds,
+            pass,
+            total_passes,
+        }
+    }
+
+    /// Position of the sample in the current pass, in seconds.
+    fn position_seconds(&self, sample: &ProgressSample) -> Option<f64> {
+        let position = sample.out_time_us? as f64 / 1_000_000.0;
+        match self.duration_seconds {
+            Some(_) if sample.is_end => self.duration_seconds,
+            Some(duration) => Some(position.min(duration)),
+            None => Some(position),
+        }
+    }
+
+    /// Completion over all passes, 0-1.
+    fn fraction(&self, sample: &ProgressSample) -> Option<f64> {
+        let duration = self.duration_seconds.filter(|duration| *duration > 0.0)?;
+        let completed = f64::from(self.pass.saturating_sub(1)) * duration;
+        let position = self.position_seconds(sample)?;
+        Some(((completed + position) / (f64::from(self.total_passes.max(1)) * duration)).min(1.0))
+    }
+
+    /// Source position scaled so that the last pass ends at the source duration.
+    pub fn overall_seconds(&self, sample: &ProgressSample) -> Option<f64> {
+        match self.duration_seconds {
+            Some(duration) => self.fraction(sample).map(|fraction| fraction * duration),
+            None => self.position_seconds(sample),
+        }
+    }
+
+    pub fn report(&self, sample: &ProgressSample) -> EncodeProgress {
+        let fraction = self.fraction(sample);
+        let is_output_pass = self.pass >= self.total_passes;
+
+        let eta_seconds = match (self.duration_seconds, fraction, sample.speed) {
+            (Some(duration), Some(fraction), Some(speed)) if speed > 0.0 => {
+                let remaining = (1.0 - fraction) * duration * f64::from(self.total_passes.max(1));
+                Some(remaining / speed)
+            }
+            _ => None,
+        };
+
+        let current_size = sample.total_size.filter(|_| is_output_pass);
+        let projected_size = match (
+            current_size,
+            self.duration_seconds,
+            self.position_seconds(sample),
+        ) {
+            (Some(size), _, _) if sample.is_end => Some(size),
+            (Some(size), Some(duration), Some(position)) if size > 0 && position > 0.0 => {
+                Some((size as f64 * duration / position) as u64)
+            }
+            _ => None,
+        };
+
+        EncodeProgress {
+            percentage: fraction.map(|fraction| fraction * 100.0),
+            eta_seconds,
+            current_size,
+            projected_size,
+            frame: sample.frame,
+            fps: sample.fps,
+            bitrate_kbps: sample.bitrate_kbps,
+            speed: sample.speed,
+            is_finished: sample.is_end && is_output_pass,
+        }
+    }
+}
+
+/// Parses `HH:MM:SS.ffffff` into seconds.
+fn parse_timestamp_seconds(value: &str) -> Option<f64> {
+    let mut parts = value.split(':');
+    let hours = parts.next()?.trim().parse::<f64>().ok()?;
+    let minutes = parts.next()?.trim().parse::<f64>().ok()?;
+    let seconds = parts.next()?.trim().parse::<f64>().ok()?;
+    if parts.next().is_some() || hours < 0.0 {
+        return None;
+    }
+    Some((hours * 3600.0) + (minutes * 60.0) + seconds)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    /// Two blocks of `-progress pipe:1` output, the first written before any
+    /// frame was encoded.
+    const PROGRESS: &str = "\
+frame=0
+fps=0.00
+stream_0_0_q=0.0
+bitrate=N/A
+total_size=N/A
+out_time_us=N/A
+out_time_ms=N/A
+out_time=N/A
+dup_frames=0
+drop_frames=0
+speed=N/A
+progress=continue
+frame=60
+fps=30.00
+stream_0_0_q=28.0
+bitrate= 819.2kbits/s
+total_size=256000
+out_time_us=2500000
+out_time_ms=2500000
+out_time=00:00:02.500000
+dup_frames=0
+drop_frames=0
+speed=2.00x
+progress=continue
+";
+
+    fn parse(output: &str) -> Vec<ProgressSample> {
+        let mut parser = ProgressParser::default();
+        output
+            .lines()
+            .filter_map(|line| parser.push_line(line))
+            .collect()
+    }
+
+    fn sample(out_time_us: u64, total_size: u64, speed: f64, is_end: bool) -> ProgressSample {
+        ProgressSample {
+            out_time_us: Some(out_time_us),
+            total_size: Some(total_size),
+            speed: Some(speed),
+            is_end,
+            ..ProgressSample::default()
+        }
+    }
+
+    #[test]
+    fn blocks_become_samples() {
+        let samples = parse(PROGRESS);
+
+        assert_eq!(samples.len(), 2);
+        let sample = &samples[1];
+        assert_eq!(sample.out_time_us, Some(2_500_000));
+        assert_eq!(sample.frame, Some(60));
+        assert_eq!(sample.fps, Some(30.0));
+        assert_eq!(sample.bitrate_kbps, Some(819.2));
+        assert_eq!(sample.total_size, Some(256_000));
+        assert_eq!(sample.speed, Some(2.0));
+        assert!(!sample.is_end);
+    }
+
+    #[test]
+    fn not_available_values_are_empty() {
+        let sample = &parse(PROGRESS)[0];
+
+        assert_eq!(sample.frame, Some(0));
+        assert_eq!(sample.out_time_us, None);
+        assert_eq!(sample.bitrate_kbps, None);
+        assert_eq!(sample.total_size, None);
+        assert_eq!(sample.speed, None);
+    }
+
+    #[test]
+    fn values_do_not_leak_into_the_next_block() {
+        let samples = parse("frame=10\nprogress=continue\nspeed=1.5x\nprogress=end\n");
+
+        assert_eq!(samples[1].frame, None);
+        assert_eq!(samples[1].speed, Some(1.5));
+        assert!(samples[1].is_end);
+    }
+
+    #[test]
+    fn older_time_keys_are_fallbacks() {
+        let samples = parse(
+            "out_time_ms=1500000\nprogress=continue\nout_time=01:02:03.500000\nprogress=continue\n",
+        );
+
+        assert_eq!(samples[0].out_time_us, Some(1_500_000));
+        assert_eq!(samples[1].out_time_us, Some(3_723_500_000));
+        assert_eq!(parse_timestamp_seconds("1:2"), None);
+    }
+
+    #[test]
+    fn report_projects_percentage_eta_and_size() {
+        let progress =
+            ProgressTracker::single(Some(10.0)).report(&sample(2_500_000, 1000, 2.0, false));
+
+        assert_eq!(progress.percentage, Some(25.0));
+        assert_eq!(progress.eta_seconds, Some(3.75));
+        assert_eq!(progress.current_size, Some(1000));
+        assert_eq!(progress.projected_size, Some(4000));
+        assert!(!progress.is_finished);
+    }
+
+    #[test]
+    fn end_of_output_pass_is_finished() {
+        let progress =
+            ProgressTracker::single(Some(10.0)).report(&sample(9_960_000, 3900, 1.0, true));
+
+        assert_eq!(progress.percentage, Some(100.0));
+        assert_eq!(progress.projected_size, Some(3900));
+        assert!(progress.is_finished);
+    }
+
+    #[test]
+    fn passes_share_the_percentage() {
+        let first = ProgressTracker::pass(Some(10.0), 1, 2);
+        let second = ProgressTracker::pass(Some(10.0), 2, 2);
+        let halfway = sample(5_000_000, 1000, 1.0, false);
+
+        let progress = first.report(&halfway);
+        assert_eq!(progress.percentage, Some(25.0));
+        assert_eq!(progress.eta_seconds, Some(15.0));
+        // The analysis pass writes no output.
+        assert_eq!(progress.current_size, None);
+        assert_eq!(progress.projected_size, None);
+        assert!(!first.report(&sample(10_000_000, 0, 1.0, true)).is_finished);
+
+        assert_eq!(second.report(&halfway).percentage, Some(75.0));
+        assert_eq!(second.overall_seconds(&halfway), Some(7.5));
+    }
+
+    #[test]
+    fn position_is_clamped_to_the_duration() {
+        let progress =
+            ProgressTracker::single(Some(10.0)).report(&sample(12_000_000, 1000, 1.0, false));
+
+        assert_eq!(progress.percentage, Some(100.0));
+        assert_eq!(progress.eta_seconds, Some(0.0));
+    }
+
+    #[test]
+    fn missing_duration_reports_only_raw_values() {
+        let tracker = ProgressTracker::single(None);
+        let progress = tracker.report(&sample(2_500_000, 1000, 2.0, false));
+
+        assert_eq!(progress.percentage, None);
+        assert_eq!(progress.eta_seconds, None);
+        assert_eq!(progress.projected_size, None);
+        assert_eq!(progress.current_size, Some(1000));
+        assert_eq!(progress.speed, Some(2.0));
+        assert_eq!(
+            tracker.overall_seconds(&sample(2_500_000, 1000, 2.0, false)),
+            Some(2.5)
+        );
+    }
+}
@@ -0,0 +1,659 @@
+use nanoid::nanoid;
+use serde::Serialize;
+use std::{
+    collections::HashSet,
+    fs,
+    path::{Path, PathBuf},
+    sync::{Mutex, MutexGuard},
+};
+use tauri::{AppHandle, Emitter, Manager};
+
+use crate::{
+    domain::{
+        CancelInProgressCompressionPayload, CompressionJob, CompressionQueueSnapshot,
+        CompressionResult, CustomEvents, FfmpegError, FfmpegErrorKind, JobStatus,
+        NewCompressionJob,
+    },
+    ffmpeg::FFMPEG,
+    fs::{copy_file, delete_file, delete_stale_files},
+};
+
+pub const MAX_CONCURRENCY: usize = 8;
+
+const QUEUE_FILE_NAME: &str = "queue.json";
+
+struct QueueState {
+    jobs: Vec<CompressionJob>,
+    concurrency: usize,
+    is_paused: bool,
+    /// Running jobs asked to stop, checked again when they finish in case the
+    /// cancel event arrived before ffmpeg was started.
+    cancel_requested: HashSet<String>,
+    /// Where the queue is saved after every change. Not saved when missing.
+    store_path: Option<PathBuf>,
+}
+
+impl QueueState {
+    fn snapshot(&self) -> CompressionQueueSnapshot {
+        CompressionQueueSnapshot {
+            jobs: self.jobs.clone(),
+            concurrency: self.concurrency,
+            is_paused: self.is_paused,
+        }
+    }
+
+    fn save(&self) {
+        let Some(store_path) = &self.store_path else {
+            return;
+        };
+        let result = serde_json::to_vec_pretty(&self.snapshot())
+            .map_err(|err| err.to_string())
+            .and_then(|json| {
+                // Written next to the real file first so a crash never leaves half a queue.
+                let temp_path = store_path.with_extension("json.tmp");
+                fs::write(&temp_path, json)
+                    .and_then(|_| fs::rename(&temp_path, store_path))
+                    .map_err(|err| err.to_string())
+            });
+        if let Err(err) = result {
+            log::error!("[queue] queue could not be saved {}", err);
+        }
+    }
+}
+
+/// Compression jobs run by the backend, managed as Tauri state so a batch
+/// survives frontend reloads and, through `queue.json`, app restarts.
+pub struct CompressionQueue(Mutex<QueueState>);
+
+impl Default for CompressionQueue {
+    fn default() -> Self {
+        Self(Mutex::new(QueueState {
+            jobs: Vec::new(),
+            concurrency: 1,
+            is_paused: false,
+            cancel_requested: HashSet::new(),
+            store_path: None,
+        }))
+    }
+}
+
+impl CompressionQueue {
+    /// Loads the queue saved in the app data dir. Jobs that were queued or
+    /// running when the app stopped come back as interrupted, and whatever
+    /// they had written to `assets/` or next to their output path is discarded.
+    pub fn restore(app_data_dir: &Path) -> Self {
+        let store_path = app_data_dir.join(QUEUE_FILE_NAME);
+        let mut can_save = true;
+        let saved = match fs::read(&store_path) {
+            Ok(json) => match serde_json::from_slice::<CompressionQueueSnapshot>(&json) {
+                Ok(saved) => Some(saved),
+                Err(err) => {
+                    log::error!("[queue] saved queue could not be read {}", err);
+                    // Kept aside, the empty queue saved below would replace it.
+                    // Without a backup the queue is not saved at all.
+                    let backup_path = store_path.with_extension("json.bak");
+                    if let Err(err) = fs::rename(&store_path, &backup_path) {
+                        log::error!("[queue] saved queue could not be backed up {}", err);
+                        can_save = false;
+                    }
+                    None
+                }
+            },
+            Err(_) => None,
+        };
+
+        let mut state = QueueState {
+            jobs: Vec::new(),
+            concurrency: 1,
+            is_paused: false,
+            cancel_requested: HashSet::new(),
+            store_path: if can_save { Some(store_path) } else { None },
+        };
+        if let Some(saved) = saved {
+            state.concurrency = saved.concurrency.clamp(1, MAX_CONCURRENCY);
+            state.jobs = saved.jobs;
+        }
+
+        let assets_dir = app_data_dir.join("assets");
+        for job in state.jobs.iter_mut() {
+            if matches!(job.status, JobStatus::Queued | JobStatus::Running) {
+                if job.status == JobStatus::Running {
+                    discard_partial_outputs(&assets_dir, job);
+                }
+                job.status = JobStatus::Interrupted;
+            }
+        }
+        state.save();
+
+        Self(Mutex::new(state))
+    }
+
+    fn lock(&self) -> Result<MutexGuard<'_, QueueState>, String> {
+        self.0.lock().map_err(|err| err.to_string())
+    }
+
+    pub fn snapshot(&self) -> Result<CompressionQueueSnapshot, String> {
+        Ok(self.lock()?.snapshot())
+    }
+}
+
+/// Name an output is copied under before it is renamed to `output_path`, so
+/// an interrupted copy never looks like a finished output.
+fn partial_output_path(output_path: &str) -> String {
+    format!("{output_path}.part")
+}
+
+/// Removes the output and two-pass stats files of a job that never finished,
+/// all of which are named after the job id, and its partial copy.
+fn discard_partial_outputs(assets_dir: &Path, job: &CompressionJob) {
+    if let Some(output_path) = &job.output_path {
+        let partial_path = partial_output_path(output_path);
+        if Path::new(&partial_path).exists() {
+            match fs::remove_file(&partial_path) {
+                Ok(_) => log::info!("[queue] discarded partial output {}", partial_path),
+                Err(err) => log::error!("[queue] partial output could not be deleted {}", err),
+            }
+        }
+    }
+    let Ok(entries) = fs::read_dir(assets_dir) else {
+        return;
+    };
+    for entry in entries.flatten() {
+        let file_name = entry.file_name().to_string_lossy().to_string();
+        let is_partial = file_name
+            .strip_prefix(&job.id)
+            .is_some_and(|rest| rest.starts_with('.') || rest.starts_with('-'));
+        if is_partial {
+            match fs::remove_file(entry.path()) {
+                Ok(_) => log::info!("[queue] discarded partial output {}", file_name),
+                Err(err) => log::error!("[queue] partial output could not be deleted {}", err),
+            }
+        }
+    }
+}
+
+/// Adds jobs to the end of the queue and starts them when slots are free.
+pub fn enqueue(
+    app: &AppHandle,
+    new_jobs: Vec<NewCompressionJob>,
+) -> Result<Vec<CompressionJob>, String> {
+    for new_job in &new_jobs {
+        new_job
+            .options
+            .validate()
+            .map_err(|errors| format!("{}: {}", new_job.video_path, errors))?;
+    }
+
+    let queue = app.state::<CompressionQueue>();
+    let added = {
+        let mut state = queue.lock()?;
+        let mut added: Vec<CompressionJob> = Vec::with_capacity(new_jobs.len());
+        for new_job in new_jobs {
+            let id = new_job.id.unwrap_or_else(|| nanoid!());
+            let is_duplicate = state
+                .jobs
+                .iter()
+                .chain(added.iter())
+                .any(|job| job.id == id && !job.status.is_finished());
+            if is_duplicate {
+                return Err(format!("Job {id} is already in the queue."));
+            }
+            added.push(CompressionJob {
+                id,
+                video_path: new_job.video_path,
+                output_path: new_job.output_path,
+                options: new_job.options,
+                status: JobStatus::Queued,
+                attempts: 0,
+                result: None,
+                error: None,
+            });
+        }
+        // A finished job with the same id is replaced by the new one.
+        state
+            .jobs
+            .retain(|job| !added.iter().any(|new_job| new_job.id == job.id));
+        state.jobs.extend(added.iter().cloned());
+        added
+    };
+
+    emit_snapshot(app);
+    schedule(app);
+    Ok(added)
+}
+
+/// Moves a job to `index`, which decides when a queued job starts.
+pub fn reorder(app: &AppHandle, job_id: &str, index: usize) -> Result<(), String> {
+    let queue = app.state::<CompressionQueue>();
+    {
+        let mut state = queue.lock()?;
+        let position = find_job(&state, job_id)?;
+        let job = state.jobs.remove(position);
+        let index = index.min(state.jobs.len());
+        state.jobs.insert(index, job);
+    }
+    emit_snapshot(app);
+    Ok(())
+}
+
+/// A paused queue lets running jobs finish but starts no new ones.
+pub fn set_paused(app: &AppHandle, is_paused: bool) -> Result<(), String> {
+    let queue = app.state::<CompressionQueue>();
+    queue.lock()?.is_paused = is_paused;
+    emit_snapshot(app);
+    if !is_paused {
+        schedule(app);
+    }
+    Ok(())
+}
+
+pub fn set_concurrency(app: &AppHandle, concurrency: usize) -> Result<(), String> {
+    if !(1..=MAX_CONCURRENCY).contains(&concurrency) {
+        return Err(format!(
+            "Concurrency must be between 1 and {MAX_CONCURRENCY}."
+        ));
+    }
+    let queue = app.state::<CompressionQueue>();
+    queue.lock()?.concurrency = concurrency;
+    emit_snapshot(app);
+    schedule(app);
+    Ok(())
+}
+
+/// Drops a queued job or stops a running one.
+pub fn cancel(app: &AppHandle, job_id: &str) -> Result<(), String> {
+    let queue = app.state::<CompressionQueue>();
+    let mut state = queue.lock()?;
+    let position = find_job(&state, job_id)?;
+    match state.jobs[position].status {
+        JobStatus::Queued | JobStatus::Interrupted => {
+            state.jobs[position].status = JobStatus::Cancelled;
+            let job = state.jobs[position].clone();
+            drop(state);
+            job_updated(app, job);
+        }
+        JobStatus::Running => {
+            state.cancel_requested.insert(String::from(job_id));
+            drop(state);
+            // Handled by the listener of the running ffmpeg pass.
+            app.emit(
+                CustomEvents::CancelInProgressCompression.as_ref(),
+                CancelInProgressCompressionPayload {
+                    video_id: String::from(job_id),
+                },
+            )
+            .map_err(|err| err.to_string())?;
+        }
+        _ => return Err(String::from("Job has already finished.")),
+    }
+    Ok(())
+}
+
+/// Queues a failed, cancelled or interrupted job again.
+pub fn retry(app: &AppHandle, job_id: &str) -> Result<(), String> {
+    let queue = app.state::<CompressionQueue>();
+    let job = {
+        let mut state = queue.lock()?;
+        let position = find_job(&state, job_id)?;
+        let job = &mut state.jobs[position];
+        if !matches!(
+            job.status,
+            JobStatus::Failed | JobStatus::Cancelled | JobStatus::Interrupted
+        ) {
+            return Err(String::from(
+                "Only failed, cancelled or interrupted jobs can be retried.",
+            ));
+        }
+        job.status = JobStatus::Queued;
+        job.result = None;
+        job.error = None;
+        job.clone()
+    };
+    job_updated(app, job);
+    schedule(app);
+    Ok(())
+}
+
+/// Queues every job interrupted by the last shutdown again, in queue order.
+pub fn resume_interrupted(app: &AppHandle) -> Result<(), String> {
+    let queue = app.state::<CompressionQueue>();
+    let resumed: Vec<CompressionJob> = {
+        let mut state = queue.lock()?;
+        state
+            .jobs
+            .iter_mut()
+            .filter(|job| job.status == JobStatus::Interrupted)
+            .map(|job| {
+                job.status = JobStatus::Queued;
+                job.clone()
+            })
+            .collect()
+    };
+    for job in resumed {
+        job_updated(app, job);
+    }
+    schedule(app);
+    Ok(())
+}
+
+/// Removes completed, failed and cancelled jobs.
+pub fn clear_finished(app: &AppHandle) -> Result<(), String> {
+    let queue = app.state::<CompressionQueue>();
+    queue.lock()?.jobs.retain(|job| !job.status.is_finished());
+    emit_snapshot(app);
+    Ok(())
+}
+
+fn find_job(state: &QueueState, job_id: &str) -> Result<usize, String> {
+    state
+        .jobs
+        .iter()
+        .position(|job| job.id == job_id)
+        .ok_or_else(|| format!("Job {job_id} does not exist."))
+}
+
+/// Starts queued jobs in queue order until the concurrency limit is reached.
+fn schedule(app: &AppHandle) {
+    let queue = app.state::<CompressionQueue>();
+    let started: Vec<CompressionJob> = match queue.lock() {
+        Ok(mut state) => {
+            if state.is_paused {
+                return;
+            }
+            let running = state
+                .jobs
+                .iter()
+                .filter(|job| job.status == JobStatus::Running)
+                .count();
+            let free_slots = state.concurrency.saturating_sub(running);
+            state
+                .jobs
+                .iter_mut()
+                .filter(|job| job.status == JobStatus::Queued)
+                .take(free_slots)
+                .map(|job| {
+                    job.status = JobStatus::Running;
+                    job.attempts += 1;
+                    job.clone()
+                })
+                .collect()
+        }
+        Err(err) => {
+            log::error!("[queue] state could not be locked {}", err);
+            return;
+        }
+    };
+
+    for job in started {
+        job_updated(app, job.clone());
+        tauri::async_runtime::spawn(run_job(app.clone(), job));
+    }
+}
+
+async fn run_job(app: AppHandle, job: CompressionJob) {
+    let outcome = compress(&app, &job).await;
+
+    let queue = app.state::<CompressionQueue>();
+    let cancel_requested = match queue.lock() {
+        Ok(mut state) => state.cancel_requested.remove(&job.id),
+        Err(_) => false,
+    };
+    let outcome = match outcome {
+        Ok(result) if cancel_requested => {
+            let _ = delete_file(&result.file_path).await;
+            Err(FfmpegError::cancelled())
+        }
+        outcome => outcome,
+    };
+
+    let updated = match queue.lock() {
+        Ok(mut state) => state
+            .jobs
+            .iter_mut()
+            .find(|entry| entry.id == job.id)
+            .map(|entry| {
+                match outcome {
+                    Ok(result) => {
+                        entry.status = JobStatus::Completed;
+                        entry.result = Some(result);
+                    }
+                    Err(err) if err.kind == FfmpegErrorKind::Cancelled => {
+                        entry.status = JobStatus::Cancelled;
+                    }
+                    Err(err) => {
+                        entry.status = JobStatus::Failed;
+                        entry.error = Some(err);
+                    }
+                }
+                entry.clone()
+            }),
+        Err(err) => {
+            log::error!("[queue] state could not be locked {}", err);
+            None
+        }
+    };
+
+    if let Some(job) = updated {
+        job_updated(&app, job);
+    }
+    schedule(&app);
+}
+
+async fn compress(app: &AppHandle, job: &CompressionJob) -> Result<CompressionResult, FfmpegError> {
+    let mut ffmpeg = FFMPEG::new(app)?;
+    if let Ok(files) =
+        delete_stale_files(ffmpeg.get_asset_dir().as_str(), 24 * 60 * 60 * 1000).await
+    {
+        log::debug!(
+            "[queue] Stale files deleted. Number of deleted files = {}",
+            files.len()
+        )
+    };
+
+    let result = ffmpeg
+        .compress_video(&job.video_path, Some(&job.id), &job.options)
+        .await?;
+
+    let Some(output_path) = &job.output_path else {
+        return Ok(result);
+    };
+    let keep_file_times = job.options.metadata.copy_file_times;
+    let partial_path = partial_output_path(output_path);
+    let moved = match copy_file(&result.file_path, &partial_path, keep_file_times).await {
+        Ok(_) => match tokio::fs::rename(&partial_path, output_path).await {
+            Ok(_) => delete_file(&result.file_path).await,
+            Err(err) => Err(err),
+        },
+        Err(err) => Err(err),
+    };
+    if let Err(err) = moved {
+        let _ = fs::remove_file(&partial_path);
+        let kind = match err.kind() {
+            std::io::ErrorKind::PermissionDenied => FfmpegErrorKind::PermissionDenied,
+            _ => FfmpegErrorKind::Unknown,
+        };
+        return Err(FfmpegError::new(
+            kind,
+            &format!("Could not move the compressed video to {output_path}: {err}"),
+        ));
+    }
+
+    Ok(CompressionResult {
+        file_name: Path::new(output_path)
+            .file_name()
+            .map(|name| name.to_string_lossy().to_string())
+            .unwrap_or_default(),
+        file_path: output_path.clone(),
+    })
+}
+
+/// Saves the queue and sends the whole snapshot to the frontend.
+fn emit_snapshot(app: &AppHandle) {
+    match app.state::<CompressionQueue>().lock() {
+        Ok(state) => {
+            state.save();
+            let snapshot = state.snapshot();
+            drop(state);
+            emit(app, CustomEvents::CompressionQueueUpdated, snapshot);
+        }
+        Err(err) => log::error!("[queue] snapshot could not be taken {}", err),
+    }
+}
+
+/// Saves the queue and sends the changed job to the frontend.
+fn job_updated(app: &AppHandle, job: CompressionJob) {
+    match app.state::<CompressionQueue>().lock() {
+        Ok(state) => state.save(),
+        Err(err) => log::error!("[queue] state could not be locked {}", err),
+    }
+    emit(app, CustomEvents::CompressionJobUpdated, job);
+}
+
+fn emit<S: Serialize + Clone>(app: &AppHandle, event: CustomEvents, payload: S) {
+    if let Some(window) = app.get_webview_window("main") {
+        window.emit(event.as_ref(), payload).ok();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use serde_json::json;
+
+    /// Fresh app data dir with an `assets/` folder, removed on drop.
+    struct TempDir(PathBuf);
+
+    impl TempDir {
+        fn new() -> Self {
+            let dir = std::env::temp_dir().join(format!("compresso-queue-{}", nanoid!()));
+            fs::create_dir_all(dir.join("assets")).unwrap();
+            Self(dir)
+        }
+    }
+
+    impl Drop for TempDir {
+        fn drop(&mut self) {
+            let _ = fs::remove_dir_all(&self.0);
+        }
+    }
+
+    fn job(id: &str, status: &str, output_path: Option<&Path>) -> serde_json::Value {
+        json!({
+            "id": id,
+            "videoPath": "in.mp4",
+            "outputPath": output_path,
+            "options": { "container": "mp4" },
+            "status": status,
+            "attempts": 1,
+            "result": null,
+            "error": null
+        })
+    }
+
+    fn save_queue(dir: &Path, jobs: Vec<serde_json::Value>) {
+        let queue = json!({ "jobs": jobs, "concurrency": 20, "isPaused": false });
+        fs::write(dir.join(QUEUE_FILE_NAME), queue.to_string()).unwrap();
+    }
+
+    fn statuses(queue: &CompressionQueue) -> Vec<(String, JobStatus)> {
+        queue
+            .snapshot()
+            .unwrap()
+            .jobs
+            .into_iter()
+            .map(|job| (job.id, job.status))
+            .collect()
+    }
+
+    #[test]
+    fn missing_queue_file_restores_an_empty_queue() {
+        let dir = TempDir::new();
+
+        let queue = CompressionQueue::restore(&dir.0);
+
+        let snapshot = queue.snapshot().unwrap();
+        assert!(snapshot.jobs.is_empty());
+        assert_eq!(snapshot.concurrency, 1);
+        assert!(dir.0.join(QUEUE_FILE_NAME).exists());
+    }
+
+    #[test]
+    fn unfinished_jobs_come_back_interrupted() {
+        let dir = TempDir::new();
+        save_queue(
+            &dir.0,
+            vec![
+                job("queued", "queued", None),
+                job("running", "running", None),
+                job("done", "completed", None),
+                job("failed", "failed", None),
+            ],
+        );
+
+        let queue = CompressionQueue::restore(&dir.0);
+
+        assert_eq!(
+            statuses(&queue),
+            [
+                (String::from("queued"), JobStatus::Interrupted),
+                (String::from("running"), JobStatus::Interrupted),
+                (String::from("done"), JobStatus::Completed),
+                (String::from("failed"), JobStatus::Failed),
+            ]
+        );
+        assert_eq!(queue.snapshot().unwrap().concurrency, MAX_CONCURRENCY);
+        let saved: CompressionQueueSnapshot =
+            serde_json::from_slice(&fs::read(dir.0.join(QUEUE_FILE_NAME)).unwrap()).unwrap();
+        assert_eq!(saved.jobs[0].status, JobStatus::Interrupted);
+    }
+
+    #[test]
+    fn partial_outputs_of_running_jobs_are_discarded() {
+        let dir = TempDir::new();
+        let output_path = dir.0.join("out.mp4");
+        let partial_path = PathBuf::from(partial_output_path(&output_path.display().to_string()));
+        let assets = dir.0.join("assets");
+        for file in [
+            assets.join("running.mp4"),
+            assets.join("running-passlog-0.log"),
+            assets.join("queued.mp4"),
+            assets.join("runningother.mp4"),
+            partial_path.clone(),
+        ] {
+            fs::write(file, b"partial").unwrap();
+        }
+        save_queue(
+            &dir.0,
+            vec![
+                job("running", "running", Some(&output_path)),
+                job("queued", "queued", None),
+            ],
+        );
+
+        CompressionQueue::restore(&dir.0);
+
+        assert!(!assets.join("running.mp4").exists());
+        assert!(!assets.join("running-passlog-0.log").exists());
+        assert!(!partial_path.exists());
+        // Queued jobs never started, other ids merely share a prefix.
+        assert!(assets.join("queued.mp4").exists());
+        assert!(assets.join("runningother.mp4").exists());
+    }
+
+    #[test]
+    fn unreadable_queue_file_is_backed_up() {
+        let dir = TempDir::new();
+        fs::write(dir.0.join(QUEUE_FILE_NAME), b"{ not json").unwrap();
+
+        let queue = CompressionQueue::restore(&dir.0);
+
+        assert!(queue.snapshot().unwrap().jobs.is_empty());
+        assert_eq!(
+            fs::read(dir.0.join("queue.json.bak")).unwrap(),
+            b"{ not json"
+        );
+        let saved: CompressionQueueSnapshot =
+            serde_json::from_slice(&fs::read(dir.0.join(QUEUE_FILE_NAME)).unwrap()).unwrap();
+        assert!(saved.jobs.is_empty());
+    }
+}
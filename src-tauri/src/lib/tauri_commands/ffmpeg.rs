@@ -1,24 +1,19 @@
 use crate::{
-    domain::{CompressionResult, QualityPreviewResult, VideoInfo, VideoThumbnail},
+    domain::{
+        CompressionResult, EncodeOptions, FfmpegCapabilities, FfmpegError, MediaInfo,
+        QualityPreviewResult, VideoInfo, VideoThumbnail,
+    },
     ffmpeg::{self},
     fs::delete_stale_files,
 };
-use serde_json::Value;
 
 #[tauri::command]
-#[allow(clippy::too_many_arguments)]
 pub async fn compress_video(
     app: tauri::AppHandle,
     video_path: &str,
-    convert_to_extension: &str,
-    preset_name: Option<&str>,
     video_id: Option<&str>,
-    should_mute_video: bool,
-    quality: u16,
-    dimensions: Option<(u32, u32)>,
-    fps: Option<&str>,
-    transforms_history: Option<Vec<Value>>,
-) -> Result<CompressionResult, String> {
+    options: EncodeOptions,
+) -> Result<CompressionResult, FfmpegError> {
     let mut ffmpeg = ffmpeg::FFMPEG::new(&app)?;
     if let Ok(files) =
         delete_stale_files(ffmpeg.get_asset_dir().as_str(), 24 * 60 * 60 * 1000).await
@@ -29,17 +24,7 @@ pub async fn compress_video(
         )
     };
     match ffmpeg
-        .compress_video(
-            video_path,
-            convert_to_extension,
-            preset_name,
-            video_id,
-            should_mute_video,
-            quality,
-            dimensions,
-            fps,
-            transforms_history.as_ref(),
-        )
+        .compress_video(video_path, video_id, &options)
         .await
     {
         Ok(result) => Ok(result),
@@ -51,31 +36,30 @@ pub async fn compress_video(
 pub async fn generate_video_thumbnail(
     app: tauri::AppHandle,
     video_path: &str,
-) -> Result<VideoThumbnail, String> {
+) -> Result<VideoThumbnail, FfmpegError> {
     let mut ffmpeg = ffmpeg::FFMPEG::new(&app)?;
     ffmpeg.generate_video_thumbnail(video_path).await
 }
 
 #[tauri::command]
-pub async fn get_video_info(app: tauri::AppHandle, video_path: &str) -> Result<VideoInfo, String> {
+pub async fn get_video_info(app: tauri::AppHandle, video_path: &str) -> Result<VideoInfo, FfmpegError> {
     let mut ffmpeg = ffmpeg::FFMPEG::new(&app)?;
     ffmpeg.get_video_info(video_path).await
 }
 
 #[tauri::command]
-#[allow(clippy::too_many_arguments)]
+pub async fn probe_media(app: tauri::AppHandle, video_path: &str) -> Result<MediaInfo, FfmpegError> {
+    let ffmpeg = ffmpeg::FFMPEG::new(&app)?;
+    ffmpeg.probe_media(video_path).await
+}
+
+#[tauri::command]
 pub async fn generate_quality_preview(
     app: tauri::AppHandle,
     video_path: &str,
-    convert_to_extension: &str,
-    preset_name: Option<&str>,
-    should_mute_video: bool,
-    quality: u16,
-    dimensions: Option<(u32, u32)>,
-    fps: Option<&str>,
-    transforms_history: Option<Vec<Value>>,
+    options: EncodeOptions,
     preview_seconds: Option<u16>,
-) -> Result<QualityPreviewResult, String> {
+) -> Result<QualityPreviewResult, FfmpegError> {
     let mut ffmpeg = ffmpeg::FFMPEG::new(&app)?;
     if let Ok(files) =
         delete_stale_files(ffmpeg.get_asset_dir().as_str(), 24 * 60 * 60 * 1000).await
@@ -87,16 +71,15 @@ pub async fn generate_quality_preview(
     };
 
     ffmpeg
-        .generate_quality_preview(
-            video_path,
-            convert_to_extension,
-            preset_name,
-            should_mute_video,
-            quality,
-            dimensions,
-            fps,
-            transforms_history.as_ref(),
-            preview_seconds,
-        )
+        .generate_quality_preview(video_path, &options, preview_seconds)
+        .await
+}
+
+#[tauri::command]
+pub async fn get_ffmpeg_capabilities(app: tauri::AppHandle) -> Result<FfmpegCapabilities, String> {
+    let ffmpeg = ffmpeg::FFMPEG::new(&app).map_err(|err| err.to_string())?;
+    tokio::task::spawn_blocking(move || ffmpeg.capabilities())
         .await
+        .map_err(|err| err.to_string())?
+        .map(|capabilities| (*capabilities).clone())
 }
@@ -1,5 +1,12 @@
-use crate::{domain::FileMetadata, domain::ResolveVideoFilesResult, ffmpeg, fs};
-use std::{collections::HashSet, path::Path};
+use crate::{
+    domain::{FileMetadata, RenderOutputPathRequest, ResolveVideoFilesResult, ResolvedVideoFile},
+    ffmpeg, fs,
+    naming::{self, NameContext, NameTemplate},
+};
+use std::{
+    collections::HashSet,
+    path::{Path, PathBuf},
+};
 use walkdir::WalkDir;
 
 const VIDEO_EXTENSIONS: [&str; 5] = ["mp4", "mov", "webm", "avi", "mkv"];
@@ -17,6 +24,19 @@ fn is_supported_video_file(path: &Path) -> bool {
     }
 }
 
+/// Folder of `path` relative to `base_dir`, with `/` separators.
+fn relative_dir(path: &Path, base_dir: &Path) -> String {
+    path.parent()
+        .and_then(|dir| dir.strip_prefix(base_dir).ok())
+        .map(|dir| {
+            dir.components()
+                .map(|component| component.as_os_str().to_string_lossy())
+                .collect::<Vec<_>>()
+                .join("/")
+        })
+        .unwrap_or_default()
+}
+
 #[tauri::command]
 pub async fn get_file_metadata(file_path: &str) -> Result<FileMetadata, String> {
     fs::get_file_metadata(file_path)
@@ -28,8 +48,8 @@ pub async fn get_image_dimension(image_path: &str) -> Result<(u32, u32), String>
 }
 
 #[tauri::command]
-pub async fn move_file(from: &str, to: &str) -> Result<(), String> {
-    if let Err(err) = fs::copy_file(from, to).await {
+pub async fn move_file(from: &str, to: &str, keep_file_times: bool) -> Result<(), String> {
+    if let Err(err) = fs::copy_file(from, to, keep_file_times).await {
         return Err(err.to_string());
     }
 
@@ -50,7 +70,7 @@ pub async fn delete_file(path: &str) -> Result<(), String> {
 
 #[tauri::command]
 pub async fn delete_cache(app: tauri::AppHandle) -> Result<(), String> {
-    let ffmpeg = ffmpeg::FFMPEG::new(&app)?;
+    let ffmpeg = ffmpeg::FFMPEG::new(&app).map_err(|err| err.to_string())?;
     if let Err(err) = fs::delete_stale_files(&ffmpeg.get_asset_dir(), 0).await {
         return Err(err.to_string());
     }
@@ -62,7 +82,7 @@ pub async fn resolve_video_files(
     paths: Vec<String>,
     recursive: Option<bool>,
 ) -> Result<ResolveVideoFilesResult, String> {
-    let mut files: Vec<FileMetadata> = Vec::new();
+    let mut files: Vec<ResolvedVideoFile> = Vec::new();
     let mut invalid_paths: Vec<String> = Vec::new();
     let mut skipped_paths: Vec<String> = Vec::new();
     let mut ignored_count: u64 = 0;
@@ -83,7 +103,10 @@ pub async fn resolve_video_files(
                 if !seen_paths.contains(&normalized) {
                     if let Ok(metadata) = fs::get_file_metadata(&normalized) {
                         seen_paths.insert(normalized);
-                        files.push(metadata);
+                        files.push(ResolvedVideoFile {
+                            metadata,
+                            relative_dir: String::new(),
+                        });
                     } else {
                         invalid_paths.push(path_str);
                     }
@@ -108,7 +131,10 @@ pub async fn resolve_video_files(
                             if !seen_paths.contains(&normalized) {
                                 if let Ok(metadata) = fs::get_file_metadata(&normalized) {
                                     seen_paths.insert(normalized);
-                                    files.push(metadata);
+                                    files.push(ResolvedVideoFile {
+                                        metadata,
+                                        relative_dir: relative_dir(entry_path, path),
+                                    });
                                 }
                             }
                         } else {
@@ -125,7 +151,10 @@ pub async fn resolve_video_files(
                             if !seen_paths.contains(&normalized) {
                                 if let Ok(metadata) = fs::get_file_metadata(&normalized) {
                                     seen_paths.insert(normalized);
-                                    files.push(metadata);
+                                    files.push(ResolvedVideoFile {
+                                        metadata,
+                                        relative_dir: String::new(),
+                                    });
                                 }
                             }
                         } else {
@@ -137,7 +166,7 @@ pub async fn resolve_video_files(
         }
     }
 
-    files.sort_by(|a, b| a.path.cmp(&b.path));
+    files.sort_by(|a, b| a.metadata.path.cmp(&b.metadata.path));
 
     Ok(ResolveVideoFilesResult {
         files,
@@ -146,3 +175,50 @@ pub async fn resolve_video_files(
         ignored_count,
     })
 }
+
+#[tauri::command]
+pub async fn validate_name_template(template: &str) -> Result<(), String> {
+    NameTemplate::parse(template).map(|_| ())
+}
+
+/// Output path of the `index`-th video of a batch. Returns `None` when the
+/// video should be skipped.
+#[tauri::command]
+pub async fn render_output_path(
+    app: tauri::AppHandle,
+    request: RenderOutputPathRequest,
+) -> Result<Option<String>, String> {
+    let RenderOutputPathRequest {
+        video_path,
+        output_dir,
+        template,
+        options,
+        index,
+        conflict_policy,
+        reserved,
+    } = request;
+    let template = NameTemplate::parse(&template)?;
+
+    // Only probe when the template asks for dimensions the options don't set.
+    let source_dimensions = if template.needs_dimensions() && options.dimensions.is_none() {
+        let ffmpeg = ffmpeg::FFMPEG::new(&app).map_err(|err| err.to_string())?;
+        let media_info = ffmpeg
+            .probe_media(&video_path)
+            .await
+            .map_err(|err| err.to_string())?;
+        media_info
+            .primary_video_stream()
+            .and_then(|stream| stream.display_dimensions())
+    } else {
+        None
+    };
+
+    let context = NameContext::new(Path::new(&video_path), &options, source_dimensions, index);
+    let candidate = Path::new(&output_dir).join(template.render(&context)?);
+    let reserved: HashSet<PathBuf> = reserved.into_iter().map(PathBuf::from).collect();
+
+    Ok(
+        naming::resolve_conflict(candidate, conflict_policy, &reserved)
+            .map(|path| path.display().to_string()),
+    )
+}
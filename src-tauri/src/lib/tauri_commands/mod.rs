@@ -1,4 +1,6 @@
 pub mod ffmpeg;
 pub mod file_manager;
 pub mod fs;
-pub mod system;
\ No newline at end of file
+pub mod profiles;
+pub mod queue;
+pub mod system;
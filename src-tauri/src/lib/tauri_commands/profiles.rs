@@ -0,0 +1,55 @@
+use std::path::{Path, PathBuf};
+use tauri::Manager;
+
+use crate::{
+    domain::EncodeProfile,
+    profiles::{self, PROFILES_FILE_NAME},
+};
+
+fn profiles_path(app: &tauri::AppHandle) -> Result<PathBuf, String> {
+    app.path()
+        .app_config_dir()
+        .map(|dir| dir.join(PROFILES_FILE_NAME))
+        .map_err(|err| err.to_string())
+}
+
+#[tauri::command]
+pub fn list_profiles(app: tauri::AppHandle) -> Result<Vec<EncodeProfile>, String> {
+    profiles::read_profiles(&profiles_path(&app)?)
+}
+
+#[tauri::command]
+pub fn create_profile(
+    app: tauri::AppHandle,
+    profile: EncodeProfile,
+) -> Result<Vec<EncodeProfile>, String> {
+    profiles::create_profile(&profiles_path(&app)?, profile)
+}
+
+#[tauri::command]
+pub fn update_profile(
+    app: tauri::AppHandle,
+    name: &str,
+    profile: EncodeProfile,
+) -> Result<Vec<EncodeProfile>, String> {
+    profiles::update_profile(&profiles_path(&app)?, name, profile)
+}
+
+#[tauri::command]
+pub fn delete_profile(app: tauri::AppHandle, name: &str) -> Result<Vec<EncodeProfile>, String> {
+    profiles::delete_profile(&profiles_path(&app)?, name)
+}
+
+#[tauri::command]
+pub fn import_profiles(app: tauri::AppHandle, path: &str) -> Result<Vec<EncodeProfile>, String> {
+    profiles::import_profiles(&profiles_path(&app)?, Path::new(path))
+}
+
+#[tauri::command]
+pub fn export_profiles(
+    app: tauri::AppHandle,
+    path: &str,
+    names: Vec<String>,
+) -> Result<(), String> {
+    profiles::export_profiles(&profiles_path(&app)?, Path::new(path), &names)
+}
@@ -0,0 +1,68 @@
+use tauri::State;
+
+use crate::{
+    domain::{CompressionJob, CompressionQueueSnapshot, NewCompressionJob},
+    queue::{self, CompressionQueue},
+};
+
+#[tauri::command]
+pub fn enqueue_compression_jobs(
+    app: tauri::AppHandle,
+    jobs: Vec<NewCompressionJob>,
+) -> Result<Vec<CompressionJob>, String> {
+    queue::enqueue(&app, jobs)
+}
+
+#[tauri::command]
+pub fn reorder_compression_job(
+    app: tauri::AppHandle,
+    job_id: &str,
+    index: usize,
+) -> Result<(), String> {
+    queue::reorder(&app, job_id, index)
+}
+
+#[tauri::command]
+pub fn pause_compression_queue(app: tauri::AppHandle) -> Result<(), String> {
+    queue::set_paused(&app, true)
+}
+
+#[tauri::command]
+pub fn resume_compression_queue(app: tauri::AppHandle) -> Result<(), String> {
+    queue::set_paused(&app, false)
+}
+
+#[tauri::command]
+pub fn cancel_compression_job(app: tauri::AppHandle, job_id: &str) -> Result<(), String> {
+    queue::cancel(&app, job_id)
+}
+
+#[tauri::command]
+pub fn retry_compression_job(app: tauri::AppHandle, job_id: &str) -> Result<(), String> {
+    queue::retry(&app, job_id)
+}
+
+#[tauri::command]
+pub fn resume_interrupted_compression_jobs(app: tauri::AppHandle) -> Result<(), String> {
+    queue::resume_interrupted(&app)
+}
+
+#[tauri::command]
+pub fn set_compression_concurrency(
+    app: tauri::AppHandle,
+    concurrency: usize,
+) -> Result<(), String> {
+    queue::set_concurrency(&app, concurrency)
+}
+
+#[tauri::command]
+pub fn clear_finished_compression_jobs(app: tauri::AppHandle) -> Result<(), String> {
+    queue::clear_finished(&app)
+}
+
+#[tauri::command]
+pub fn get_compression_queue(
+    queue: State<CompressionQueue>,
+) -> Result<CompressionQueueSnapshot, String> {
+    queue.snapshot()
+}
@@ -25,7 +25,7 @@ pub async fn schedule_system_shutdown(delay_seconds: u64) -> Result<(), String>
 
     #[cfg(target_os = "macos")]
     {
-        let minutes = (delay_seconds + 59) / 60; // Round up to nearest minute
+        let minutes = delay_seconds.div_ceil(60); // Round up to nearest minute
         let result = Command::new("shutdown")
             .args(["-h", &format!("+{}", minutes)])
             .output();
@@ -47,7 +47,7 @@ pub async fn schedule_system_shutdown(delay_seconds: u64) -> Result<(), String>
 
     #[cfg(target_os = "linux")]
     {
-        let minutes = (delay_seconds + 59) / 60; // Round up to nearest minute
+        let minutes = delay_seconds.div_ceil(60); // Round up to nearest minute
         let result = Command::new("shutdown")
             .args(["-h", &format!("+{}", minutes)])
             .output();
@@ -1,7 +1,9 @@
 // Prevents additional console window on Windows in release, DO NOT REMOVE!!
 #![cfg_attr(not(debug_assertions), windows_subsystem = "windows")]
 
+use lib::ffmpeg::RunningProcesses;
 use lib::fs::{self as file_system};
+use lib::queue::CompressionQueue;
 use tauri_plugin_log::Target as LogTarget;
 #[cfg(debug_assertions)]
 use tauri_plugin_log::TargetKind as LogTargetKind;
@@ -9,14 +11,33 @@ use tauri_plugin_log::TargetKind as LogTargetKind;
 use lib::tauri_commands::{
     ffmpeg::{
         __cmd__compress_video, __cmd__generate_quality_preview, __cmd__generate_video_thumbnail,
-        __cmd__get_video_info, compress_video, generate_quality_preview, generate_video_thumbnail,
-        get_video_info,
+        __cmd__get_ffmpeg_capabilities, __cmd__get_video_info, __cmd__probe_media, compress_video,
+        generate_quality_preview, generate_video_thumbnail, get_ffmpeg_capabilities,
+        get_video_info, probe_media,
     },
     file_manager::{__cmd__show_item_in_file_manager, show_item_in_file_manager},
     fs::{
         __cmd__delete_cache, __cmd__delete_file, __cmd__get_file_metadata,
-        __cmd__get_image_dimension, __cmd__move_file, __cmd__resolve_video_files, delete_cache,
-        delete_file, get_file_metadata, get_image_dimension, move_file, resolve_video_files,
+        __cmd__get_image_dimension, __cmd__move_file, __cmd__render_output_path,
+        __cmd__resolve_video_files, __cmd__validate_name_template, delete_cache, delete_file,
+        get_file_metadata, get_image_dimension, move_file, render_output_path,
+        resolve_video_files, validate_name_template,
+    },
+    profiles::{
+        __cmd__create_profile, __cmd__delete_profile, __cmd__export_profiles,
+        __cmd__import_profiles, __cmd__list_profiles, __cmd__update_profile, create_profile,
+        delete_profile, export_profiles, import_profiles, list_profiles, update_profile,
+    },
+    queue::{
+        __cmd__cancel_compression_job, __cmd__clear_finished_compression_jobs,
+        __cmd__enqueue_compression_jobs, __cmd__get_compression_queue,
+        __cmd__pause_compression_queue, __cmd__reorder_compression_job,
+        __cmd__resume_compression_queue, __cmd__resume_interrupted_compression_jobs,
+        __cmd__retry_compression_job, __cmd__set_compression_concurrency,
+        cancel_compression_job, clear_finished_compression_jobs, enqueue_compression_jobs,
+        get_compression_queue, pause_compression_queue, reorder_compression_job,
+        resume_compression_queue, resume_interrupted_compression_jobs, retry_compression_job,
+        set_compression_concurrency,
     },
     system::{
         __cmd__cancel_system_shutdown, __cmd__schedule_system_shutdown,
@@ -28,8 +49,7 @@ use lib::tauri_commands::{
 use lib::tauri_commands::file_manager::DbusState;
 #[cfg(target_os = "linux")]
 use std::sync::Mutex;
-#[cfg(target_os = "linux")]
-use tauri::Manager;
+use tauri::{Manager, RunEvent};
 
 #[cfg(debug_assertions)]
 const LOG_TARGETS: [LogTarget; 1] = [LogTarget::new(LogTargetKind::Stdout)];
@@ -39,7 +59,7 @@ const LOG_TARGETS: [LogTarget; 0] = [];
 
 #[tokio::main]
 async fn main() {
-    if let Err(err) = tauri::Builder::default()
+    let app = match tauri::Builder::default()
         .plugin(
             tauri_plugin_log::Builder::new()
                 .targets(LOG_TARGETS)
@@ -56,7 +76,10 @@ async fn main() {
                 dbus::blocking::SyncConnection::new_session().ok(),
             )));
 
-            file_system::setup_app_data_dir(app)?;
+            let app_data_dir = file_system::setup_app_data_dir(app)?;
+
+            app.manage(CompressionQueue::restore(&app_data_dir));
+            app.manage(RunningProcesses::default());
 
             Ok(())
         })
@@ -65,18 +88,49 @@ async fn main() {
             generate_quality_preview,
             generate_video_thumbnail,
             get_video_info,
+            probe_media,
+            get_ffmpeg_capabilities,
+            enqueue_compression_jobs,
+            reorder_compression_job,
+            pause_compression_queue,
+            resume_compression_queue,
+            cancel_compression_job,
+            retry_compression_job,
+            resume_interrupted_compression_jobs,
+            set_compression_concurrency,
+            clear_finished_compression_jobs,
+            get_compression_queue,
+            list_profiles,
+            create_profile,
+            update_profile,
+            delete_profile,
+            import_profiles,
+            export_profiles,
             get_image_dimension,
             get_file_metadata,
             move_file,
             delete_file,
             delete_cache,
             resolve_video_files,
+            validate_name_template,
+            render_output_path,
             show_item_in_file_manager,
             schedule_system_shutdown,
             cancel_system_shutdown
         ])
-        .run(tauri::generate_context!())
+        .build(tauri::generate_context!())
     {
-        eprintln!("error while running tauri application: {err}");
-    }
+        Ok(app) => app,
+        Err(err) => {
+            eprintln!("error while running tauri application: {err}");
+            return;
+        }
+    };
+
+    app.run(|app, event| {
+        // Encodes outlive the window, but not the app.
+        if let RunEvent::Exit = event {
+            app.state::<RunningProcesses>().kill_all();
+        }
+    });
 }
@@ -8,6 +8,27 @@ const videoConfigInitialState: VideoConfig = {
   presetName: 'ironclad',
   shouldDisableCompression: false,
   shouldMuteVideo: false,
+  audio: {
+    codec: null,
+    bitrateKbps: null,
+    channels: null,
+    sampleRate: null,
+    normalize: false,
+  },
+  streams: {
+    audio: { type: 'default' },
+    subtitles: { type: 'default' },
+    mixAudio: false,
+  },
+  subtitles: {
+    files: [],
+    burnIn: null,
+  },
+  metadata: {
+    mode: 'default',
+    copyFileTimes: false,
+  },
+  hdr: 'tonemap',
   quality: 50,
   shouldEnableQuality: false,
 }
@@ -16,9 +37,12 @@ const batchConfigInitialState: BatchConfig = {
   namingMode: 'suffix',
   prefix: '',
   suffix: '_compressed',
+  nameTemplate: '{stem}_compressed.{ext}',
   outputFolderMode: 'source',
   outputFolder: null,
   includeSubfolders: true,
+  mirrorSubfolders: false,
+  conflictPolicy: 'autoRename',
   shutdownTimer: {
     delaySeconds: 0,
   },
@@ -61,6 +85,7 @@ const videoInitialState: Video = {
   isCompressionSuccessful: false,
   compressedVideo: null,
   compressionProgress: 0,
+  compressionStats: null,
   config: videoConfigInitialState,
   batch: batchInitialState,
 }
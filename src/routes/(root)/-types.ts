@@ -1,9 +1,18 @@
 import {
+  AudioOptions,
   compressionPresets,
   extensions,
+  HdrFormat,
+  hdrModes,
+  MetadataOptions,
+  StreamInfo,
+  StreamOptions,
+  SubtitleOptions,
+  TrimRange,
   VideoTransforms,
   VideoTransformsHistory,
 } from '@/types/compression'
+import { ConflictPolicy } from '@/types/fs'
 
 export type ConvertToExtension = keyof typeof extensions.video | 'source'
 
@@ -23,6 +32,11 @@ export type VideoConfig = {
   presetName: keyof typeof compressionPresets
   shouldDisableCompression: boolean
   shouldMuteVideo: boolean
+  audio: AudioOptions
+  streams: StreamOptions
+  subtitles: SubtitleOptions
+  metadata: MetadataOptions
+  hdr: keyof typeof hdrModes
   shouldEnableQuality?: boolean
   quality?: number | null
   shouldEnableCustomDimensions?: boolean
@@ -35,9 +49,12 @@ export type VideoConfig = {
     transformsHistory: VideoTransformsHistory[]
     previewUrl?: string
   }
+  shouldTrimVideo?: boolean
+  // Parts of the video to keep, joined in order.
+  trimRanges?: TrimRange[]
 }
 
-export type BatchNamingMode = 'suffix' | 'prefix' | 'replace'
+export type BatchNamingMode = 'suffix' | 'prefix' | 'template' | 'replace'
 export type BatchOutputFolderMode = 'source' | 'custom'
 
 export type ShutdownTimerConfig = {
@@ -54,9 +71,15 @@ export type BatchConfig = {
   namingMode: BatchNamingMode
   prefix: string
   suffix: string
+  // e.g. `{stem}_{width}x{height}.{ext}`, used by the `template` naming mode.
+  nameTemplate: string
   outputFolderMode: BatchOutputFolderMode
   outputFolder: string | null
   includeSubfolders: boolean
+  // Recreates the dropped folders' subfolders inside a custom output folder.
+  mirrorSubfolders: boolean
+  // What to do when an output file already exists.
+  conflictPolicy: ConflictPolicy
   shutdownTimer: ShutdownTimerConfig
 }
 
@@ -71,6 +94,7 @@ export type BatchItemStatus =
 export type BatchItem = {
   id: string
   path: string
+  relativeDir: string
   fileName: string
   extension: string
   sizeInBytes: number
@@ -136,8 +160,16 @@ export type Video = {
     savedPath?: string
   } | null
   compressionProgress?: number
+  compressionStats?: {
+    etaSeconds?: number | null
+    projectedSize?: number | null
+  } | null
   config: VideoConfig
   dimensions?: { width: number; height: number }
   fps?: number
+  // HDR transfer of the selected video, `null` for SDR.
+  hdr?: HdrFormat | null
+  // Audio and subtitle streams of the selected video, when it could be probed.
+  mediaStreams?: StreamInfo[]
   batch: BatchState
 }
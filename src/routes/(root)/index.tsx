@@ -10,7 +10,11 @@ import Icon from '@/components/Icon'
 import Layout from '@/components/Layout'
 import Tabs, { Tab } from '@/components/Tabs'
 import { toast } from '@/components/Toast'
-import { generateVideoThumbnail, getVideoInfo } from '@/tauri/commands/ffmpeg'
+import {
+  generateVideoThumbnail,
+  getVideoInfo,
+  probeMedia,
+} from '@/tauri/commands/ffmpeg'
 import { getFileMetadata, resolveVideoFiles } from '@/tauri/commands/fs'
 import VideoPicker from '@/tauri/components/VideoPicker'
 import { extensions } from '@/types/compression'
@@ -40,9 +44,11 @@ function Root() {
           toast.error('Invalid file selected.')
           return
         }
-        const [fileMetadata, videoInfo] = await Promise.all([
+        const [fileMetadata, videoInfo, mediaInfo] = await Promise.all([
           getFileMetadata(path),
           getVideoInfo(path),
+          // Only used to pick streams, ffmpeg picks them when probing fails.
+          probeMedia(path).catch(() => null),
         ])
 
         if (
@@ -62,11 +68,24 @@ function Root() {
         videoProxy.state.isThumbnailGenerating = true
         videoProxy.state.extension = fileMetadata?.extension?.toLowerCase?.()
 
+        videoProxy.state.mediaStreams = mediaInfo?.streams.filter(
+          (stream) => stream.kind === 'audio' || stream.kind === 'subtitle',
+        )
+        // Picked streams are positions in the previous video.
+        videoProxy.state.config.streams = {
+          audio: { type: 'default' },
+          subtitles: { type: 'default' },
+          mixAudio: false,
+        }
+        videoProxy.state.config.subtitles = { files: [], burnIn: null }
+
         if (fileMetadata?.extension) {
           videoProxy.state.config.convertToExtension = videoProxy.state
             .extension as keyof (typeof extensions)['video']
         }
 
+        videoProxy.state.hdr = videoInfo?.hdr ?? null
+
         if (videoInfo) {
           const dimensions = videoInfo.dimensions
           if (
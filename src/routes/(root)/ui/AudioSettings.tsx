@@ -0,0 +1,218 @@
+import { SelectItem } from '@heroui/select'
+import { AnimatePresence, motion } from 'framer-motion'
+import { useSnapshot } from 'valtio'
+
+import Select from '@/components/Select'
+import Switch from '@/components/Switch'
+import Tooltip from '@/components/Tooltip'
+import { AudioOptions, audioCodecs } from '@/types/compression'
+import { slideDownTransition } from '@/utils/animation'
+import { videoProxy } from '../-state'
+
+const DEFAULT_KEY = 'default'
+
+const codecLabels: Record<keyof typeof audioCodecs, string> = {
+  copy: 'Copy (no re-encode)',
+  aac: 'AAC',
+  opus: 'Opus',
+}
+
+const bitrateOptions = [64, 96, 128, 160, 192, 256, 320]
+
+const channelLabels: Record<string, string> = {
+  '1': 'Mono',
+  '2': 'Stereo',
+}
+
+// Opus only supports a few rates, 44.1 kHz is not one of them.
+const sampleRateOptions = [48000, 44100, 24000, 16000]
+
+type AudioSettingsProps = {
+  isDisabled: boolean
+}
+
+function AudioSettings({ isDisabled }: AudioSettingsProps) {
+  const {
+    state: {
+      config: { shouldMuteVideo, audio },
+    },
+  } = useSnapshot(videoProxy)
+
+  const isCopy = audio.codec === audioCodecs.copy
+
+  const update = (changes: Partial<AudioOptions>) => {
+    videoProxy.state.config.audio = {
+      ...videoProxy.state.config.audio,
+      ...changes,
+    }
+  }
+
+  const parseKey = (value: string | undefined) =>
+    value && value !== DEFAULT_KEY ? Number(value) : null
+
+  const selectClassNames = {
+    label: '!text-gray-600 dark:!text-gray-400 text-sm',
+  }
+
+  return (
+    <AnimatePresence mode="wait">
+      {!shouldMuteVideo ? (
+        <motion.div {...slideDownTransition} className="my-2 space-y-2">
+          <Select
+            fullWidth
+            label="Audio Codec:"
+            size="sm"
+            selectedKeys={[audio.codec ?? DEFAULT_KEY]}
+            onChange={(evt) => {
+              const value = evt?.target?.value
+              if (!value) return
+              if (value === audioCodecs.copy) {
+                // Copied audio cannot be resampled or normalized.
+                update({
+                  codec: audioCodecs.copy,
+                  bitrateKbps: null,
+                  channels: null,
+                  sampleRate: null,
+                  normalize: false,
+                })
+              } else if (value === audioCodecs.opus) {
+                update({
+                  codec: audioCodecs.opus,
+                  sampleRate:
+                    audio.sampleRate === 44100 ? 48000 : audio.sampleRate,
+                })
+              } else {
+                update({
+                  codec: value === DEFAULT_KEY ? null : audioCodecs.aac,
+                })
+              }
+            }}
+            selectionMode="single"
+            isDisabled={isDisabled}
+            classNames={selectClassNames}
+          >
+            {[
+              <SelectItem key={DEFAULT_KEY} value={DEFAULT_KEY}>
+                Default for extension
+              </SelectItem>,
+              ...(
+                Object.keys(codecLabels) as (keyof typeof audioCodecs)[]
+              ).map((codec) => (
+                <SelectItem key={codec} value={codec}>
+                  {codecLabels[codec]}
+                </SelectItem>
+              )),
+            ]}
+          </Select>
+          <AnimatePresence mode="wait">
+            {!isCopy ? (
+              <motion.div {...slideDownTransition} className="space-y-2">
+                <Select
+                  fullWidth
+                  label="Audio Bitrate:"
+                  size="sm"
+                  selectedKeys={[
+                    audio.bitrateKbps?.toString() ?? DEFAULT_KEY,
+                  ]}
+                  onChange={(evt) => {
+                    const value = evt?.target?.value
+                    if (!value) return
+                    update({ bitrateKbps: parseKey(value) })
+                  }}
+                  selectionMode="single"
+                  isDisabled={isDisabled}
+                  classNames={selectClassNames}
+                >
+                  {[
+                    <SelectItem key={DEFAULT_KEY} value={DEFAULT_KEY}>
+                      Default
+                    </SelectItem>,
+                    ...bitrateOptions.map((kbps) => (
+                      <SelectItem key={kbps.toString()} value={kbps}>
+                        {`${kbps} kbps`}
+                      </SelectItem>
+                    )),
+                  ]}
+                </Select>
+                <Select
+                  fullWidth
+                  label="Channels:"
+                  size="sm"
+                  selectedKeys={[audio.channels?.toString() ?? DEFAULT_KEY]}
+                  onChange={(evt) => {
+                    const value = evt?.target?.value
+                    if (!value) return
+                    update({ channels: parseKey(value) })
+                  }}
+                  selectionMode="single"
+                  isDisabled={isDisabled}
+                  classNames={selectClassNames}
+                >
+                  {[
+                    <SelectItem key={DEFAULT_KEY} value={DEFAULT_KEY}>
+                      Same as source
+                    </SelectItem>,
+                    ...Object.keys(channelLabels).map((channels) => (
+                      <SelectItem key={channels} value={channels}>
+                        {channelLabels[channels]}
+                      </SelectItem>
+                    )),
+                  ]}
+                </Select>
+                <Select
+                  fullWidth
+                  label="Sample Rate:"
+                  size="sm"
+                  selectedKeys={[audio.sampleRate?.toString() ?? DEFAULT_KEY]}
+                  onChange={(evt) => {
+                    const value = evt?.target?.value
+                    if (!value) return
+                    update({ sampleRate: parseKey(value) })
+                  }}
+                  selectionMode="single"
+                  isDisabled={isDisabled}
+                  classNames={selectClassNames}
+                >
+                  {[
+                    <SelectItem key={DEFAULT_KEY} value={DEFAULT_KEY}>
+                      {audio.normalize ? '48 kHz' : 'Same as source'}
+                    </SelectItem>,
+                    ...sampleRateOptions
+                      .filter(
+                        (rate) =>
+                          audio.codec !== audioCodecs.opus || rate !== 44100,
+                      )
+                      .map((rate) => (
+                        <SelectItem key={rate.toString()} value={rate}>
+                          {`${rate / 1000} kHz`}
+                        </SelectItem>
+                      )),
+                  ]}
+                </Select>
+                <Switch
+                  isSelected={audio.normalize}
+                  onValueChange={() => {
+                    update({ normalize: !audio.normalize })
+                  }}
+                  isDisabled={isDisabled}
+                >
+                  <Tooltip
+                    content="Measures the loudness first, then evens it out to -16 LUFS (EBU R128). Takes an extra pass over the audio."
+                    placement="top"
+                    delay={500}
+                  >
+                    <p className="text-gray-600 dark:text-gray-400 text-sm mr-2 w-full cursor-help">
+                      Normalize Loudness
+                    </p>
+                  </Tooltip>
+                </Switch>
+              </motion.div>
+            ) : null}
+          </AnimatePresence>
+        </motion.div>
+      ) : null}
+    </AnimatePresence>
+  )
+}
+
+export default AudioSettings
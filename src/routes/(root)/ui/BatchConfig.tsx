@@ -1,8 +1,8 @@
 import { SelectItem } from '@heroui/select'
 import { core, event } from '@tauri-apps/api'
-import { emitTo } from '@tauri-apps/api/event'
 import { open } from '@tauri-apps/plugin-dialog'
 import { AnimatePresence, motion } from 'framer-motion'
+import cloneDeep from 'lodash/cloneDeep'
 import React from 'react'
 import { snapshot, useSnapshot } from 'valtio'
 
@@ -14,31 +14,43 @@ import Select from '@/components/Select'
 import Spinner from '@/components/Spinner'
 import Switch from '@/components/Switch'
 import { toast } from '@/components/Toast'
-import { compressVideo, getVideoInfo } from '@/tauri/commands/ffmpeg'
+import { EncodeOptionsInput, toEncodeOptions } from '@/tauri/commands/ffmpeg'
 import {
   getFileMetadata,
-  moveFile,
+  renderOutputPath,
   resolveVideoFiles,
+  validateNameTemplate,
 } from '@/tauri/commands/fs'
+import {
+  cancelCompressionJob,
+  runCompressionJobs,
+} from '@/tauri/commands/queue'
 import { scheduleSystemShutdown } from '@/tauri/commands/system'
 import {
+  CompressionJob,
   CustomEvents,
   extensions,
   VideoCompressionProgress,
 } from '@/types/compression'
+import { ConflictPolicy } from '@/types/fs'
 import { createBatchItems, mergeBatchItems } from '@/utils/batch-utils'
+import { getErrorMessage, isCancelledError } from '@/utils/ffmpeg-error'
 import { formatBytes } from '@/utils/fs'
 import { convertDurationToMilliseconds } from '@/utils/string'
 import { cn } from '@/utils/tailwind'
+import AudioSettings from './AudioSettings'
 import BatchDimensions from './BatchDimensions'
 import BatchFPS from './BatchFPS'
 import CompressionPreset from './CompressionPreset'
 import CompressionQuality from './CompressionQuality'
 import DragAndDrop from './DragAndDrop'
+import HdrSettings from './HdrSettings'
+import MetadataSettings from './MetadataSettings'
 import QualityPreviewAction from './QualityPreviewAction'
 import ShutdownCountdownModal from './ShutdownCountdownModal'
 import ShutdownTimer from './ShutdownTimer'
 import styles from './styles.module.css'
+import VideoStreams from './VideoStreams'
 import { videoProxy } from '../-state'
 import {
   BatchNamingMode,
@@ -52,6 +64,7 @@ const extensionOptions = ['source', ...videoExtensions] as readonly string[]
 const namingOptions: Record<BatchNamingMode, string> = {
   suffix: 'Add suffix',
   prefix: 'Add prefix',
+  template: 'Name template',
   replace: 'Replace original',
 }
 
@@ -60,6 +73,12 @@ const outputFolderOptions: Record<BatchOutputFolderMode, string> = {
   custom: 'Choose folder',
 }
 
+const conflictPolicyOptions: Record<ConflictPolicy, string> = {
+  autoRename: 'Add a number',
+  skip: 'Skip video',
+  overwrite: 'Overwrite',
+}
+
 function sanitizeNamePart(value: string) {
   return value.replace(/[<>:"/\\|?*]/g, '').trim()
 }
@@ -114,11 +133,38 @@ function BatchConfig() {
   const [isCancelling, setIsCancelling] = React.useState(false)
   const [isResolving, setIsResolving] = React.useState(false)
   const [showCountdownModal, setShowCountdownModal] = React.useState(false)
+  const [nameTemplateError, setNameTemplateError] = React.useState<
+    string | null
+  >(null)
 
   const totalSize = React.useMemo(() => {
     return batch.items.reduce((sum, item) => sum + (item.sizeInBytes ?? 0), 0)
   }, [batch.items])
 
+  React.useEffect(() => {
+    if (batch.config.namingMode !== 'template') {
+      setNameTemplateError(null)
+      return
+    }
+    let isCurrent = true
+    validateNameTemplate(batch.config.nameTemplate)
+      .then(() => {
+        if (isCurrent) {
+          setNameTemplateError(null)
+        }
+      })
+      .catch((error: unknown) => {
+        if (isCurrent) {
+          setNameTemplateError(
+            getErrorMessage(error, 'Invalid name template.'),
+          )
+        }
+      })
+    return () => {
+      isCurrent = false
+    }
+  }, [batch.config.namingMode, batch.config.nameTemplate])
+
   React.useEffect(() => {
     if (batch.config.namingMode === 'replace') {
       if (config.convertToExtension !== 'source') {
@@ -150,11 +196,15 @@ function BatchConfig() {
         (evt) => {
           const payload = evt?.payload
           if (!payload?.videoId) return
-          if (!payload?.currentDuration) return
           const items = videoProxy.state.batch.items
           const index = items.findIndex((item) => item.id === payload.videoId)
           if (index === -1) return
           const current = items[index]
+          if (payload.percentage != null) {
+            items[index] = { ...current, progress: payload.percentage }
+            return
+          }
+          if (!payload?.currentDuration) return
           const durationMs = current.durationMilliseconds ?? 0
           if (durationMs <= 0) return
           const currentDurationMs = convertDurationToMilliseconds(
@@ -282,10 +332,25 @@ function BatchConfig() {
     }
   }
 
-  const resolveUniquePath = async (path: string) => {
+  // Same rules as `ConflictPolicy` in the backend: paths given to other videos
+  // of the batch are never overwritten. `null` skips the video.
+  const resolveOutputPath = async (path: string, reserved: Set<string>) => {
+    const isTaken = async (candidate: string) =>
+      reserved.has(candidate) || (await pathExists(candidate))
+    if (!(await isTaken(path))) {
+      return path
+    }
+    const conflictPolicy = batch.config.conflictPolicy
+    if (conflictPolicy === 'overwrite' && !reserved.has(path)) {
+      return path
+    }
+    if (conflictPolicy === 'skip') {
+      return null
+    }
+
     let candidate = path
     let index = 1
-    while (await pathExists(candidate)) {
+    while (await isTaken(candidate)) {
       candidate = appendIndexToPath(path, index)
       index += 1
       if (index > 1000) {
@@ -295,13 +360,44 @@ function BatchConfig() {
     return candidate
   }
 
-  const buildOutputPath = async (item: (typeof batch.items)[number]) => {
+  const buildOutputPath = async (
+    item: (typeof batch.items)[number],
+    index: number,
+    encodeOptions: EncodeOptionsInput,
+    reserved: Set<string>,
+  ) => {
     const namingMode = batch.config.namingMode
 
     if (namingMode === 'replace') {
       return item.path
     }
 
+    const customOutputDir =
+      batch.config.outputFolderMode === 'custom'
+        ? batch.config.outputFolder
+        : null
+    const outputDir = customOutputDir
+      ? batch.config.mirrorSubfolders && item.relativeDir
+        ? joinPath(customOutputDir, item.relativeDir)
+        : customOutputDir
+      : getDirname(item.path)
+
+    if (!outputDir) {
+      throw new Error('Output folder is not set.')
+    }
+
+    if (namingMode === 'template') {
+      const outputPath = await renderOutputPath({
+        videoPath: item.path,
+        outputDir,
+        template: batch.config.nameTemplate,
+        options: toEncodeOptions(encodeOptions),
+        index: index + 1,
+        conflictPolicy: batch.config.conflictPolicy,
+        reserved: [...reserved],
+      })
+    }
+
     const outputExt =
       config.convertToExtension === 'source'
         ? item.extension
@@ -324,17 +420,8 @@ function BatchConfig() {
 
     const fileName = outputExt ? `${baseName}.${outputExt}` : baseName
 
-    const outputDir =
-      batch.config.outputFolderMode === 'custom'
-        ? batch.config.outputFolder
-        : getDirname(item.path)
-
-    if (!outputDir) {
-      throw new Error('Output folder is not set.')
-    }
-
     const fullPath = joinPath(outputDir, fileName)
-    return resolveUniquePath(fullPath)
+    return resolveOutputPath(fullPath, reserved)
   }
 
   const handleStartBatch = async () => {
@@ -346,6 +433,15 @@ function BatchConfig() {
       return
     }
 
+    if (snapshotState.batch.config.namingMode === 'template') {
+      try {
+        await validateNameTemplate(snapshotState.batch.config.nameTemplate)
+      } catch (error: unknown) {
+        toast.error(getErrorMessage(error, 'Invalid name template.'))
+        return
+      }
+    }
+
     if (
       snapshotState.batch.config.outputFolderMode === 'custom' &&
       !snapshotState.batch.config.outputFolder
@@ -371,73 +467,96 @@ function BatchConfig() {
       output: null,
     }))
 
-    try {
-      const items = [...videoProxy.state.batch.items]
-      for (let index = 0; index < items.length; index += 1) {
-        const item = items[index]
-        if (videoProxy.state.batch.cancelRequested) {
-          videoProxy.state.batch.items = videoProxy.state.batch.items.map(
-            (entry) =>
-              entry.status === 'pending'
-                ? { ...entry, status: 'cancelled' }
-                : entry,
-          )
-          break
-        }
-
-        videoProxy.state.batch.currentItemId = item.id
-        videoProxy.state.batch.items[index] = { ...item, status: 'compressing' }
-
-        try {
-          const info = await getVideoInfo(item.path)
-          if (info?.duration) {
-            const durationMs = convertDurationToMilliseconds(info.duration)
-            videoProxy.state.batch.items[index] = {
-              ...videoProxy.state.batch.items[index],
-              durationMilliseconds: durationMs,
-              durationRaw: info.duration,
-            }
-          }
+    const items = [...videoProxy.state.batch.items]
+    // Output paths are picked up front, none of them exists until its job ran.
+    const reserved = new Set<string>()
+    const jobs: Parameters<typeof runCompressionJobs>[0] = []
+    for (let index = 0; index < items.length; index += 1) {
+      const item = items[index]
+      const convertToExtension =
+        batch.config.namingMode === 'replace'
+          ? item.extension
+          : config.convertToExtension === 'source'
+            ? item.extension
+            : config.convertToExtension
+
+      const encodeOptions: EncodeOptionsInput = {
+        convertToExtension,
+        presetName: !config.shouldDisableCompression ? config.presetName : null,
+        shouldMuteVideo: config.shouldMuteVideo,
+        audio: { ...config.audio },
+        streams: cloneDeep(config.streams),
+        metadata: { ...config.metadata },
+        hdr: config.hdr,
+        ...(config.shouldEnableQuality
+          ? { quality: config.quality as number }
+          : {}),
+        ...(config.shouldEnableCustomDimensions && config.customDimensions
+          ? { dimensions: config.customDimensions }
+          : {}),
+        ...(config.shouldEnableCustomFPS && typeof config.customFPS === 'number'
+          ? { fps: config.customFPS.toString() }
+          : {}),
+      }
 
-          const outputPath = await buildOutputPath(item)
-
-          const convertToExtension =
-            batch.config.namingMode === 'replace'
-              ? item.extension
-              : config.convertToExtension === 'source'
-                ? item.extension
-                : config.convertToExtension
-
-          const result = await compressVideo({
-            videoPath: item.path,
-            convertToExtension,
-            presetName: !config.shouldDisableCompression
-              ? config.presetName
-              : null,
-            videoId: item.id,
-            shouldMuteVideo: config.shouldMuteVideo,
-            ...(config.shouldEnableQuality
-              ? { quality: config.quality as number }
-              : {}),
-            ...(config.shouldEnableCustomDimensions && config.customDimensions
-              ? { dimensions: config.customDimensions }
-              : {}),
-            ...(config.shouldEnableCustomFPS &&
-            typeof config.customFPS === 'number'
-              ? { fps: config.customFPS.toString() }
-              : {}),
-          })
-
-          if (!result?.filePath) {
-            throw new Error('Compression failed.')
+      try {
+        const outputPath = await buildOutputPath(
+          item,
+          index,
+          encodeOptions,
+          reserved,
+        )
+        if (!outputPath) {
+          videoProxy.state.batch.items[index] = {
+            ...videoProxy.state.batch.items[index],
+            status: 'skipped',
           }
+          videoProxy.state.batch.skippedCount += 1
+          continue
+        }
+        reserved.add(outputPath)
+        jobs.push({
+          id: item.id,
+          videoPath: item.path,
+          outputPath,
+          ...encodeOptions,
+        })
+      } catch (error: unknown) {
+        videoProxy.state.batch.items[index] = {
+          ...videoProxy.state.batch.items[index],
+          status: 'failed',
+          error: getErrorMessage(error, 'Could not pick an output file name.'),
+        }
+        videoProxy.state.batch.failedCount += 1
+      }
+    }
 
-          await moveFile(result.filePath, outputPath)
-
-          const outputMetadata = await getFileMetadata(outputPath)
-          const outputFileName = outputMetadata?.fileName ?? ''
-          const outputSize = formatBytes(outputMetadata?.size ?? 0)
+    const handleJobUpdated = async (job: CompressionJob) => {
+      const index = videoProxy.state.batch.items.findIndex(
+        (item) => item.id === job.id,
+      )
+      if (index === -1) return
+      const item = videoProxy.state.batch.items[index]
 
+      if (job.status === 'running') {
+        videoProxy.state.batch.currentItemId = job.id
+        videoProxy.state.batch.items[index] = { ...item, status: 'compressing' }
+      } else if (job.status === 'cancelled') {
+        videoProxy.state.batch.items[index] = {
+          ...item,
+          status: 'cancelled',
+          error: null,
+        }
+      } else if (job.status === 'failed') {
+        videoProxy.state.batch.items[index] = {
+          ...item,
+          status: 'failed',
+          error: getErrorMessage(job.error, 'Compression failed.'),
+        }
+        videoProxy.state.batch.failedCount += 1
+      } else if (job.status === 'completed' && job.result) {
+        try {
+          const outputMetadata = await getFileMetadata(job.result.filePath)
           videoProxy.state.batch.items[index] = {
             ...videoProxy.state.batch.items[index],
             status: 'success',
@@ -445,44 +564,40 @@ function BatchConfig() {
             output: {
               pathRaw: outputMetadata?.path,
               path: core.convertFileSrc(outputMetadata?.path ?? ''),
-              fileName: outputFileName,
+              fileName: outputMetadata?.fileName ?? '',
               sizeInBytes: outputMetadata?.size,
-              size: outputSize,
+              size: formatBytes(outputMetadata?.size ?? 0),
               extension: outputMetadata?.extension,
             },
           }
           videoProxy.state.batch.completedCount += 1
         } catch (error: unknown) {
-          const message = String(error ?? '')
-          if (message.includes('CANCELLED')) {
-            videoProxy.state.batch.items[index] = {
-              ...videoProxy.state.batch.items[index],
-              status: 'cancelled',
-              error: null,
-            }
-            break
-          }
           videoProxy.state.batch.items[index] = {
             ...videoProxy.state.batch.items[index],
             status: 'failed',
-            error: message || 'Compression failed.',
+            error: getErrorMessage(error, 'Compression failed.'),
           }
           videoProxy.state.batch.failedCount += 1
         }
       }
+    }
+
+    try {
+      await runCompressionJobs(jobs, handleJobUpdated)
+    } catch (error: unknown) {
+      toast.error(getErrorMessage(error, 'Could not start the batch.'))
+      videoProxy.state.batch.items = videoProxy.state.batch.items.map(
+        (entry) =>
+          entry.status === 'pending'
+            ? { ...entry, status: 'cancelled' }
+            : entry,
+      )
     } finally {
-      if (videoProxy.state.batch.cancelRequested) {
-        videoProxy.state.batch.items = videoProxy.state.batch.items.map(
-          (entry) =>
-            entry.status === 'pending'
-              ? { ...entry, status: 'cancelled' }
-              : entry,
-        )
-      }
       videoProxy.state.batch.currentItemId = null
       videoProxy.state.batch.isCompressing = false
       videoProxy.state.isCompressing = false
       videoProxy.state.batch.isCompleted = true
+      const cancelRequested = videoProxy.state.batch.cancelRequested
       videoProxy.state.batch.cancelRequested = false
       setConfirmCancel(false)
       setIsCancelling(false)
@@ -490,10 +605,7 @@ function BatchConfig() {
       // Trigger shutdown timer if configured
       const shutdownConfig =
         snapshot(videoProxy).state.batch.config.shutdownTimer
-      if (
-        shutdownConfig.delaySeconds > 0 &&
-        !videoProxy.state.batch.cancelRequested
-      ) {
+      if (shutdownConfig.delaySeconds > 0 && !cancelRequested) {
         handleScheduleShutdown(shutdownConfig.delaySeconds)
       }
     }
@@ -503,12 +615,13 @@ function BatchConfig() {
     if (!batch.isCompressing) return
     setIsCancelling(true)
     videoProxy.state.batch.cancelRequested = true
-    const currentId = snapshot(videoProxy).state.batch.currentItemId
-    if (currentId) {
+    // Queued jobs are dropped, the running ones stopped.
+    const unfinished = snapshot(videoProxy).state.batch.items.filter((item) =>
+      ['pending', 'compressing'].includes(item.status),
+    )
+    for (const item of unfinished) {
       try {
-        await emitTo('main', CustomEvents.CancelInProgressCompression, {
-          videoId: currentId,
-        })
+        await cancelCompressionJob(item.id)
       } catch {
         // ignore
       }
@@ -718,6 +831,7 @@ function BatchConfig() {
                     value &&
                     (value === 'suffix' ||
                       value === 'prefix' ||
+                      value === 'template' ||
                       value === 'replace')
                   ) {
                     videoProxy.state.batch.config.namingMode = value
@@ -766,12 +880,66 @@ function BatchConfig() {
                 />
               ) : null}
 
+              {batch.config.namingMode === 'template' ? (
+                <div className="space-y-1">
+                  <input
+                    type="text"
+                    value={batch.config.nameTemplate}
+                    onChange={(evt) => {
+                      videoProxy.state.batch.config.nameTemplate =
+                        evt.target.value
+                    }}
+                    placeholder="{stem}_compressed.{ext}"
+                    className="w-full rounded-xl border border-zinc-200 dark:border-zinc-800 bg-transparent px-3 py-2 text-sm text-gray-700 dark:text-gray-200"
+                    disabled={batch.isCompressing}
+                  />
+                  {nameTemplateError ? (
+                    <p className="text-xs text-red-500">{nameTemplateError}</p>
+                  ) : (
+                    <p className="text-xs text-gray-500">
+                      {
+                        '{stem} {ext} {date} {width} {height} {quality} {preset} {codec} {index} {parent}'
+                      }
+                    </p>
+                  )}
+                </div>
+              ) : null}
+
               {batch.config.namingMode === 'replace' ? (
                 <p className="text-xs text-amber-500">
                   Original files will be replaced after successful compression.
                 </p>
               ) : null}
 
+              <Select
+                fullWidth
+                label="Existing Files:"
+                className="block flex-shrink-0 rounded-2xl"
+                size="sm"
+                value={batch.config.conflictPolicy}
+                selectedKeys={[batch.config.conflictPolicy]}
+                onChange={(evt) => {
+                  const value = evt?.target?.value
+                  if (value && value in conflictPolicyOptions) {
+                    videoProxy.state.batch.config.conflictPolicy =
+                      value as ConflictPolicy
+                  }
+                }}
+                selectionMode="single"
+                isDisabled={
+                  batch.isCompressing || batch.config.namingMode === 'replace'
+                }
+                classNames={{
+                  label: '!text-gray-600 dark:!text-gray-400 text-sm',
+                }}
+              >
+                {Object.entries(conflictPolicyOptions).map(([key, label]) => (
+                  <SelectItem key={key} value={key}>
+                    {label}
+                  </SelectItem>
+                ))}
+              </Select>
+
               <Divider />
 
               <Select
@@ -817,6 +985,21 @@ function BatchConfig() {
                 </div>
               ) : null}
 
+              {batch.config.outputFolderMode === 'custom' ? (
+                <Switch
+                  isSelected={batch.config.mirrorSubfolders}
+                  onValueChange={() => {
+                    videoProxy.state.batch.config.mirrorSubfolders =
+                      !batch.config.mirrorSubfolders
+                  }}
+                  isDisabled={batch.isCompressing}
+                >
+                  <p className="text-gray-600 dark:text-gray-400 text-sm mr-2 w-full">
+                    Recreate subfolders in output folder
+                  </p>
+                </Switch>
+              ) : null}
+
               <Switch
                 isSelected={batch.config.includeSubfolders}
                 onValueChange={() => {
@@ -855,6 +1038,10 @@ function BatchConfig() {
                 </div>
               </Switch>
             </div>
+            <AudioSettings isDisabled={batch.isCompressing} />
+            <VideoStreams isDisabled={batch.isCompressing} />
+            <MetadataSettings isDisabled={batch.isCompressing} />
+            <HdrSettings isDisabled={batch.isCompressing} />
             <Divider className="my-2" />
             <CompressionQuality />
             <Divider className="my-2" />
@@ -1,11 +1,11 @@
 import { event } from '@tauri-apps/api'
-import { emitTo } from '@tauri-apps/api/event'
 import { AnimatePresence, motion } from 'framer-motion'
 import React from 'react'
 import { snapshot, useSnapshot } from 'valtio'
 
 import Button from '@/components/Button'
 import { toast } from '@/components/Toast'
+import { cancelCompressionJob } from '@/tauri/commands/queue'
 import { CustomEvents, VideoCompressionProgress } from '@/types/compression'
 import { convertDurationToMilliseconds } from '@/utils/string'
 import { videoProxy } from '../-state'
@@ -32,6 +32,14 @@ function CancelCompression() {
             (evt) => {
               const payload = evt?.payload
               if (videoId === payload?.videoId) {
+                videoProxy.state.compressionStats = {
+                  etaSeconds: payload.etaSeconds,
+                  projectedSize: payload.projectedSize,
+                }
+                if (payload.percentage != null) {
+                  videoProxy.state.compressionProgress = payload.percentage
+                  return
+                }
                 const currentDurationInMilliseconds =
                   convertDurationToMilliseconds(payload?.currentDuration)
                 if (
@@ -62,9 +70,10 @@ function CancelCompression() {
   const cancelOngoingCompression = async () => {
     try {
       setIsCancelling(true)
-      await emitTo('main', CustomEvents.CancelInProgressCompression, {
-        videoId: snapshot(videoProxy).state.id,
-      })
+      const jobId = snapshot(videoProxy).state.id
+      if (jobId) {
+        await cancelCompressionJob(jobId)
+      }
       videoProxy.timeTravel('beforeCompressionStarted')
     } catch {
       toast.error('Cannot cancel compression at this point.')
@@ -4,6 +4,8 @@ import { useSnapshot } from 'valtio'
 
 import Image from '@/components/Image'
 import Progress from '@/components/Progress'
+import { formatBytes } from '@/utils/fs'
+import { formatRemainingTime } from '@/utils/string'
 import { videoProxy } from '../-state'
 
 function Compressing() {
@@ -14,6 +16,7 @@ function Compressing() {
       thumbnailPath,
       config,
       compressionProgress,
+      compressionStats,
       extension,
     },
   } = useSnapshot(videoProxy)
@@ -66,6 +69,21 @@ function Compressing() {
       >
         {compressionProgress?.toFixed(2)}%
       </p>
+      {compressionStats?.etaSeconds != null ||
+      compressionStats?.projectedSize != null ? (
+        <p className="text-sm text-center text-gray-600 dark:text-gray-400">
+          {compressionStats?.etaSeconds != null
+            ? `${formatRemainingTime(compressionStats.etaSeconds)} left`
+            : null}
+          {compressionStats?.etaSeconds != null &&
+          compressionStats?.projectedSize != null
+            ? ' · '
+            : null}
+          {compressionStats?.projectedSize != null
+            ? `~${formatBytes(compressionStats.projectedSize)}`
+            : null}
+        </p>
+      ) : null}
     </motion.div>
   ) : null
 }
@@ -0,0 +1,68 @@
+import { SelectItem } from '@heroui/select'
+import { useSnapshot } from 'valtio'
+
+import Select from '@/components/Select'
+import { HdrFormat, hdrModes } from '@/types/compression'
+import { videoProxy } from '../-state'
+
+const formatLabels: Record<HdrFormat, string> = {
+  pq: 'HDR10 / PQ',
+  hlg: 'HLG',
+}
+
+const modeLabels: Record<keyof typeof hdrModes, string> = {
+  tonemap: 'Convert to SDR (BT.709)',
+  preserve: 'Keep HDR (10-bit H.265, VP9 for WebM)',
+}
+
+type HdrSettingsProps = {
+  isDisabled: boolean
+}
+
+function HdrSettings({ isDisabled }: HdrSettingsProps) {
+  const {
+    state: {
+      mode,
+      hdr,
+      config: { hdr: hdrMode, convertToExtension },
+    },
+  } = useSnapshot(videoProxy)
+
+  // A single SDR video has nothing to convert.
+  if (mode === 'single' && !hdr) {
+    return null
+  }
+
+  return (
+    <div className="my-2">
+      <Select
+        fullWidth
+        label={hdr ? `HDR Video (${formatLabels[hdr]}):` : 'HDR Videos:'}
+        size="sm"
+        selectedKeys={[hdrMode]}
+        // avi only stores h264, which cannot keep HDR.
+        disabledKeys={convertToExtension === 'avi' ? [hdrModes.preserve] : []}
+        onChange={(evt) => {
+          const value = evt?.target?.value
+          if (!value || !(value in hdrModes)) return
+          videoProxy.state.config.hdr = value as keyof typeof hdrModes
+        }}
+        selectionMode="single"
+        isDisabled={isDisabled}
+        classNames={{
+          label: '!text-gray-600 dark:!text-gray-400 text-sm',
+        }}
+      >
+        {(Object.keys(modeLabels) as (keyof typeof hdrModes)[]).map(
+          (option) => (
+            <SelectItem key={option} value={option}>
+              {modeLabels[option]}
+            </SelectItem>
+          ),
+        )}
+      </Select>
+    </div>
+  )
+}
+
+export default HdrSettings
@@ -0,0 +1,81 @@
+import { SelectItem } from '@heroui/select'
+import { useSnapshot } from 'valtio'
+
+import Select from '@/components/Select'
+import Switch from '@/components/Switch'
+import Tooltip from '@/components/Tooltip'
+import { MetadataOptions, metadataModes } from '@/types/compression'
+import { videoProxy } from '../-state'
+
+const modeLabels: Record<keyof typeof metadataModes, string> = {
+  default: 'Default',
+  preserve: 'Preserve all (tags and chapters)',
+  strip: 'Strip all (GPS, device and other tags)',
+}
+
+type MetadataSettingsProps = {
+  isDisabled: boolean
+}
+
+function MetadataSettings({ isDisabled }: MetadataSettingsProps) {
+  const {
+    state: {
+      config: { metadata },
+    },
+  } = useSnapshot(videoProxy)
+
+  const update = (changes: Partial<MetadataOptions>) => {
+    videoProxy.state.config.metadata = {
+      ...videoProxy.state.config.metadata,
+      ...changes,
+    }
+  }
+
+  return (
+    <div className="my-2 space-y-2">
+      <Select
+        fullWidth
+        label="Metadata:"
+        size="sm"
+        selectedKeys={[metadata.mode]}
+        onChange={(evt) => {
+          const value = evt?.target?.value
+          if (!value || !(value in metadataModes)) return
+          update({ mode: value as keyof typeof metadataModes })
+        }}
+        selectionMode="single"
+        isDisabled={isDisabled}
+        classNames={{
+          label: '!text-gray-600 dark:!text-gray-400 text-sm',
+        }}
+      >
+        {(Object.keys(modeLabels) as (keyof typeof metadataModes)[]).map(
+          (mode) => (
+            <SelectItem key={mode} value={mode}>
+              {modeLabels[mode]}
+            </SelectItem>
+          ),
+        )}
+      </Select>
+      <Switch
+        isSelected={metadata.copyFileTimes}
+        onValueChange={() => {
+          update({ copyFileTimes: !metadata.copyFileTimes })
+        }}
+        isDisabled={isDisabled}
+      >
+        <Tooltip
+          content="Gives the compressed video the modified and accessed dates of the original."
+          placement="top"
+          delay={500}
+        >
+          <p className="text-gray-600 dark:text-gray-400 text-sm mr-2 w-full cursor-help">
+            Keep File Dates
+          </p>
+        </Tooltip>
+      </Switch>
+    </div>
+  )
+}
+
+export default MetadataSettings
@@ -1,4 +1,5 @@
 import { core } from '@tauri-apps/api'
+import cloneDeep from 'lodash/cloneDeep'
 import React from 'react'
 import { snapshot, useSnapshot } from 'valtio'
 
@@ -91,6 +92,9 @@ function QualityPreviewAction({ mode }: QualityPreviewActionProps) {
           ? state.config.presetName
           : null,
         shouldMuteVideo: state.config.shouldMuteVideo,
+        audio: state.config.audio,
+        streams: state.config.streams,
+        hdr: state.config.hdr,
         quality: state.config.shouldEnableQuality ? state.config.quality : null,
         dimensions: state.config.shouldEnableCustomDimensions
           ? state.config.customDimensions
@@ -111,12 +115,17 @@ function QualityPreviewAction({ mode }: QualityPreviewActionProps) {
         const transformsHistory = state.config.shouldTransformVideo
           ? (state.config.transformVideoConfig?.transformsHistory ?? [])
           : []
+        const trimRanges = state.config.shouldTrimVideo
+          ? (state.config.trimRanges ?? [])
+          : []
 
         return JSON.stringify({
           mode,
           videoPath: state.pathRaw ?? '',
           convertToExtension,
           transformsHistory,
+          trimRanges,
+          subtitles: state.config.subtitles,
           ...common,
         })
       }
@@ -191,6 +200,10 @@ function QualityPreviewAction({ mode }: QualityPreviewActionProps) {
             ? state.config.presetName
             : null,
           shouldMuteVideo: state.config.shouldMuteVideo,
+          audio: { ...state.config.audio },
+          streams: cloneDeep(state.config.streams),
+          subtitles: cloneDeep(state.config.subtitles),
+          hdr: state.config.hdr,
           previewSeconds: PREVIEW_SECONDS,
           ...(state.config.shouldEnableQuality
             ? { quality: state.config.quality as number }
@@ -206,6 +219,9 @@ function QualityPreviewAction({ mode }: QualityPreviewActionProps) {
           ...(transformsHistory && transformsHistory.length > 0
             ? { transformsHistory }
             : {}),
+          ...(state.config.shouldTrimVideo
+            ? { trimRanges: [...(state.config.trimRanges ?? [])] }
+            : {}),
         })
 
         const item: QualityPreviewItem = {
@@ -260,6 +276,9 @@ function QualityPreviewAction({ mode }: QualityPreviewActionProps) {
               ? state.config.presetName
               : null,
             shouldMuteVideo: state.config.shouldMuteVideo,
+            audio: { ...state.config.audio },
+            streams: cloneDeep(state.config.streams),
+            hdr: state.config.hdr,
             previewSeconds: PREVIEW_SECONDS,
             ...(state.config.shouldEnableQuality
               ? { quality: state.config.quality as number }
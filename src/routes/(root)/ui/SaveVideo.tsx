@@ -32,7 +32,11 @@ function Success() {
           isSaving: true,
           isSaved: false,
         }
-        await moveFile(compressedVideo?.pathRaw as string, pathToSave)
+        await moveFile(
+          compressedVideo?.pathRaw as string,
+          pathToSave,
+          snapshot(videoProxy).state.config.metadata.copyFileTimes,
+        )
         videoProxy.state.compressedVideo = {
           ...(snapshot(videoProxy).state.compressedVideo ?? {}),
           savedPath: pathToSave,
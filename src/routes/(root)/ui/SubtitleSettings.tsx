@@ -0,0 +1,158 @@
+import { SelectItem } from '@heroui/select'
+import { open } from '@tauri-apps/plugin-dialog'
+import { useSnapshot } from 'valtio'
+
+import Button from '@/components/Button'
+import Icon from '@/components/Icon'
+import Select from '@/components/Select'
+import { toast } from '@/components/Toast'
+import {
+  SubtitleBurnIn,
+  SubtitleOptions,
+  subtitleFileExtensions,
+} from '@/types/compression'
+import { videoProxy } from '../-state'
+
+const NONE_KEY = 'none'
+
+function fileName(path: string) {
+  return path.split(/[\\/]/).pop() ?? path
+}
+
+// Select keys of the burn in options, e.g. `stream:1` or `file:/subs/en.srt`.
+function burnInKey(burnIn: SubtitleBurnIn | null) {
+  return burnIn ? `${burnIn.type}:${burnIn.value}` : NONE_KEY
+}
+
+type SubtitleSettingsProps = {
+  isDisabled: boolean
+}
+
+function SubtitleSettings({ isDisabled }: SubtitleSettingsProps) {
+  const {
+    state: {
+      mediaStreams,
+      config: { subtitles },
+    },
+  } = useSnapshot(videoProxy)
+
+  const subtitleStreams =
+    mediaStreams?.filter((stream) => stream.kind === 'subtitle') ?? []
+
+  const update = (changes: Partial<SubtitleOptions>) => {
+    videoProxy.state.config.subtitles = {
+      ...videoProxy.state.config.subtitles,
+      ...changes,
+    }
+  }
+
+  const handleAddFiles = async () => {
+    try {
+      const result = await open({
+        directory: false,
+        multiple: true,
+        title: 'Select subtitle files',
+        filters: [{ name: 'subtitles', extensions: subtitleFileExtensions }],
+      })
+      const paths = Array.isArray(result) ? result : result ? [result] : []
+      update({
+        files: [
+          ...subtitles.files,
+          ...paths.filter((path) => !subtitles.files.includes(path)),
+        ],
+      })
+    } catch {
+      toast.error('Could not select subtitle files.')
+    }
+  }
+
+  const handleRemoveFile = (path: string) => {
+    const burnsFile =
+      subtitles.burnIn?.type === 'file' && subtitles.burnIn.value === path
+    update({
+      files: subtitles.files.filter((file) => file !== path),
+      burnIn: burnsFile ? null : subtitles.burnIn,
+    })
+  }
+
+  const burnInOptions: { burnIn: SubtitleBurnIn; label: string }[] = [
+    ...subtitleStreams.map((stream, position) => ({
+      burnIn: { type: 'stream' as const, value: position },
+      label: `Track ${position + 1}: ${[stream.codecName, stream.language]
+        .filter(Boolean)
+        .join(', ')}`,
+    })),
+    ...subtitles.files.map((path) => ({
+      burnIn: { type: 'file' as const, value: path },
+      label: fileName(path),
+    })),
+  ]
+
+  return (
+    <div className="my-2 space-y-2">
+      <div className="flex items-center justify-between">
+        <p className="text-gray-600 dark:text-gray-400 text-sm">
+          Subtitle Files
+        </p>
+        <Button size="sm" onPress={handleAddFiles} isDisabled={isDisabled}>
+          Add
+        </Button>
+      </div>
+      {subtitles.files.map((path) => (
+        <div key={path} className="flex items-center justify-between gap-2">
+          <p className="text-sm text-gray-600 dark:text-gray-400 truncate">
+            {fileName(path)}
+          </p>
+          <Button
+            isIconOnly
+            size="sm"
+            variant="flat"
+            onPress={() => handleRemoveFile(path)}
+            isDisabled={isDisabled}
+          >
+            <Icon name="cross" />
+          </Button>
+        </div>
+      ))}
+      {burnInOptions.length > 0 ? (
+        <Select
+          fullWidth
+          label="Burn In Subtitles:"
+          size="sm"
+          selectedKeys={[burnInKey(subtitles.burnIn)]}
+          onChange={(evt) => {
+            const value = evt?.target?.value
+            if (!value) return
+            update({
+              burnIn:
+                burnInOptions.find(
+                  (option) => burnInKey(option.burnIn) === value,
+                )?.burnIn ?? null,
+            })
+          }}
+          selectionMode="single"
+          isDisabled={isDisabled}
+          classNames={{
+            label: '!text-gray-600 dark:!text-gray-400 text-sm',
+          }}
+        >
+          {[
+            <SelectItem key={NONE_KEY} value={NONE_KEY}>
+              None
+            </SelectItem>,
+            ...burnInOptions.map((option) => (
+              <SelectItem
+                key={burnInKey(option.burnIn)}
+                value={burnInKey(option.burnIn)}
+              >
+                {option.label}
+              </SelectItem>
+            )),
+          ]}
+        </Select>
+      ) : null}
+    </div>
+  )
+}
+
+export default SubtitleSettings
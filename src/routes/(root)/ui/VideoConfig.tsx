@@ -1,6 +1,7 @@
 import { SelectItem } from '@heroui/select'
 import { core } from '@tauri-apps/api'
 import { AnimatePresence, motion } from 'framer-motion'
+import cloneDeep from 'lodash/cloneDeep'
 import React from 'react'
 import { snapshot, useSnapshot } from 'valtio'
 
@@ -12,26 +13,33 @@ import Select from '@/components/Select'
 import Spinner from '@/components/Spinner'
 import Switch from '@/components/Switch'
 import { toast } from '@/components/Toast'
-import { compressVideo } from '@/tauri/commands/ffmpeg'
 import { getFileMetadata } from '@/tauri/commands/fs'
+import { runCompressionJobs } from '@/tauri/commands/queue'
 import { extensions } from '@/types/compression'
 import { zoomInTransition } from '@/utils/animation'
+import { getErrorMessage, isCancelledError } from '@/utils/ffmpeg-error'
 import { formatBytes } from '@/utils/fs'
 import { cn } from '@/utils/tailwind'
+import AudioSettings from './AudioSettings'
 import CancelCompression from './CancelCompression'
 import Compressing from './Compressing'
 import CompressionPreset from './CompressionPreset'
 import CompressionQuality from './CompressionQuality'
 import FileName from './FileName'
+import HdrSettings from './HdrSettings'
+import MetadataSettings from './MetadataSettings'
 import PreviewVideo from './PreviewVideo'
 import QualityPreviewAction from './QualityPreviewAction'
 import SaveVideo from './SaveVideo'
 import Success from './Success'
 import styles from './styles.module.css'
+import SubtitleSettings from './SubtitleSettings'
 import TransformVideo from './TransformVideo'
 import VideoDimensions from './VideoDimensions'
 import VideoFPS from './VideoFPS'
+import VideoStreams from './VideoStreams'
 import VideoThumbnail from './VideoThumbnail'
+import VideoTrim from './VideoTrim'
 import { videoProxy } from '../-state'
 import { isConvertToExtension } from '../-types'
 
@@ -60,6 +68,13 @@ function VideoConfig() {
   const handleCompression = async () => {
     const videoSnapshot = snapshot(videoProxy)
     if (videoSnapshot.state.isCompressing) return
+    if (
+      videoSnapshot.state.config.shouldTrimVideo &&
+      !videoSnapshot.state.config.trimRanges?.length
+    ) {
+      toast.error('Enter the parts of the video to keep.')
+      return
+    }
     try {
       videoProxy.takeSnapshot('beforeCompressionStarted')
       videoProxy.state.isCompressing = true
@@ -77,38 +92,53 @@ function VideoConfig() {
           ? (videoSnapshot.state.extension ?? 'mp4')
           : (videoSnapshot.state?.config?.convertToExtension ?? 'mp4')
 
-      const result = await compressVideo({
-        videoPath: videoSnapshot.state.pathRaw as string,
-        convertToExtension,
-        presetName: !videoSnapshot?.state?.config?.shouldDisableCompression
-          ? presetName
-          : null,
-        videoId,
-        shouldMuteVideo,
-        ...(videoSnapshot?.state?.config?.shouldEnableQuality
-          ? { quality: videoSnapshot.state?.config?.quality as number }
-          : {}),
-        ...(videoSnapshot.state.config.shouldEnableCustomDimensions &&
-        videoSnapshot.state.config.customDimensions
-          ? { dimensions: videoSnapshot.state.config.customDimensions }
-          : {}),
-        ...(videoSnapshot.state.config.shouldEnableCustomFPS &&
-        typeof videoSnapshot.state.config.customFPS === 'number'
-          ? { fps: videoSnapshot.state.config.customFPS.toString() }
-          : {}),
-        ...(videoSnapshot.state.config.shouldTransformVideo
-          ? {
-              transformsHistory: [
-                ...(videoSnapshot.state.config.transformVideoConfig
-                  ?.transformsHistory ?? []),
-              ],
-            }
-          : {}),
-      })
-      if (!result) {
-        throw new Error()
+      const [job] = await runCompressionJobs([
+        {
+          id: videoId ?? crypto.randomUUID(),
+          videoPath: videoSnapshot.state.pathRaw as string,
+          convertToExtension,
+          presetName: !videoSnapshot?.state?.config?.shouldDisableCompression
+            ? presetName
+            : null,
+          shouldMuteVideo,
+          audio: { ...videoSnapshot.state.config.audio },
+          streams: cloneDeep(videoSnapshot.state.config.streams),
+          subtitles: cloneDeep(videoSnapshot.state.config.subtitles),
+          metadata: { ...videoSnapshot.state.config.metadata },
+          hdr: videoSnapshot.state.config.hdr,
+          ...(videoSnapshot?.state?.config?.shouldEnableQuality
+            ? { quality: videoSnapshot.state?.config?.quality as number }
+            : {}),
+          ...(videoSnapshot.state.config.shouldEnableCustomDimensions &&
+          videoSnapshot.state.config.customDimensions
+            ? { dimensions: videoSnapshot.state.config.customDimensions }
+            : {}),
+          ...(videoSnapshot.state.config.shouldEnableCustomFPS &&
+          typeof videoSnapshot.state.config.customFPS === 'number'
+            ? { fps: videoSnapshot.state.config.customFPS.toString() }
+            : {}),
+          ...(videoSnapshot.state.config.shouldTransformVideo
+            ? {
+                transformsHistory: [
+                  ...(videoSnapshot.state.config.transformVideoConfig
+                    ?.transformsHistory ?? []),
+                ],
+              }
+            : {}),
+          ...(videoSnapshot.state.config.shouldTrimVideo
+            ? {
+                trimRanges: [...(videoSnapshot.state.config.trimRanges ?? [])],
+              }
+            : {}),
+        },
+      ])
+      if (job.status === 'cancelled') {
+        return
+      }
+      if (job.status !== 'completed' || !job.result) {
+        throw job.error ?? new Error()
       }
-      const compressedVideoMetadata = await getFileMetadata(result?.filePath)
+      const compressedVideoMetadata = await getFileMetadata(job.result.filePath)
       if (!compressedVideoMetadata) {
         throw new Error()
       }
@@ -130,8 +160,10 @@ function VideoConfig() {
         extension: compressedVideoMetadata?.extension,
       }
     } catch (error) {
-      if (error !== 'CANCELLED') {
-        toast.error('Something went wrong during compression.')
+      if (!isCancelledError(error)) {
+        toast.error(
+          getErrorMessage(error, 'Something went wrong during compression.'),
+        )
         videoProxy.timeTravel('beforeCompressionStarted')
       }
     }
@@ -256,6 +288,21 @@ function VideoConfig() {
                   </div>
                 </Switch>
               </div>
+              <AudioSettings
+                isDisabled={isCompressing || isCompressionSuccessful}
+              />
+              <VideoStreams
+                isDisabled={isCompressing || isCompressionSuccessful}
+              />
+              <SubtitleSettings
+                isDisabled={isCompressing || isCompressionSuccessful}
+              />
+              <MetadataSettings
+                isDisabled={isCompressing || isCompressionSuccessful}
+              />
+              <HdrSettings
+                isDisabled={isCompressing || isCompressionSuccessful}
+              />
               <Divider className="my-2" />
             </>
 
@@ -277,6 +324,10 @@ function VideoConfig() {
                 <Divider className="my-2" />
               </>
             ) : null}
+            <>
+              <VideoTrim />
+              <Divider className="my-2" />
+            </>
             <>
               <div className="mt-8">
                 <Select
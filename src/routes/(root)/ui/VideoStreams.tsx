@@ -0,0 +1,220 @@
+import { SelectItem } from '@heroui/select'
+import { useSnapshot } from 'valtio'
+
+import Checkbox from '@/components/Checkbox'
+import Select from '@/components/Select'
+import Switch from '@/components/Switch'
+import Tooltip from '@/components/Tooltip'
+import { StreamInfo, StreamOptions, StreamSelection } from '@/types/compression'
+import { videoProxy } from '../-state'
+
+type StreamKind = 'audio' | 'subtitles'
+
+// Positions among the streams of one kind that `selection` keeps.
+function selectedPositions(
+  selection: StreamSelection,
+  streams: readonly StreamInfo[],
+  defaultPositions: number[],
+): number[] {
+  switch (selection.type) {
+    case 'default':
+      return defaultPositions
+    case 'all':
+      return streams.map((_, position) => position)
+    case 'pick':
+      return streams
+        .map((stream, position) => ({ stream, position }))
+        .filter(({ stream, position }) =>
+          selection.value.some((selector) =>
+            selector.type === 'index'
+              ? selector.value === position
+              : stream.language?.toLowerCase() ===
+                selector.value.toLowerCase(),
+          ),
+        )
+        .map(({ position }) => position)
+  }
+}
+
+function describeStream(stream: StreamInfo, position: number) {
+  const parts = [stream.codecName ?? stream.kind]
+  if (stream.channelLayout) {
+    parts.push(stream.channelLayout)
+  }
+  if (stream.language) {
+    parts.push(stream.language)
+  }
+  const title = stream.title ? ` "${stream.title}"` : ''
+  return `Track ${position + 1}: ${parts.join(', ')}${title}`
+}
+
+type VideoStreamsProps = {
+  isDisabled: boolean
+}
+
+function VideoStreams({ isDisabled }: VideoStreamsProps) {
+  const {
+    state: {
+      mode,
+      mediaStreams,
+      config: { shouldMuteVideo, streams },
+    },
+  } = useSnapshot(videoProxy)
+
+  const update = (changes: Partial<StreamOptions>) => {
+    videoProxy.state.config.streams = {
+      ...videoProxy.state.config.streams,
+      ...changes,
+    }
+  }
+
+  const selectClassNames = {
+    label: '!text-gray-600 dark:!text-gray-400 text-sm',
+  }
+
+  const mixSwitch = (
+    <Switch
+      isSelected={streams.mixAudio}
+      onValueChange={() => {
+        update({ mixAudio: !streams.mixAudio })
+      }}
+      isDisabled={isDisabled}
+    >
+      <Tooltip
+        content="Mixes the kept audio tracks into one, e.g. a microphone and a desktop track."
+        placement="top"
+        delay={500}
+      >
+        <p className="text-gray-600 dark:text-gray-400 text-sm mr-2 w-full cursor-help">
+          Mix Audio Tracks
+        </p>
+      </Tooltip>
+    </Switch>
+  )
+
+  // Without the streams of a single video, only whole kinds can be picked.
+  if (mode === 'batch' || !mediaStreams) {
+    return (
+      <div className="my-2 space-y-2">
+        {!shouldMuteVideo ? (
+          <Select
+            fullWidth
+            label="Audio Tracks:"
+            size="sm"
+            selectedKeys={[streams.audio.type === 'all' ? 'all' : 'default']}
+            onChange={(evt) => {
+              const value = evt?.target?.value
+              if (!value) return
+              update({
+                audio: { type: value === 'all' ? 'all' : 'default' },
+              })
+            }}
+            selectionMode="single"
+            isDisabled={isDisabled}
+            classNames={selectClassNames}
+          >
+            <SelectItem key="default" value="default">
+              One track
+            </SelectItem>
+            <SelectItem key="all" value="all">
+              All tracks
+            </SelectItem>
+          </Select>
+        ) : null}
+        {!shouldMuteVideo && streams.audio.type === 'all' ? mixSwitch : null}
+        <Select
+          fullWidth
+          label="Subtitles:"
+          size="sm"
+          selectedKeys={[streams.subtitles.type === 'all' ? 'all' : 'default']}
+          onChange={(evt) => {
+            const value = evt?.target?.value
+            if (!value) return
+            update({
+              subtitles: { type: value === 'all' ? 'all' : 'default' },
+            })
+          }}
+          selectionMode="single"
+          isDisabled={isDisabled}
+          classNames={selectClassNames}
+        >
+          <SelectItem key="default" value="default">
+            Default
+          </SelectItem>
+          <SelectItem key="all" value="all">
+            All subtitles
+          </SelectItem>
+        </Select>
+      </div>
+    )
+  }
+
+  const streamsOf = {
+    audio: mediaStreams.filter((stream) => stream.kind === 'audio'),
+    subtitles: mediaStreams.filter((stream) => stream.kind === 'subtitle'),
+  }
+  const positionsOf = {
+    audio: selectedPositions(
+      streams.audio,
+      streamsOf.audio,
+      streamsOf.audio.length > 0 ? [0] : [],
+    ),
+    subtitles: selectedPositions(streams.subtitles, streamsOf.subtitles, []),
+  }
+
+  const toggle = (kind: StreamKind, position: number) => {
+    const current = positionsOf[kind]
+    const next = current.includes(position)
+      ? current.filter((selected) => selected !== position)
+      : [...current, position].sort((a, b) => a - b)
+    // Muting removes the audio, one track is always kept here.
+    if (kind === 'audio' && next.length === 0) return
+
+    let selection: StreamSelection
+    if (next.length === 0) {
+      selection = { type: 'default' }
+    } else if (next.length === streamsOf[kind].length) {
+      selection = { type: 'all' }
+    } else {
+      selection = {
+        type: 'pick',
+        value: next.map((selected) => ({ type: 'index', value: selected })),
+      }
+    }
+    update({ [kind]: selection })
+  }
+
+  const showAudio = !shouldMuteVideo && streamsOf.audio.length > 1
+  if (!showAudio && streamsOf.subtitles.length === 0) {
+    return null
+  }
+
+  return (
+    <div className="my-2 space-y-2">
+      {(['audio', 'subtitles'] as const).map((kind) =>
+        (kind === 'audio' ? showAudio : streamsOf.subtitles.length > 0) ? (
+          <div key={kind} className="flex flex-col gap-1">
+            <p className="text-gray-600 dark:text-gray-400 text-sm">
+              {kind === 'audio' ? 'Audio Tracks' : 'Subtitles'}
+            </p>
+            {streamsOf[kind].map((stream, position) => (
+              <Checkbox
+                key={stream.index}
+                isSelected={positionsOf[kind].includes(position)}
+                onValueChange={() => toggle(kind, position)}
+                isDisabled={isDisabled}
+              >
+                <span className="text-sm text-gray-600 dark:text-gray-400">
+                  {describeStream(stream, position)}
+                </span>
+              </Checkbox>
+            ))}
+          </div>
+        ) : null,
+      )}
+      {showAudio && positionsOf.audio.length > 1 ? mixSwitch : null}
+    </div>
+  )
+}
+
+export default VideoStreams
@@ -0,0 +1,92 @@
+import { AnimatePresence, motion } from 'framer-motion'
+import React from 'react'
+import { useSnapshot } from 'valtio'
+
+import Switch from '@/components/Switch'
+import Tooltip from '@/components/Tooltip'
+import { slideDownTransition } from '@/utils/animation'
+import { formatTrimRanges, parseTrimRanges } from '@/utils/string'
+import { videoProxy } from '../-state'
+
+function VideoTrim() {
+  const {
+    state: {
+      isCompressing,
+      isCompressionSuccessful,
+      config: { shouldTrimVideo, trimRanges },
+      videoDurationMilliseconds,
+    },
+  } = useSnapshot(videoProxy)
+
+  const [rangesText, setRangesText] = React.useState(() =>
+    formatTrimRanges(trimRanges ?? []),
+  )
+  const [error, setError] = React.useState<string | null>(null)
+
+  const handleChange = (value: string) => {
+    setRangesText(value)
+    try {
+      const ranges = parseTrimRanges(value)
+      const durationSeconds =
+        typeof videoDurationMilliseconds === 'number'
+          ? videoDurationMilliseconds / 1000
+          : null
+      if (
+        durationSeconds !== null &&
+        ranges.some((range) => range.startSeconds >= durationSeconds)
+      ) {
+        throw new Error('A range starts after the end of the video.')
+      }
+      videoProxy.state.config.trimRanges = ranges
+      setError(null)
+    } catch (err) {
+      videoProxy.state.config.trimRanges = []
+      setError(err instanceof Error ? err.message : 'Invalid ranges.')
+    }
+  }
+
+  return (
+    <>
+      <Switch
+        isSelected={shouldTrimVideo}
+        onValueChange={() => {
+          videoProxy.state.config.shouldTrimVideo = !shouldTrimVideo
+        }}
+        isDisabled={isCompressing || isCompressionSuccessful}
+      >
+        <Tooltip
+          content="Keep only parts of the video. Each range is cut at the exact frame and the ranges are joined in order."
+          placement="top"
+          delay={500}
+        >
+          <p className="text-gray-600 dark:text-gray-400 text-sm mr-2 w-full cursor-help">
+            Trim
+          </p>
+        </Tooltip>
+      </Switch>
+      <AnimatePresence mode="wait">
+        {shouldTrimVideo ? (
+          <motion.div {...slideDownTransition} className="mt-2 space-y-1">
+            <input
+              type="text"
+              value={rangesText}
+              onChange={(evt) => handleChange(evt.target.value)}
+              placeholder="Ranges to keep, e.g. 0:10-0:45, 1:20-2:00"
+              className="w-full rounded-xl border border-zinc-200 dark:border-zinc-800 bg-transparent px-3 py-2 text-sm text-gray-700 dark:text-gray-200"
+              disabled={isCompressing || isCompressionSuccessful}
+            />
+            {error ? (
+              <p className="text-xs text-red-500">{error}</p>
+            ) : (
+              <p className="text-xs text-gray-500">
+                Leave the end empty (1:20-) to keep the rest of the video.
+              </p>
+            )}
+          </motion.div>
+        ) : null}
+      </AnimatePresence>
+    </>
+  )
+}
+
+export default VideoTrim
@@ -1,47 +1,113 @@
 import { core } from '@tauri-apps/api'
 
 import {
+  AudioOptions,
   CompressionResult,
+  EncodeOptions,
+  FfmpegCapabilities,
+  MediaInfo,
+  MetadataOptions,
   QualityPreviewResult,
+  StreamOptions,
+  SubtitleOptions,
+  TrimRange,
   VideoInfo,
   VideoThumbnail,
   VideoTransformsHistory,
 } from '@/types/compression'
 import { FileMetadata } from '@/types/fs'
 
-export function compressVideo({
-  videoPath,
+export type EncodeOptionsInput = {
+  convertToExtension?: string
+  videoCodec?: EncodeOptions['videoCodec']
+  presetName?: string | null
+  shouldMuteVideo?: boolean
+  audio?: AudioOptions
+  streams?: StreamOptions
+  subtitles?: SubtitleOptions
+  metadata?: MetadataOptions
+  hdr?: EncodeOptions['hdr']
+  quality?: number
+  targetSizeMb?: number
+  dimensions?: readonly [number, number]
+  fps?: string
+  transformsHistory?: VideoTransformsHistory[]
+  trimRanges?: TrimRange[]
+}
+
+// A missing preset means compression tuning is disabled, which uses the fast path.
+export function toEncodeOptions({
   convertToExtension,
+  videoCodec,
   presetName,
-  videoId,
   shouldMuteVideo = false,
-  quality = 101, // quality should be within 0-100, but if you supply out of bound value, backend will automatically select optimum quality
+  audio,
+  streams,
+  subtitles,
+  metadata,
+  hdr = 'tonemap',
+  quality,
+  targetSizeMb,
   dimensions,
   fps,
   transformsHistory,
+  trimRanges,
+}: EncodeOptionsInput): EncodeOptions {
+  const container = (convertToExtension ?? 'mp4') as EncodeOptions['container']
+  return {
+    container,
+    // Keeping HDR needs a 10-bit codec, webm already defaults to vp9.
+    videoCodec:
+      videoCodec ??
+      (hdr === 'preserve' && container !== 'webm' ? 'h265' : null),
+    preset: (presetName ?? 'thunderbolt') as EncodeOptions['preset'],
+    // quality should be within 0-100, otherwise backend will automatically select optimum quality
+    quality:
+      typeof quality === 'number' && quality >= 0 && quality <= 100
+        ? quality
+        : null,
+    targetSizeMb: targetSizeMb ?? null,
+    dimensions: dimensions
+      ? [Math.round(dimensions[0]), Math.round(dimensions[1])]
+      : null,
+    fps: fps ? Number(fps) : null,
+    muteAudio: shouldMuteVideo,
+    audio: audio ?? {
+      codec: null,
+      bitrateKbps: null,
+      channels: null,
+      sampleRate: null,
+      normalize: false,
+    },
+    streams: streams ?? {
+      audio: { type: 'default' },
+      subtitles: { type: 'default' },
+      mixAudio: false,
+    },
+    subtitles: subtitles ?? { files: [], burnIn: null },
+    metadata: metadata ?? { mode: 'default', copyFileTimes: false },
+    hdr,
+    transforms: [
+      ...(transformsHistory ?? []),
+      ...(trimRanges && trimRanges.length > 0
+        ? [{ type: 'trim' as const, value: trimRanges }]
+        : []),
+    ],
+  }
+}
+
+export function compressVideo({
+  videoPath,
+  videoId,
+  ...options
 }: {
   videoPath: string
-  convertToExtension?: string
-  presetName?: string | null
   videoId?: string | null
-  shouldMuteVideo?: boolean
-  quality?: number
-  dimensions?: readonly [number, number]
-  fps?: string
-  transformsHistory?: VideoTransformsHistory[]
-}): Promise<CompressionResult> {
+} & EncodeOptionsInput): Promise<CompressionResult> {
   return core.invoke('compress_video', {
     videoPath,
-    convertToExtension: convertToExtension ?? 'mp4',
-    presetName,
     videoId,
-    shouldMuteVideo,
-    quality,
-    fps,
-    ...(dimensions
-      ? { dimensions: [Math.round(dimensions[0]), Math.round(dimensions[1])] }
-      : {}),
-    transformsHistory,
+    options: toEncodeOptions(options),
   })
 }
 
@@ -59,38 +125,25 @@ export function getVideoInfo(videoPath: string): Promise<VideoInfo | null> {
   return core.invoke('get_video_info', { videoPath })
 }
 
+export function probeMedia(videoPath: string): Promise<MediaInfo> {
+  return core.invoke('probe_media', { videoPath })
+}
+
+export function getFfmpegCapabilities(): Promise<FfmpegCapabilities> {
+  return core.invoke('get_ffmpeg_capabilities')
+}
+
 export function generateQualityPreview({
   videoPath,
-  convertToExtension,
-  presetName,
-  shouldMuteVideo = false,
-  quality = 101,
-  dimensions,
-  fps,
-  transformsHistory,
   previewSeconds = 20,
+  ...options
 }: {
   videoPath: string
-  convertToExtension?: string
-  presetName?: string | null
-  shouldMuteVideo?: boolean
-  quality?: number
-  dimensions?: readonly [number, number]
-  fps?: string
-  transformsHistory?: VideoTransformsHistory[]
   previewSeconds?: number
-}): Promise<QualityPreviewResult> {
+} & EncodeOptionsInput): Promise<QualityPreviewResult> {
   return core.invoke('generate_quality_preview', {
     videoPath,
-    convertToExtension: convertToExtension ?? 'mp4',
-    presetName,
-    shouldMuteVideo,
-    quality,
-    fps,
     previewSeconds,
-    ...(dimensions
-      ? { dimensions: [Math.round(dimensions[0]), Math.round(dimensions[1])] }
-      : {}),
-    transformsHistory,
+    options: toEncodeOptions(options),
   })
 }
@@ -1,6 +1,11 @@
 import { core } from '@tauri-apps/api'
 
-import { FileMetadata, ResolveVideoFilesResult } from '@/types/fs'
+import { EncodeOptions } from '@/types/compression'
+import {
+  ConflictPolicy,
+  FileMetadata,
+  ResolveVideoFilesResult,
+} from '@/types/fs'
 
 export function getFileMetadata(filePath: string): Promise<FileMetadata> {
   return core.invoke('get_file_metadata', { filePath })
@@ -12,8 +17,12 @@ export function getImageDimension(
   return core.invoke('get_image_dimension', { imagePath })
 }
 
-export function moveFile(from: string, to: string): Promise<void> {
-  return core.invoke('move_file', { from, to })
+export function moveFile(
+  from: string,
+  to: string,
+  keepFileTimes = false,
+): Promise<void> {
+  return core.invoke('move_file', { from, to, keepFileTimes })
 }
 
 export function deleteFile(path: string): Promise<void> {
@@ -34,3 +43,38 @@ export function resolveVideoFiles(
 ): Promise<ResolveVideoFilesResult> {
   return core.invoke('resolve_video_files', { paths, recursive })
 }
+
+export function validateNameTemplate(template: string): Promise<void> {
+  return core.invoke('validate_name_template', { template })
+}
+
+// Resolves to `null` when the conflict policy skips the video.
+export function renderOutputPath({
+  videoPath,
+  outputDir,
+  template,
+  options,
+  index,
+  conflictPolicy = 'autoRename',
+  reserved = [],
+}: {
+  videoPath: string
+  outputDir: string
+  template: string
+  options: EncodeOptions
+  index: number
+  conflictPolicy?: ConflictPolicy
+  reserved?: string[]
+}): Promise<string | null> {
+  return core.invoke('render_output_path', {
+    request: {
+      videoPath,
+      outputDir,
+      template,
+      options,
+      index,
+      conflictPolicy,
+      reserved,
+    },
+  })
+}
@@ -0,0 +1,37 @@
+import { core } from '@tauri-apps/api'
+
+import { EncodeProfile } from '@/types/compression'
+
+export function listProfiles(): Promise<EncodeProfile[]> {
+  return core.invoke('list_profiles')
+}
+
+export function createProfile(
+  profile: EncodeProfile,
+): Promise<EncodeProfile[]> {
+  return core.invoke('create_profile', { profile })
+}
+
+export function updateProfile(
+  name: string,
+  profile: EncodeProfile,
+): Promise<EncodeProfile[]> {
+  return core.invoke('update_profile', { name, profile })
+}
+
+export function deleteProfile(name: string): Promise<EncodeProfile[]> {
+  return core.invoke('delete_profile', { name })
+}
+
+// Accepts .toml or .json files, profiles with the same name are replaced.
+export function importProfiles(path: string): Promise<EncodeProfile[]> {
+  return core.invoke('import_profiles', { path })
+}
+
+// Exports every profile when `names` is empty.
+export function exportProfiles(
+  path: string,
+  names: string[] = [],
+): Promise<void> {
+  return core.invoke('export_profiles', { path, names })
+}
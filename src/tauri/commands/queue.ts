@@ -0,0 +1,112 @@
+import { core, event } from '@tauri-apps/api'
+
+import { EncodeOptionsInput, toEncodeOptions } from '@/tauri/commands/ffmpeg'
+import {
+  CompressionJob,
+  CompressionQueueSnapshot,
+  CustomEvents,
+  JobStatus,
+} from '@/types/compression'
+
+type NewCompressionJob = {
+  id?: string | null
+  videoPath: string
+  outputPath?: string | null
+} & EncodeOptionsInput
+
+const finishedJobStatuses: JobStatus[] = ['completed', 'failed', 'cancelled']
+
+export function enqueueCompressionJobs(
+  jobs: NewCompressionJob[],
+): Promise<CompressionJob[]> {
+  return core.invoke('enqueue_compression_jobs', {
+    jobs: jobs.map(({ id, videoPath, outputPath, ...options }) => ({
+      id: id ?? null,
+      videoPath,
+      outputPath: outputPath ?? null,
+      options: toEncodeOptions(options),
+    })),
+  })
+}
+
+export function reorderCompressionJob(
+  jobId: string,
+  index: number,
+): Promise<void> {
+  return core.invoke('reorder_compression_job', { jobId, index })
+}
+
+export function pauseCompressionQueue(): Promise<void> {
+  return core.invoke('pause_compression_queue')
+}
+
+export function resumeCompressionQueue(): Promise<void> {
+  return core.invoke('resume_compression_queue')
+}
+
+export function cancelCompressionJob(jobId: string): Promise<void> {
+  return core.invoke('cancel_compression_job', { jobId })
+}
+
+export function retryCompressionJob(jobId: string): Promise<void> {
+  return core.invoke('retry_compression_job', { jobId })
+}
+
+export function resumeInterruptedCompressionJobs(): Promise<void> {
+  return core.invoke('resume_interrupted_compression_jobs')
+}
+
+export function setCompressionConcurrency(concurrency: number): Promise<void> {
+  return core.invoke('set_compression_concurrency', { concurrency })
+}
+
+export function clearFinishedCompressionJobs(): Promise<void> {
+  return core.invoke('clear_finished_compression_jobs')
+}
+
+export function getCompressionQueue(): Promise<CompressionQueueSnapshot> {
+  return core.invoke('get_compression_queue')
+}
+
+/**
+ * Queues the jobs and resolves with them once all have finished. `onUpdate`
+ * gets every status change of the jobs on the way.
+ */
+export async function runCompressionJobs(
+  jobs: (NewCompressionJob & { id: string })[],
+  onUpdate?: (job: CompressionJob) => void | Promise<void>,
+): Promise<CompressionJob[]> {
+  if (jobs.length === 0) return []
+
+  const ids = new Set(jobs.map((job) => job.id))
+  const finished = new Map<string, CompressionJob>()
+  const updates: (void | Promise<void>)[] = []
+  let resolveFinished: () => void = () => {}
+  const allFinished = new Promise<void>((resolve) => {
+    resolveFinished = resolve
+  })
+
+  // Listening first, as jobs can finish before the enqueue call returns.
+  const unlisten = await event.listen<CompressionJob>(
+    CustomEvents.CompressionJobUpdated,
+    (evt) => {
+      const job = evt?.payload
+      if (!job || !ids.has(job.id)) return
+      updates.push(onUpdate?.(job))
+      if (finishedJobStatuses.includes(job.status)) {
+        finished.set(job.id, job)
+        if (finished.size === ids.size) {
+          resolveFinished()
+        }
+      }
+    },
+  )
+  try {
+    await enqueueCompressionJobs(jobs)
+    await allFinished
+    await Promise.all(updates)
+  } finally {
+    unlisten()
+  }
+  return jobs.map((job) => finished.get(job.id) as CompressionJob)
+}
@@ -12,6 +12,29 @@ export const compressionPresets: {
   thunderbolt: 'thunderbolt',
 }
 
+export const videoCodecs: {
+  h264: 'h264'
+  h265: 'h265'
+  av1: 'av1'
+  vp9: 'vp9'
+} = {
+  h264: 'h264',
+  h265: 'h265',
+  av1: 'av1',
+  vp9: 'vp9',
+}
+
+// `copy` keeps the source audio stream as is.
+export const audioCodecs: {
+  copy: 'copy'
+  aac: 'aac'
+  opus: 'opus'
+} = {
+  copy: 'copy',
+  aac: 'aac',
+  opus: 'opus',
+}
+
 export type CompressionResult = {
   fileName: string
   filePath: string
@@ -27,13 +50,29 @@ export type QualityPreviewResult = {
 export enum CustomEvents {
   VideoCompressionProgress = 'VideoCompressionProgress',
   CancelInProgressCompression = 'CancelInProgressCompression',
+  CompressionJobUpdated = 'CompressionJobUpdated',
+  CompressionQueueUpdated = 'CompressionQueueUpdated',
+}
+
+export type EncodeProgress = {
+  percentage: number | null
+  etaSeconds: number | null
+  currentSize: number | null
+  projectedSize: number | null
+  frame: number | null
+  fps: number | null
+  bitrateKbps: number | null
+  speed: number | null
+  isFinished: boolean
 }
 
 export type VideoCompressionProgress = {
   videoId: string
   fileName: string
   currentDuration: string
-}
+  pass: number
+  totalPasses: number
+} & EncodeProgress
 
 export type VideoThumbnail = {
   id: string
@@ -41,10 +80,128 @@ export type VideoThumbnail = {
   filePath: string
 }
 
+// Transfer of an HDR video, `pq` for HDR10 and Dolby Vision.
+export type HdrFormat = 'pq' | 'hlg'
+
 export type VideoInfo = {
   duration: string
+  // As displayed, after the rotation of phone videos.
   dimensions: [number, number]
   fps: number
+  hdr: HdrFormat | null
+}
+
+export type StreamKind =
+  | 'video'
+  | 'audio'
+  | 'subtitle'
+  | 'data'
+  | 'attachment'
+  | 'unknown'
+
+export type StreamInfo = {
+  index: number
+  kind: StreamKind
+  codecName: string | null
+  codecLongName: string | null
+  profile: string | null
+  pixFmt: string | null
+  bitDepth: number | null
+  width: number | null
+  height: number | null
+  frameRate: number | null
+  colorRange: string | null
+  colorSpace: string | null
+  colorTransfer: string | null
+  colorPrimaries: string | null
+  bitRate: number | null
+  sampleRate: number | null
+  channels: number | null
+  channelLayout: string | null
+  language: string | null
+  title: string | null
+  rotation: number | null
+  durationSeconds: number | null
+  isDefault: boolean
+  isAttachedPicture: boolean
+  tags: Record<string, string>
+}
+
+export type MediaInfo = {
+  format: {
+    formatName: string | null
+    formatLongName: string | null
+    durationSeconds: number | null
+    startTimeSeconds: number | null
+    size: number | null
+    bitRate: number | null
+    tags: Record<string, string>
+  }
+  streams: StreamInfo[]
+  chapters: {
+    id: number
+    startSeconds: number
+    endSeconds: number
+    title: string | null
+  }[]
+}
+
+export type FfmpegCapabilities = {
+  encoders: { name: string; kind: StreamKind; description: string }[]
+  muxers: string[]
+  filters: string[]
+  videoCodecs: (keyof typeof videoCodecs)[]
+  containers: (keyof (typeof extensions)['video'])[]
+}
+
+export type FfmpegErrorKind =
+  | 'missingInput'
+  | 'unsupportedCodec'
+  | 'diskFull'
+  | 'permissionDenied'
+  | 'invalidFilter'
+  | 'decodingError'
+  | 'cancelled'
+  | 'ffmpegNotFound'
+  | 'invalidOptions'
+  | 'unknown'
+
+export type FieldError = {
+  field: string
+  message: string
+}
+
+export type FfmpegError = {
+  kind: FfmpegErrorKind
+  message: string
+  stderr: string | null
+  exitCode: number | null
+  fieldErrors: FieldError[]
+}
+
+export type JobStatus =
+  | 'queued'
+  | 'running'
+  | 'completed'
+  | 'failed'
+  | 'cancelled'
+  | 'interrupted'
+
+export type CompressionJob = {
+  id: string
+  videoPath: string
+  outputPath: string | null
+  options: EncodeOptions
+  status: JobStatus
+  attempts: number
+  result: CompressionResult | null
+  error: FfmpegError | null
+}
+
+export type CompressionQueueSnapshot = {
+  jobs: CompressionJob[]
+  concurrency: number
+  isPaused: boolean
 }
 
 export type VideoTransforms = {
@@ -53,6 +210,12 @@ export type VideoTransforms = {
   flip: { horizontal: boolean; vertical: boolean }
 }
 
+// `endSeconds: null` keeps the rest of the video.
+export type TrimRange = {
+  startSeconds: number
+  endSeconds: number | null
+}
+
 export type VideoTransformsHistory =
   | {
       type: 'crop'
@@ -60,3 +223,107 @@ export type VideoTransformsHistory =
     }
   | { type: 'rotate'; value: number }
   | { type: 'flip'; value: { horizontal: boolean; vertical: boolean } }
+  | { type: 'trim'; value: TrimRange[] }
+
+// `null` fields keep ffmpeg's defaults for the container.
+export type AudioOptions = {
+  codec: keyof typeof audioCodecs | null
+  bitrateKbps: number | null
+  channels: number | null
+  sampleRate: number | null
+  // EBU R128 loudness normalization, measured in a first pass.
+  normalize: boolean
+}
+
+export type StreamSelector =
+  | { type: 'index'; value: number }
+  | { type: 'language'; value: string }
+
+// `default` is ffmpeg's own pick. When the other kind is selected it keeps
+// the first audio stream and no subtitles.
+export type StreamSelection =
+  | { type: 'default' }
+  | { type: 'all' }
+  | { type: 'pick'; value: StreamSelector[] }
+
+export type StreamOptions = {
+  audio: StreamSelection
+  subtitles: StreamSelection
+  // Mixes the kept audio streams into one.
+  mixAudio: boolean
+}
+
+export const subtitleFileExtensions = ['srt', 'vtt', 'ass', 'ssa']
+
+// `stream` is a position among the video's subtitle streams, from 0.
+export type SubtitleBurnIn =
+  | { type: 'stream'; value: number }
+  | { type: 'file'; value: string }
+
+export type SubtitleOptions = {
+  // Subtitle files stored as streams after the kept ones.
+  files: string[]
+  burnIn: SubtitleBurnIn | null
+}
+
+// `default` keeps ffmpeg's copy of the main tags, `strip` also removes GPS
+// and device tags.
+export const metadataModes: {
+  default: 'default'
+  preserve: 'preserve'
+  strip: 'strip'
+} = {
+  default: 'default',
+  preserve: 'preserve',
+  strip: 'strip',
+}
+
+export type MetadataOptions = {
+  mode: keyof typeof metadataModes
+  // Gives the output the modification and access times of the source.
+  copyFileTimes: boolean
+}
+
+// Encoding of HDR videos, SDR videos are not affected. `preserve` needs
+// h265, av1 or vp9.
+export const hdrModes: {
+  tonemap: 'tonemap'
+  preserve: 'preserve'
+} = {
+  tonemap: 'tonemap',
+  preserve: 'preserve',
+}
+
+export type EncodeOptions = {
+  container: keyof (typeof extensions)['video']
+  videoCodec: keyof typeof videoCodecs | null
+  preset: keyof typeof compressionPresets
+  quality: number | null
+  targetSizeMb: number | null
+  dimensions: [number, number] | null
+  fps: number | null
+  muteAudio: boolean
+  audio: AudioOptions
+  streams: StreamOptions
+  subtitles: SubtitleOptions
+  metadata: MetadataOptions
+  hdr: keyof typeof hdrModes
+  transforms: VideoTransformsHistory[]
+}
+
+// `container: null` keeps the source container.
+export type EncodeProfile = {
+  name: string
+  container: EncodeOptions['container'] | null
+  videoCodec: EncodeOptions['videoCodec']
+  preset: EncodeOptions['preset']
+  quality: number | null
+  targetSizeMb: number | null
+  fps: number | null
+  dimensions: [number, number] | null
+  muteAudio: boolean
+  audio: AudioOptions
+  streams: StreamOptions
+  metadata: MetadataOptions
+  hdr: keyof typeof hdrModes
+}
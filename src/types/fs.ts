@@ -6,8 +6,17 @@ export type FileMetadata = {
   size: number
 }
 
+// What to do when an output file already exists. `autoRename` appends `_1`, `_2`, ...
+export type ConflictPolicy = 'overwrite' | 'skip' | 'autoRename'
+
+// `relativeDir` is the file's folder relative to the dropped folder it was
+// found in, empty for files dropped directly.
+export type ResolvedVideoFile = FileMetadata & {
+  relativeDir: string
+}
+
 export type ResolveVideoFilesResult = {
-  files: FileMetadata[]
+  files: ResolvedVideoFile[]
   invalidPaths: string[]
   skippedPaths: string[]
   ignoredCount: number
@@ -1,5 +1,5 @@
 import type { BatchItem } from '@/routes/(root)/-types'
-import type { FileMetadata } from '@/types/fs'
+import type { ResolvedVideoFile } from '@/types/fs'
 import { formatBytes } from '@/utils/fs'
 
 function createBatchId() {
@@ -9,10 +9,11 @@ function createBatchId() {
   return `batch_${Date.now()}_${Math.random().toString(16).slice(2)}`
 }
 
-export function createBatchItems(files: FileMetadata[]): BatchItem[] {
+export function createBatchItems(files: ResolvedVideoFile[]): BatchItem[] {
   return files.map((file) => ({
     id: createBatchId(),
     path: file.path,
+    relativeDir: file.relativeDir,
     fileName: file.fileName,
     extension: file.extension?.toLowerCase?.() ?? file.extension,
     sizeInBytes: file.size,
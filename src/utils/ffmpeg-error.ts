@@ -0,0 +1,22 @@
+import type { FfmpegError } from '@/types/compression'
+
+export function isFfmpegError(error: unknown): error is FfmpegError {
+  return (
+    typeof error === 'object' &&
+    error !== null &&
+    'kind' in error &&
+    'message' in error
+  )
+}
+
+export function isCancelledError(error: unknown) {
+  return isFfmpegError(error) && error.kind === 'cancelled'
+}
+
+export function getErrorMessage(error: unknown, fallback: string) {
+  if (isFfmpegError(error)) {
+    return error.message
+  }
+  const message = String(error ?? '')
+  return message || fallback
+}
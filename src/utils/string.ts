@@ -1,3 +1,5 @@
+import type { TrimRange } from '@/types/compression'
+
 /**
  *  Converts string duration to milliseconds
  *
@@ -17,3 +19,105 @@ export function convertDurationToMilliseconds(duration: string): number {
     return 0
   }
 }
+
+/**
+ *  Formats seconds as a short remaining time
+ *
+ * @param {number} seconds: Remaining seconds.
+ * @returns {string}: "MM:SS", or "HH:MM:SS" for an hour or more.
+ */
+export function formatRemainingTime(seconds: number): string {
+  const total = Math.max(0, Math.round(seconds))
+  const hours = Math.floor(total / 3600)
+  const minutes = Math.floor((total % 3600) / 60)
+  const rest = total % 60
+  const pad = (value: number) => String(value).padStart(2, '0')
+  return hours > 0
+    ? `${pad(hours)}:${pad(minutes)}:${pad(rest)}`
+    : `${pad(minutes)}:${pad(rest)}`
+}
+
+/**
+ *  Parses a timestamp typed by the user
+ *
+ * @param {string} value: "SS", "MM:SS" or "HH:MM:SS", seconds may have decimals.
+ * @returns {number | null}: Seconds, or null if the format is wrong.
+ */
+export function parseTimestamp(value: string): number | null {
+  const parts = value.trim().split(':')
+  if (parts.length > 3 || parts.some((part) => !/^\d+(\.\d+)?$/.test(part))) {
+    return null
+  }
+  return parts.reduce((seconds, part) => seconds * 60 + Number(part), 0)
+}
+
+/**
+ *  Parses ranges to keep, e.g. "0:10-0:45, 1:20-2:00"
+ *
+ * @param {string} value: Comma separated START-END ranges. An empty START is the beginning of the video, an empty END the end of it.
+ * @returns {TrimRange[]}: The ranges. Throws an Error describing the first invalid range.
+ */
+export function parseTrimRanges(value: string): TrimRange[] {
+  const ranges = value
+    .split(',')
+    .filter((range) => range.trim().length > 0)
+    .map((range) => {
+      const [start, end, ...rest] = range.split('-')
+      const startSeconds = start.trim() ? parseTimestamp(start) : 0
+      const endSeconds = end?.trim() ? parseTimestamp(end) : null
+      if (
+        end === undefined ||
+        rest.length > 0 ||
+        startSeconds === null ||
+        (end.trim() && endSeconds === null)
+      ) {
+        throw new Error(`"${range.trim()}" is not a START-END range.`)
+      }
+      if (endSeconds !== null && endSeconds <= startSeconds) {
+        throw new Error(`"${range.trim()}" must end after it starts.`)
+      }
+      return { startSeconds, endSeconds }
+    })
+
+  ranges.forEach((range, index) => {
+    const previous = ranges[index - 1]
+    if (!previous) {
+      return
+    }
+    if (previous.endSeconds === null) {
+      throw new Error('Only the last range can run to the end of the video.')
+    }
+    if (range.startSeconds < previous.endSeconds) {
+      throw new Error('Ranges must be in order and must not overlap.')
+    }
+  })
+
+  return ranges
+}
+
+/**
+ *  Formats ranges the way parseTrimRanges reads them
+ *
+ * @param {TrimRange[]} ranges: Ranges to keep.
+ * @returns {string}: e.g. "0:10-0:45, 1:20-"
+ */
+export function formatTrimRanges(ranges: readonly TrimRange[]): string {
+  const format = (seconds: number) => {
+    const milliseconds = Math.round(seconds * 1000)
+    const whole = Math.floor(milliseconds / 1000)
+    const minutes = Math.floor(whole / 60)
+    const rest = String(whole % 60).padStart(2, '0')
+    const fraction = milliseconds % 1000
+    return fraction > 0
+      ? `${minutes}:${rest}.${String(fraction).padStart(3, '0')}`
+      : `${minutes}:${rest}`
+  }
+  return ranges
+    .map(
+      (range) =>
+        `${format(range.startSeconds)}-${
+          range.endSeconds === null ? '' : format(range.endSeconds)
+        }`,
+    )
+    .join(', ')
+}